@@ -0,0 +1,127 @@
+use std::io::{self, Read, Write};
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
+use super::CHUNK_ALIGN;
+
+/// Byte order of the numbers stored in a .DGC archive and in the files that
+/// it contains. The GameCube release is big endian.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endianness {
+    #[default]
+    Big,
+    Little,
+}
+
+impl Endianness {
+    /// Detect the byte order of an archive from the raw bytes of the chunk
+    /// size in its header. A valid chunk size is a non-zero multiple of 0x800.
+    /// If both byte orders give a valid chunk size, then the smaller one is
+    /// used. If neither does, then big endian is assumed.
+    /// Many big endian chunk sizes are also valid little endian ones, so use
+    /// `detect_archive` if the start of the archive's first chunk is known.
+    pub fn detect(chunk_size: [u8; 4]) -> Endianness {
+        let big = u32::from_be_bytes(chunk_size);
+        let little = u32::from_le_bytes(chunk_size);
+        if valid_chunk_size(little) && (!valid_chunk_size(big) || little < big) {
+            Endianness::Little
+        } else {
+            Endianness::Big
+        }
+    }
+
+    /// Detect the byte order of an archive from the raw bytes of the chunk
+    /// size in its header and the start of its first chunk. A byte order is
+    /// plausible if it gives a valid chunk size, and the first chunk's file
+    /// count and the sizes of its files fit into a chunk of that size. Only
+    /// the files whose sizes are within the given bytes are checked.
+    /// If both byte orders are plausible, then big endian is used. If neither
+    /// is, then the byte order is detected from the chunk size alone.
+    pub fn detect_archive(chunk_size: [u8; 4], first_chunk: &[u8]) -> Endianness {
+        let big = Endianness::Big.fits_chunk(chunk_size, first_chunk);
+        let little = Endianness::Little.fits_chunk(chunk_size, first_chunk);
+        match (big, little) {
+            (false, true) => Endianness::Little,
+            (false, false) => Endianness::detect(chunk_size),
+            _ => Endianness::Big,
+        }
+    }
+
+    /// Returns true if the start of a first chunk is consistent with the
+    /// given chunk size when both are read in this byte order.
+    fn fits_chunk(self, chunk_size: [u8; 4], chunk: &[u8]) -> bool {
+        let size = self.u32_from_bytes(chunk_size);
+        if !valid_chunk_size(size) {
+            return false;
+        }
+        let size = size as usize;
+        let read = |offset: usize| {
+            chunk.get(offset..offset + 4)
+                .map(|bytes| self.u32_from_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+        };
+        let num_files = match read(0) {
+            Some(num_files) => num_files,
+            None => return true,
+        };
+        if num_files.saturating_mul(16).saturating_add(4) > size {
+            return false;
+        }
+        let mut offset = 4;
+        for _ in 0..num_files {
+            match read(offset) {
+                Some(file_size) if file_size < 16 || file_size > size - offset => return false,
+                Some(file_size) => offset += file_size,
+                None => break,
+            }
+        }
+        true
+    }
+
+    /// The name of this byte order, for displaying to the user.
+    pub fn name(self) -> &'static str {
+        match self {
+            Endianness::Big => "big endian",
+            Endianness::Little => "little endian",
+        }
+    }
+
+    /// Decode a u32 from the given bytes.
+    pub fn u32_from_bytes(self, bytes: [u8; 4]) -> u32 {
+        match self {
+            Endianness::Big => u32::from_be_bytes(bytes),
+            Endianness::Little => u32::from_le_bytes(bytes),
+        }
+    }
+
+    pub fn read_u32<R: Read + ?Sized>(self, reader: &mut R) -> io::Result<u32> {
+        match self {
+            Endianness::Big => reader.read_u32::<BigEndian>(),
+            Endianness::Little => reader.read_u32::<LittleEndian>(),
+        }
+    }
+
+    pub fn read_i32<R: Read + ?Sized>(self, reader: &mut R) -> io::Result<i32> {
+        match self {
+            Endianness::Big => reader.read_i32::<BigEndian>(),
+            Endianness::Little => reader.read_i32::<LittleEndian>(),
+        }
+    }
+
+    pub fn write_u32<W: Write + ?Sized>(self, writer: &mut W, value: u32) -> io::Result<()> {
+        match self {
+            Endianness::Big => writer.write_u32::<BigEndian>(value),
+            Endianness::Little => writer.write_u32::<LittleEndian>(value),
+        }
+    }
+
+    pub fn write_i32<W: Write + ?Sized>(self, writer: &mut W, value: i32) -> io::Result<()> {
+        match self {
+            Endianness::Big => writer.write_i32::<BigEndian>(value),
+            Endianness::Little => writer.write_i32::<LittleEndian>(value),
+        }
+    }
+}
+
+/// Returns true if the given chunk size is a non-zero multiple of 0x800.
+fn valid_chunk_size(size: u32) -> bool {
+    size != 0 && (size as usize).is_multiple_of(CHUNK_ALIGN)
+}
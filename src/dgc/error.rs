@@ -0,0 +1,101 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// The kind of problem found in a malformed .DGC archive.
+#[derive(Debug)]
+pub enum DgcErrorKind {
+    /// An IO error occurred while reading the archive.
+    Io(io::Error),
+    /// The archive header ends before the chunk size.
+    TruncatedHeader,
+    /// The chunk size in the archive header is zero.
+    ZeroChunkSize,
+    /// The chunk size in the archive header is not a multiple of 0x800.
+    UnalignedChunkSize(usize),
+    /// The archive's data is not a whole number of chunks. Contains the size
+    /// of the last, partial chunk.
+    PartialChunk(usize),
+    /// A chunk ends in the middle of a chunk or file header.
+    TruncatedChunk,
+    /// A file's size is smaller than its own 16 byte header.
+    FileTooSmall(u32),
+    /// A file's data extends past the end of its chunk.
+    FileOutOfBounds {
+        size: usize,
+        available: usize,
+    },
+}
+
+impl DgcErrorKind {
+    /// Create an error of this kind, located at the given byte offset, and
+    /// optionally the given chunk and file index.
+    pub fn at(self, offset: u64, chunk: Option<usize>, file: Option<usize>) -> DgcError {
+        DgcError {
+            kind: self,
+            offset,
+            chunk,
+            file,
+        }
+    }
+}
+
+impl From<io::Error> for DgcErrorKind {
+    fn from(err: io::Error) -> DgcErrorKind {
+        DgcErrorKind::Io(err)
+    }
+}
+
+impl fmt::Display for DgcErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DgcErrorKind::Io(ref err) => write!(f, "{}", err),
+            DgcErrorKind::TruncatedHeader => write!(f, "archive header is truncated"),
+            DgcErrorKind::ZeroChunkSize => write!(f, "chunk size is zero"),
+            DgcErrorKind::UnalignedChunkSize(size) =>
+                write!(f, "chunk size 0x{:X} is not a multiple of 0x800", size),
+            DgcErrorKind::PartialChunk(size) =>
+                write!(f, "last chunk is only {} bytes, archive size is not a multiple of the chunk size", size),
+            DgcErrorKind::TruncatedChunk => write!(f, "chunk ends in the middle of a header"),
+            DgcErrorKind::FileTooSmall(size) =>
+                write!(f, "file size {} is smaller than its 16 byte header", size),
+            DgcErrorKind::FileOutOfBounds { size, available } =>
+                write!(f, "file data is {} bytes, but only {} bytes are left in its chunk", size, available),
+        }
+    }
+}
+
+/// Error produced when reading a malformed .DGC archive.
+/// Records where in the archive the problem was found.
+#[derive(Debug)]
+pub struct DgcError {
+    pub kind: DgcErrorKind,
+    /// Byte offset of the problem from the start of the archive.
+    pub offset: u64,
+    /// Index of the chunk that the problem was found in.
+    pub chunk: Option<usize>,
+    /// Index of the file within its chunk that the problem was found in.
+    pub file: Option<usize>,
+}
+
+impl fmt::Display for DgcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (offset 0x{:X}", self.kind, self.offset)?;
+        if let Some(chunk) = self.chunk {
+            write!(f, ", chunk {}", chunk)?;
+        }
+        if let Some(file) = self.file {
+            write!(f, ", file {}", file)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl Error for DgcError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self.kind {
+            DgcErrorKind::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
@@ -1,16 +1,121 @@
 use std::io::{self, Write, Read};
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use std::cmp;
 use std::mem;
+use error::{CResult, Error};
+
+pub mod endian;
+pub mod error;
+pub mod pack;
+pub mod reader;
+
+pub use self::endian::Endianness;
+pub use self::error::{DgcError, DgcErrorKind};
+pub use self::pack::{PackError, PackOptions, PackStrategy};
+pub use self::reader::{DgcChunkHeader, DgcFileHeader, DgcReader};
+
+/// Size of the .DGC header, which is followed by the archive's chunks.
+pub const HEADER_SIZE: usize = 0x800;
+
+/// Size of the legal notice at the start of the .DGC header.
+pub const LEGAL_NOTICE_SIZE: usize = 0x100;
+
+/// Size of the junk padding at the end of the .DGC header.
+pub const HEADER_PADDING_SIZE: usize = 0x6FC;
+
+/// Each chunk's size is a multiple of this many bytes.
+pub const CHUNK_ALIGN: usize = 0x800;
 
 /// .DGC header information
 /// Format:
 /// legal notice [u8; 0x100]
 /// chunk size   u32         (implied)
-/// junk padding [u8; 0x6FC] (ignored)
+/// junk padding [u8; 0x6FC]
 /// data         [u8; chunk size * N] (N is any whole number)
+#[derive(Clone)]
 pub struct DgcHeader {
-    pub legal_notice: [u8; 0x100],
+    pub legal_notice: [u8; LEGAL_NOTICE_SIZE],
+    /// Junk padding. This is kept so that archives can be written back
+    /// exactly as they were read. Zero-filled if shorter than 0x6FC bytes.
+    pub padding: Vec<u8>,
+}
+
+impl DgcHeader {
+    /// Get the legal notice without the NULs that pad it.
+    pub fn notice(&self) -> &[u8] {
+        let len = self.legal_notice.iter().rposition(|&b| b != 0).map_or(0, |pos| pos + 1);
+        &self.legal_notice[..len]
+    }
+
+    /// Set the legal notice, padding it with NULs. Fails if the notice is longer than
+    /// `LEGAL_NOTICE_SIZE` bytes.
+    pub fn set_notice(&mut self, notice: &[u8]) -> CResult<()> {
+        if notice.len() > LEGAL_NOTICE_SIZE {
+            return Err(Error::HeaderTooLong(notice.len()));
+        }
+        self.legal_notice = [0; LEGAL_NOTICE_SIZE];
+        self.legal_notice[..notice.len()].copy_from_slice(notice);
+        Ok(())
+    }
+
+    /// Write this header to the given writer, followed by the given chunk size.
+    pub fn write_to<W: Write>(&self, writer: &mut W, chunk_size: usize, endianness: Endianness)
+            -> io::Result<()> {
+        writer.write_all(&self.legal_notice)?;
+        endianness.write_u32(writer, chunk_size as u32)?;
+        write_padding(writer, &self.padding, HEADER_PADDING_SIZE)
+    }
+}
+
+/// The raw ids that identify a file in an archive.
+/// Files of different types or subtypes can share a name, so a name alone does not always
+/// identify a single file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FileKey {
+    pub type_id: i32,
+    pub id1: i32,
+    pub id2: i32,
+}
+
+impl FileKey {
+    /// Get the key of the same file with a different id1. A file without a subtype uses its new
+    /// id as its subtype.
+    pub fn with_id1(self, id1: i32) -> FileKey {
+        FileKey {
+            id1,
+            id2: if self.id2 == self.id1 { id1 } else { self.id2 },
+            ..self
+        }
+    }
+}
+
+/// Selects a file in an archive. An id selects the first file with that id1, and a key selects
+/// the file with exactly those ids.
+pub trait FileSelector {
+    /// Returns true if the given file is selected.
+    fn selects(&self, file: &DgcFile) -> bool;
+
+    /// The id1 of the selected file.
+    fn id(&self) -> i32;
+}
+
+impl FileSelector for i32 {
+    fn selects(&self, file: &DgcFile) -> bool {
+        file.id1 == *self
+    }
+
+    fn id(&self) -> i32 {
+        *self
+    }
+}
+
+impl FileSelector for FileKey {
+    fn selects(&self, file: &DgcFile) -> bool {
+        file.key() == *self
+    }
+
+    fn id(&self) -> i32 {
+        self.id1
+    }
 }
 
 /// .DGC file element
@@ -33,13 +138,22 @@ impl DgcFile {
         self.data.len() + 16
     }
 
+    /// Get the ids that identify this file.
+    pub fn key(&self) -> FileKey {
+        FileKey {
+            type_id: self.type_id,
+            id1: self.id1,
+            id2: self.id2,
+        }
+    }
+
     /// Write this file to the given writer.
     /// Returns the number of bytes that were written.
-    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
-        writer.write_u32::<BigEndian>(self.get_size() as u32)?;
-        writer.write_i32::<BigEndian>(self.type_id)?;
-        writer.write_i32::<BigEndian>(self.id1)?;
-        writer.write_i32::<BigEndian>(self.id2)?;
+    pub fn write_to<W: Write>(&self, writer: &mut W, endianness: Endianness) -> io::Result<usize> {
+        endianness.write_u32(writer, self.get_size() as u32)?;
+        endianness.write_i32(writer, self.type_id)?;
+        endianness.write_i32(writer, self.id1)?;
+        endianness.write_i32(writer, self.id2)?;
         writer.write_all(&self.data)?;
         Ok(self.get_size())
     }
@@ -49,15 +163,20 @@ impl DgcFile {
 /// Format:
 /// num files u32 (implied)
 /// data      [u8; chunk size] (inherited from header)
+#[derive(Default)]
 pub struct DgcChunk {
     pub data: Vec<DgcFile>,
+    /// Bytes that follow the last file in the chunk. This is kept so that
+    /// chunks can be written back exactly as they were read.
+    pub padding: Vec<u8>,
 }
 
 impl DgcChunk {
     /// Create a new DgcChunk
     pub fn new() -> DgcChunk {
         DgcChunk {
-            data: Vec::new()
+            data: Vec::new(),
+            padding: Vec::new(),
         }
     }
 
@@ -81,16 +200,17 @@ impl DgcChunk {
 
     /// Write this chunk to the given writer. Also expects a chunk size argument, that describes
     /// exactly how many bytes this chunk should write. If the chunk is too small to fill this
-    /// size, then the chunk will zero-pad the rest.
+    /// size, then the chunk will write its padding and zero-pad the rest.
     /// Returns the number of bytes that were written in total to the writer.
-    pub fn write_to<W: Write>(&self, writer: &mut W, chunk_size: usize) -> io::Result<usize> {
+    pub fn write_to<W: Write>(&self, writer: &mut W, chunk_size: usize, endianness: Endianness)
+            -> io::Result<usize> {
         let num_files = self.get_num_files() as u32;
-        writer.write_u32::<BigEndian>(num_files)?;
+        endianness.write_u32(writer, num_files)?;
         for file in &self.data {
-            file.write_to(writer)?;
+            file.write_to(writer, endianness)?;
         }
         let required_padding = chunk_size - self.get_size();
-        io::copy(&mut io::repeat(0u8).take(required_padding as u64), writer)?;
+        write_padding(writer, &self.padding, required_padding)?;
         Ok(self.get_size() + required_padding)
     }
 }
@@ -104,21 +224,28 @@ pub struct DgcArchive {
     pub header: DgcHeader,
     pub data: Vec<DgcChunk>,
     pub chunk_size: usize,
+    /// Byte order of the archive. New archives are big endian.
+    pub endianness: Endianness,
 }
 
 impl DgcArchive {
     /// Create a new DgcArchive. Expects a header and a base chunk size as arguments. The header
-    /// must be smaller than 256 bytes. The base chunk size will be automatically rounded up by
-    /// 0x800 bytes, and is may change if files are added to this archive.
+    /// is padded with NULs to 256 bytes, and truncated if it is longer; use
+    /// `DgcHeader::set_notice` to reject long headers instead. The base chunk size will be
+    /// automatically rounded up by 0x800 bytes, and is may change if files are added to this
+    /// archive.
     pub fn new(header: &str, chunk_size: usize) -> DgcArchive {
-        let mut headerdata = [0; 0x100];
-        headerdata.copy_from_slice(&header.as_bytes());
+        let mut headerdata = [0; LEGAL_NOTICE_SIZE];
+        let len = cmp::min(header.len(), LEGAL_NOTICE_SIZE);
+        headerdata[..len].copy_from_slice(&header.as_bytes()[..len]);
         DgcArchive {
             header: DgcHeader {
                 legal_notice: headerdata,
+                padding: Vec::new(),
             },
             data: vec![],
             chunk_size: calculate_chunk_size(chunk_size),
+            endianness: Endianness::default(),
         }
     }
 
@@ -129,29 +256,100 @@ impl DgcArchive {
 
     /// Set a new chunk size. Should be called if a new file is added that is larger than the chunk
     /// size.
+    /// Files are redistributed first fit, largest first; files of equal size keep their current
+    /// order.
     fn reevaluate_files(&mut self, new_size: usize) {
-        let mut old_chunks = Vec::new();
-        mem::swap(&mut self.data, &mut old_chunks);
-        let mut files: Vec<DgcFile> = old_chunks.into_iter().flat_map(|chunk| chunk.data.into_iter()).collect();
-        files.sort_unstable_by(|a, b| b.data.len().cmp(&a.data.len()));
-        self.data.push(DgcChunk::new());
+        let files = self.take_files();
         self.chunk_size = calculate_chunk_size(new_size);
-        while files.len() > 0 {
-            let mut chunk = DgcChunk::new();
-            let mut i = 0;
-            while i < files.len() {
-                if files[i].get_size() + chunk.get_size() <= self.chunk_size {
-                    chunk.add_file(files.remove(i));
-                } else {
-                    i += 1;
-                }
+        self.data = pack::pack_into(files, self.chunk_size, PackStrategy::FirstFitDecreasing);
+    }
+
+    /// Remove every file from this archive, in order.
+    fn take_files(&mut self) -> Vec<DgcFile> {
+        mem::take(&mut self.data).into_iter().flat_map(|chunk| chunk.data.into_iter()).collect()
+    }
+
+    /// Repack every file in this archive into new chunks, using the given options. This also
+    /// sets the archive's chunk size. Chunk padding is discarded.
+    /// On error, the archive is left unchanged.
+    pub fn pack(&mut self, options: &PackOptions) -> Result<(), PackError> {
+        let sizes: Vec<usize> = self.iter_files().map(DgcFile::get_size).collect();
+        let chunk_size = pack::choose_chunk_size(&sizes, options)?;
+        let files = self.take_files();
+        self.chunk_size = chunk_size;
+        self.data = pack::pack_into(files, chunk_size, options.strategy);
+        Ok(())
+    }
+
+    /// Find the chunk index and the index within that chunk of the selected file, which is
+    /// given by its id or its key.
+    pub fn position<S: FileSelector>(&self, file: S) -> Option<(usize, usize)> {
+        self.data.iter().enumerate().filter_map(|(i, chunk)| {
+            chunk.data.iter().position(|f| file.selects(f)).map(|j| (i, j))
+        }).next()
+    }
+
+    /// Get the selected file, which is given by its id or its key.
+    pub fn get_file<S: FileSelector>(&self, file: S) -> Option<&DgcFile> {
+        self.position(file).map(|(i, j)| &self.data[i].data[j])
+    }
+
+    /// Remove the selected file from this archive, and return it. The space that it used can be
+    /// reused by files that are added later. Chunks that become empty are removed.
+    pub fn remove_file<S: FileSelector>(&mut self, file: S) -> Option<DgcFile> {
+        let (i, j) = self.position(file)?;
+        Some(self.remove_at(i, j))
+    }
+
+    /// Remove the file at the given chunk index and index within that chunk.
+    fn remove_at(&mut self, i: usize, j: usize) -> DgcFile {
+        let file = self.data[i].data.remove(j);
+        if self.data[i].data.is_empty() {
+            self.data.remove(i);
+        } else {
+            // The chunk's original padding no longer matches its free space
+            self.data[i].padding.clear();
+        }
+        file
+    }
+
+    /// Replace the data of the selected file, and return the old data. The file stays in its
+    /// chunk if the new data fits, otherwise it is moved like a newly added file.
+    pub fn replace_data<S: FileSelector>(&mut self, file: S, data: Vec<u8>) -> Option<Vec<u8>> {
+        let (i, j) = self.position(file)?;
+        let old_len = self.data[i].data[j].data.len();
+        let fits = self.data[i].get_size() - old_len + data.len() <= self.chunk_size;
+        let old_data = if fits {
+            if old_len != data.len() {
+                self.data[i].padding.clear();
             }
-            self.data.push(chunk);
+            mem::replace(&mut self.data[i].data[j].data, data)
+        } else {
+            let mut file = self.remove_at(i, j);
+            let old_data = mem::replace(&mut file.data, data);
+            self.add_file(file);
+            old_data
+        };
+        Some(old_data)
+    }
+
+    /// Change the ids of the selected file. Returns false if there is no such file.
+    pub fn set_file_ids<S: FileSelector>(&mut self, file: S, type_id: i32, id1: i32, id2: i32) -> bool {
+        match self.position(file) {
+            Some((i, j)) => {
+                let file = &mut self.data[i].data[j];
+                file.type_id = type_id;
+                file.id1 = id1;
+                file.id2 = id2;
+                true
+            },
+            None => false,
         }
     }
 
-    /// Add a file to this archive. Will be automatically put into a chunk. This function may
-    /// re-distribute files to chunks if the given file is too big to fit into any chunk.
+    /// Add a file to this archive. Will be automatically put into the first chunk that it fits
+    /// in. This function may re-distribute files to chunks if the given file is too big to fit
+    /// into any chunk. Adding the same files in the same order always gives the same layout.
     pub fn add_file(&mut self, file: DgcFile) {
         // Make sure that the file's size + chunk header size is less than the chunk size
         if file.get_size() + 4 > self.chunk_size {
@@ -171,70 +369,152 @@ impl DgcArchive {
 
     /// Write this archive to a writer.
     pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
-        writer.write_all(&self.header.legal_notice)?;
-        writer.write_u32::<BigEndian>(self.chunk_size as u32)?;
-        io::copy(&mut io::repeat(0u8).take(0x6FC), writer)?;
+        self.header.write_to(writer, self.chunk_size, self.endianness)?;
         for chunk in &self.data {
-            chunk.write_to(writer, self.chunk_size)?;
+            chunk.write_to(writer, self.chunk_size, self.endianness)?;
         }
         Ok(())
     }
 
     /// Create an archive from a reader.
-    pub fn read_from<R: Read>(file: &mut R) -> io::Result<DgcArchive> {
-        let mut legal_notice: [u8; 0x100] = [0; 0x100];
-        file.read_exact(&mut legal_notice)?;
-        let size = file.read_u32::<BigEndian>()?;
-        io::copy(&mut file.take(0x6FC), &mut io::sink())?;
-        let mut fdata = Vec::new();
+    /// The archive is read one chunk at a time. Use a DgcReader instead if
+    /// not every file needs to be loaded.
+    /// The archive's byte order is detected from its chunk size.
+    pub fn read_from<R: Read>(file: &mut R) -> Result<DgcArchive, DgcError> {
+        let (header, size, endianness, probe) = read_header(file)?;
+        // The start of the first chunk was already read to detect the byte order
+        let mut file = io::Cursor::new(probe).chain(file);
         let mut chunks = Vec::new();
-        file.read_to_end(&mut fdata)?;
-        if fdata.len() % (size as usize) > 0 {
-            println!("Warning: stream size {} is not divisible by chunk size {}!", fdata.len(), size);
-        }
-        for chunk in fdata.chunks(size as usize) {
-            chunks.push(load_chunk(chunk)?);
+        // Chunks are read as they arrive, rather than allocating the chunk size up front, so a
+        // chunk size larger than the archive is reported as a partial chunk
+        let mut buffer = Vec::new();
+        loop {
+            let index = chunks.len();
+            let offset = (HEADER_SIZE + index * size) as u64;
+            buffer.clear();
+            file.by_ref().take(size as u64).read_to_end(&mut buffer)
+                .map_err(|err| DgcErrorKind::from(err).at(offset, Some(index), None))?;
+            if buffer.is_empty() {
+                break;
+            }
+            if buffer.len() < size {
+                return Err(DgcErrorKind::PartialChunk(buffer.len()).at(offset, Some(index), None));
+            }
+            chunks.push(load_chunk(&buffer, index, offset, endianness)?);
         }
         Ok(DgcArchive {
-            header: DgcHeader {
-                legal_notice: legal_notice,
-            },
+            header,
             data: chunks,
-            chunk_size: size as usize,
+            chunk_size: size,
+            endianness,
         })
     }
 }
 
 /// Calculate the size that a chunk would have to be in order to store a file of the given size.
 fn calculate_chunk_size(max_size: usize) -> usize {
-    // Each chunk's size is a multiple of 0x800 bytes
-    const CHUNK_MULT: usize = 0x800;
     if max_size == 0 {
         // avoid subtract with overflow error
-        return CHUNK_MULT;
+        return CHUNK_ALIGN;
     }
-    cmp::max(1, 1 + ((max_size - 1) / CHUNK_MULT)) * CHUNK_MULT
+    cmp::max(1, 1 + ((max_size - 1) / CHUNK_ALIGN)) * CHUNK_ALIGN
 }
 
-/// Load a chunk from the given chunk data.
-fn load_chunk(mut data: &[u8]) -> io::Result<DgcChunk> {
-    let num_files = data.read_u32::<BigEndian>()?;
+/// Read the archive header, which is located at the start of the archive, along with up to
+/// `CHUNK_ALIGN` bytes of the first chunk, which are used to detect the archive's byte order.
+/// Returns the header, the archive's chunk size, its byte order and the bytes of the first chunk
+/// that were read.
+fn read_header<R: Read>(reader: &mut R)
+        -> Result<(DgcHeader, usize, Endianness, Vec<u8>), DgcError> {
+    let truncated = |err: io::Error| {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => DgcErrorKind::TruncatedHeader,
+            _ => DgcErrorKind::Io(err),
+        }.at(0, None, None)
+    };
+    let mut legal_notice = [0; LEGAL_NOTICE_SIZE];
+    reader.read_exact(&mut legal_notice).map_err(truncated)?;
+    let mut size = [0; 4];
+    reader.read_exact(&mut size).map_err(truncated)?;
+    let mut padding = Vec::with_capacity(HEADER_PADDING_SIZE);
+    reader.take(HEADER_PADDING_SIZE as u64).read_to_end(&mut padding).map_err(truncated)?;
+    if padding.len() < HEADER_PADDING_SIZE {
+        return Err(DgcErrorKind::TruncatedHeader.at(0x104 + padding.len() as u64, None, None));
+    }
+    let mut probe = Vec::with_capacity(CHUNK_ALIGN);
+    reader.take(CHUNK_ALIGN as u64).read_to_end(&mut probe)
+        .map_err(|err| DgcErrorKind::from(err).at(HEADER_SIZE as u64, Some(0), None))?;
+    let endianness = Endianness::detect_archive(size, &probe);
+    let size = endianness.u32_from_bytes(size);
+    if size == 0 {
+        return Err(DgcErrorKind::ZeroChunkSize.at(0x100, None, None));
+    }
+    if !(size as usize).is_multiple_of(CHUNK_ALIGN) {
+        return Err(DgcErrorKind::UnalignedChunkSize(size as usize).at(0x100, None, None));
+    }
+    Ok((DgcHeader {
+        legal_notice,
+        padding,
+    }, size as usize, endianness, probe))
+}
+
+/// Read a file's header.
+/// Returns the size of the file's data along with its type id, id1 and id2.
+fn read_file_header<R: Read>(reader: &mut R, endianness: Endianness)
+        -> Result<(usize, i32, i32, i32), DgcErrorKind> {
+    let file_size = endianness.read_u32(reader)?;
+    let id_type = endianness.read_i32(reader)?;
+    let id1 = endianness.read_i32(reader)?;
+    let id2 = endianness.read_i32(reader)?;
+    let data_size = (file_size as usize).checked_sub(16)
+        .ok_or(DgcErrorKind::FileTooSmall(file_size))?;
+    Ok((data_size, id_type, id1, id2))
+}
+
+/// Load a chunk from the given chunk data. Also expects the chunk's index and
+/// its offset in the archive, which are used for error reporting.
+fn load_chunk(chunk_data: &[u8], chunk: usize, chunk_offset: u64, endianness: Endianness)
+        -> Result<DgcChunk, DgcError> {
+    let mut data = chunk_data;
+    if data.len() < 4 {
+        return Err(DgcErrorKind::TruncatedChunk.at(chunk_offset, Some(chunk), None));
+    }
+    let num_files = endianness.read_u32(&mut data)
+        .map_err(|err| DgcErrorKind::from(err).at(chunk_offset, Some(chunk), None))?;
     let mut files = Vec::new();
-    for _ in 0..num_files {
-        let file_size = data.read_u32::<BigEndian>()?;
-        let id_type = data.read_i32::<BigEndian>()?;
-        let id1 = data.read_i32::<BigEndian>()?;
-        let id2 = data.read_i32::<BigEndian>()?;
-        let mut contents: Vec<u8> = vec![0; file_size as usize - 16];
-        data.read_exact(&mut contents)?;
+    for i in 0..num_files as usize {
+        let file_offset = chunk_offset + (chunk_data.len() - data.len()) as u64;
+        let locate = |kind: DgcErrorKind| kind.at(file_offset, Some(chunk), Some(i));
+        if data.len() < 16 {
+            return Err(locate(DgcErrorKind::TruncatedChunk));
+        }
+        let (data_size, id_type, id1, id2) = read_file_header(&mut data, endianness).map_err(locate)?;
+        if data_size > data.len() {
+            return Err(locate(DgcErrorKind::FileOutOfBounds {
+                size: data_size,
+                available: data.len(),
+            }));
+        }
+        let (contents, rest) = data.split_at(data_size);
+        data = rest;
         files.push(DgcFile {
-            data: contents,
+            data: contents.to_vec(),
             type_id: id_type,
-            id1: id1,
-            id2: id2,
+            id1,
+            id2,
         });
     }
     Ok(DgcChunk {
         data: files,
+        padding: data.to_vec(),
     })
 }
+
+/// Write exactly `size` bytes of padding. The given padding data is written
+/// first, and the rest is filled with zeros.
+fn write_padding<W: Write>(writer: &mut W, padding: &[u8], size: usize) -> io::Result<()> {
+    let len = cmp::min(size, padding.len());
+    writer.write_all(&padding[..len])?;
+    io::copy(&mut io::repeat(0u8).take((size - len) as u64), writer)?;
+    Ok(())
+}
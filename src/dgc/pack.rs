@@ -0,0 +1,210 @@
+use std::cmp;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use super::{calculate_chunk_size, DgcChunk, DgcFile, CHUNK_ALIGN};
+
+/// How files are assigned to chunks when an archive is packed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PackStrategy {
+    /// Sort files from largest to smallest, then put each file into the
+    /// first chunk that it fits in.
+    #[default]
+    FirstFitDecreasing,
+    /// Sort files from largest to smallest, then put each file into the
+    /// fullest chunk that it fits in.
+    BestFit,
+    /// Keep files in their current order. A new chunk is started whenever the
+    /// next file does not fit into the last chunk.
+    PreserveOrder,
+}
+
+impl PackStrategy {
+    /// Every strategy, in the order that they should be presented to the user.
+    pub const ALL: [PackStrategy; 3] = [
+        PackStrategy::FirstFitDecreasing,
+        PackStrategy::BestFit,
+        PackStrategy::PreserveOrder,
+    ];
+
+    /// The name of this strategy, as accepted by `from_str`.
+    pub fn name(self) -> &'static str {
+        match self {
+            PackStrategy::FirstFitDecreasing => "first-fit-decreasing",
+            PackStrategy::BestFit => "best-fit",
+            PackStrategy::PreserveOrder => "preserve-order",
+        }
+    }
+
+    /// A short, human readable description of this strategy.
+    pub fn label(self) -> &'static str {
+        match self {
+            PackStrategy::FirstFitDecreasing => "First fit, largest files first",
+            PackStrategy::BestFit => "Best fit, largest files first",
+            PackStrategy::PreserveOrder => "Preserve file order",
+        }
+    }
+}
+
+impl FromStr for PackStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<PackStrategy, String> {
+        PackStrategy::ALL.iter()
+            .find(|strategy| strategy.name() == s)
+            .cloned()
+            .ok_or_else(|| format!("Unknown packing strategy '{}'", s))
+    }
+}
+
+/// Options for packing an archive's files into chunks.
+/// If neither a chunk size nor a maximum chunk count is given, then the
+/// smallest chunk size that fits the largest file is used.
+#[derive(Clone, Debug, Default)]
+pub struct PackOptions {
+    pub strategy: PackStrategy,
+    /// Use exactly this chunk size. Must be a multiple of 0x800.
+    pub chunk_size: Option<usize>,
+    /// Use at most this many chunks. If no chunk size is given, then the
+    /// smallest chunk size that needs no more than this many chunks is used.
+    pub max_chunks: Option<usize>,
+}
+
+/// An error that occurred while packing an archive.
+#[derive(Debug)]
+pub enum PackError {
+    /// The requested chunk size is not a non-zero multiple of 0x800.
+    UnalignedChunkSize(usize),
+    /// A file does not fit into the requested chunk size.
+    FileTooLarge {
+        size: usize,
+        chunk_size: usize,
+    },
+    /// The files need more chunks than the requested maximum.
+    TooManyChunks {
+        chunks: usize,
+        max_chunks: usize,
+    },
+}
+
+impl fmt::Display for PackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PackError::UnalignedChunkSize(size) =>
+                write!(f, "Chunk size 0x{:X} is not a multiple of 0x{:X}", size, CHUNK_ALIGN),
+            PackError::FileTooLarge { size, chunk_size } =>
+                write!(f, "A file of {} bytes does not fit into chunks of 0x{:X} bytes",
+                       size, chunk_size),
+            PackError::TooManyChunks { chunks, max_chunks } =>
+                write!(f, "Packing needs {} chunks, but at most {} are allowed",
+                       chunks, max_chunks),
+        }
+    }
+}
+
+impl Error for PackError {}
+
+/// Choose a chunk size for files of the given sizes, including their headers.
+pub fn choose_chunk_size(sizes: &[usize], options: &PackOptions) -> Result<usize, PackError> {
+    // Each chunk needs room for its 4 byte header and its largest file
+    let largest = sizes.iter().map(|size| size + 4).max().unwrap_or(0);
+    let chunk_size = match (options.chunk_size, options.max_chunks) {
+        (Some(chunk_size), _) => {
+            if chunk_size == 0 || chunk_size % CHUNK_ALIGN != 0 {
+                return Err(PackError::UnalignedChunkSize(chunk_size));
+            }
+            if chunk_size < largest {
+                return Err(PackError::FileTooLarge {
+                    size: largest - 4,
+                    chunk_size,
+                });
+            }
+            chunk_size
+        },
+        (None, Some(max_chunks)) => {
+            // Each chunk holds at most its size minus its header, so start
+            // from the smallest size that could possibly work.
+            let total: usize = sizes.iter().sum();
+            let per_chunk = total / cmp::max(max_chunks, 1) + 4;
+            let mut chunk_size = cmp::max(calculate_chunk_size(largest),
+                                          calculate_chunk_size(per_chunk));
+            while chunk_size < total + 4
+                    && assign(sizes, chunk_size, options.strategy).num_chunks > max_chunks {
+                chunk_size += CHUNK_ALIGN;
+            }
+            chunk_size
+        },
+        (None, None) => calculate_chunk_size(largest),
+    };
+    if let Some(max_chunks) = options.max_chunks {
+        let chunks = assign(sizes, chunk_size, options.strategy).num_chunks;
+        if chunks > max_chunks {
+            return Err(PackError::TooManyChunks {
+                chunks,
+                max_chunks,
+            });
+        }
+    }
+    Ok(chunk_size)
+}
+
+/// Pack the given files into chunks of the given size.
+/// Every file must fit into an empty chunk.
+pub fn pack_into(files: Vec<DgcFile>, chunk_size: usize, strategy: PackStrategy) -> Vec<DgcChunk> {
+    let sizes: Vec<usize> = files.iter().map(DgcFile::get_size).collect();
+    let assignment = assign(&sizes, chunk_size, strategy);
+    let mut chunks: Vec<DgcChunk> = Vec::new();
+    chunks.resize_with(assignment.num_chunks, DgcChunk::new);
+    let mut files: Vec<Option<DgcFile>> = files.into_iter().map(Some).collect();
+    for (index, chunk) in assignment.order {
+        chunks[chunk].add_file(files[index].take().unwrap());
+    }
+    chunks
+}
+
+/// The result of assigning files to chunks.
+struct Assignment {
+    /// Pairs of file index and chunk index, in the order that files are added
+    order: Vec<(usize, usize)>,
+    num_chunks: usize,
+}
+
+/// Assign files with the given sizes to chunks. Only the sizes are needed, so
+/// that different chunk sizes can be tried without moving any file data.
+fn assign(sizes: &[usize], chunk_size: usize, strategy: PackStrategy) -> Assignment {
+    let mut indices: Vec<usize> = (0..sizes.len()).collect();
+    if strategy != PackStrategy::PreserveOrder {
+        // Stable sort, so that files of equal size keep their current order
+        indices.sort_by_key(|&i| cmp::Reverse(sizes[i]));
+    }
+    // Free space left in each chunk
+    let mut free: Vec<usize> = Vec::new();
+    let mut order = Vec::with_capacity(sizes.len());
+    for i in indices {
+        let size = sizes[i];
+        let found = match strategy {
+            PackStrategy::FirstFitDecreasing => free.iter().position(|&space| space >= size),
+            PackStrategy::BestFit => free.iter()
+                .enumerate()
+                .filter(|&(_, &space)| space >= size)
+                .min_by_key(|&(_, &space)| space)
+                .map(|(chunk, _)| chunk),
+            PackStrategy::PreserveOrder => free.last()
+                .filter(|&&space| space >= size)
+                .map(|_| free.len() - 1),
+        };
+        let chunk = match found {
+            Some(chunk) => chunk,
+            None => {
+                free.push(chunk_size - 4);
+                free.len() - 1
+            }
+        };
+        free[chunk] -= size;
+        order.push((i, chunk));
+    }
+    Assignment {
+        order,
+        num_chunks: free.len(),
+    }
+}
@@ -0,0 +1,209 @@
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use super::{read_header, read_file_header, DgcArchive, DgcChunk, DgcError, DgcErrorKind, DgcFile,
+            DgcHeader, Endianness, FileKey, HEADER_SIZE};
+
+/// Header information of a file stored in a .DGC archive.
+/// Only the location of the file's data is stored, the data itself is only
+/// loaded when it is requested from a DgcReader.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DgcFileHeader {
+    pub type_id: i32,
+    pub id1: i32,
+    pub id2: i32,
+    /// Offset of the file's data from the start of the archive.
+    pub offset: u64,
+    /// Size of the file's data, not including its header.
+    pub data_size: usize,
+}
+
+impl DgcFileHeader {
+    /// Get the total size of this file, including its header information.
+    pub fn get_size(&self) -> usize {
+        self.data_size + 16
+    }
+
+    /// Get the ids that identify this file.
+    pub fn key(&self) -> FileKey {
+        FileKey {
+            type_id: self.type_id,
+            id1: self.id1,
+            id2: self.id2,
+        }
+    }
+}
+
+/// Header information of a .DGC chunk.
+/// Contains the headers of every file stored in the chunk.
+#[derive(Clone, Debug, Default)]
+pub struct DgcChunkHeader {
+    pub files: Vec<DgcFileHeader>,
+}
+
+impl DgcChunkHeader {
+    /// Get the total size of this chunk, not including padding.
+    pub fn get_size(&self) -> usize {
+        self.files.iter().fold(4, |acc, f| acc + f.get_size())
+    }
+
+    /// Get the number of files stored within this chunk.
+    pub fn get_num_files(&self) -> usize {
+        self.files.len()
+    }
+}
+
+/// Random access .DGC reader.
+/// Only the archive's header and the headers of each chunk and file are read
+/// when the reader is created. File data is read on demand, so listing the
+/// contents of an archive or reading a single file does not require loading
+/// the entire archive into memory.
+pub struct DgcReader<R> {
+    reader: R,
+    pub header: DgcHeader,
+    pub chunk_size: usize,
+    pub endianness: Endianness,
+    pub chunks: Vec<DgcChunkHeader>,
+}
+
+impl<R: Read + Seek> DgcReader<R> {
+    /// Create a reader by indexing the chunks and files of the given archive.
+    pub fn new(mut reader: R) -> Result<DgcReader<R>, DgcError> {
+        reader.seek(SeekFrom::Start(0))
+            .map_err(|err| DgcErrorKind::from(err).at(0, None, None))?;
+        let (header, chunk_size, endianness, _) = read_header(&mut reader)?;
+        let end = reader.seek(SeekFrom::End(0))
+            .map_err(|err| DgcErrorKind::from(err).at(HEADER_SIZE as u64, None, None))?;
+        let data_size = end - HEADER_SIZE as u64;
+        let num_chunks = (data_size / chunk_size as u64) as usize;
+        let remainder = (data_size % chunk_size as u64) as usize;
+        if remainder > 0 {
+            let offset = end - remainder as u64;
+            return Err(DgcErrorKind::PartialChunk(remainder).at(offset, Some(num_chunks), None));
+        }
+        let mut chunks = Vec::with_capacity(num_chunks);
+        for i in 0..num_chunks {
+            chunks.push(index_chunk(&mut reader, i, chunk_size, endianness)?);
+        }
+        Ok(DgcReader {
+            reader,
+            header,
+            chunk_size,
+            endianness,
+            chunks,
+        })
+    }
+
+    /// Iterate over the headers of all files in this archive.
+    pub fn iter_files(&self) -> impl Iterator<Item=&DgcFileHeader> {
+        self.chunks.iter().flat_map(|chunk| chunk.files.iter())
+    }
+
+    /// Read the data of the given file.
+    pub fn read_data(&mut self, file: &DgcFileHeader) -> Result<Vec<u8>, DgcError> {
+        let mut data = vec![0; file.data_size];
+        self.reader.seek(SeekFrom::Start(file.offset))
+            .and_then(|_| self.reader.read_exact(&mut data))
+            .map_err(|err| DgcErrorKind::from(err).at(file.offset, None, None))?;
+        Ok(data)
+    }
+
+    /// Read the given file, including its data.
+    pub fn read_file(&mut self, file: &DgcFileHeader) -> Result<DgcFile, DgcError> {
+        Ok(DgcFile {
+            data: self.read_data(file)?,
+            type_id: file.type_id,
+            id1: file.id1,
+            id2: file.id2,
+        })
+    }
+
+    /// Read the padding that follows the last file of the given chunk.
+    pub fn read_padding(&mut self, index: usize, chunk: &DgcChunkHeader) -> Result<Vec<u8>, DgcError> {
+        let offset = (HEADER_SIZE + index * self.chunk_size + chunk.get_size()) as u64;
+        let mut data = vec![0; self.chunk_size - chunk.get_size()];
+        self.reader.seek(SeekFrom::Start(offset))
+            .and_then(|_| self.reader.read_exact(&mut data))
+            .map_err(|err| DgcErrorKind::from(err).at(offset, Some(index), None))?;
+        Ok(data)
+    }
+
+    /// Load every file in this archive into a DgcArchive.
+    pub fn into_archive(mut self) -> Result<DgcArchive, DgcError> {
+        self.read_archive()
+    }
+
+    /// Read every file in this archive into a DgcArchive, keeping the reader.
+    pub fn read_archive(&mut self) -> Result<DgcArchive, DgcError> {
+        let chunk_headers = self.chunks.clone();
+        let mut chunks = Vec::with_capacity(chunk_headers.len());
+        for (i, chunk) in chunk_headers.iter().enumerate() {
+            let mut files = Vec::with_capacity(chunk.files.len());
+            for file in &chunk.files {
+                files.push(self.read_file(file)?);
+            }
+            chunks.push(DgcChunk {
+                data: files,
+                padding: self.read_padding(i, chunk)?,
+            });
+        }
+        Ok(DgcArchive {
+            header: self.header.clone(),
+            data: chunks,
+            chunk_size: self.chunk_size,
+            endianness: self.endianness,
+        })
+    }
+}
+
+impl<'a> DgcReader<Cursor<&'a [u8]>> {
+    /// Borrow the data of the given file directly from the underlying buffer,
+    /// e.g. a memory mapped archive, without copying it.
+    pub fn get_data(&self, file: &DgcFileHeader) -> &'a [u8] {
+        let data: &'a [u8] = self.reader.get_ref();
+        let start = file.offset as usize;
+        &data[start..start + file.data_size]
+    }
+}
+
+/// Index the chunk with the given index.
+fn index_chunk<R: Read + Seek>(reader: &mut R, chunk: usize, chunk_size: usize,
+                               endianness: Endianness) -> Result<DgcChunkHeader, DgcError> {
+    let chunk_start = (HEADER_SIZE + chunk * chunk_size) as u64;
+    let chunk_end = chunk_start + chunk_size as u64;
+    if chunk_size < 4 {
+        return Err(DgcErrorKind::TruncatedChunk.at(chunk_start, Some(chunk), None));
+    }
+    let num_files = reader.seek(SeekFrom::Start(chunk_start))
+        .and_then(|_| endianness.read_u32(reader))
+        .map_err(|err| DgcErrorKind::from(err).at(chunk_start, Some(chunk), None))?;
+    let mut files = Vec::new();
+    let mut file_offset = chunk_start + 4;
+    for i in 0..num_files as usize {
+        let locate = |kind: DgcErrorKind| kind.at(file_offset, Some(chunk), Some(i));
+        if file_offset + 16 > chunk_end {
+            return Err(locate(DgcErrorKind::TruncatedChunk));
+        }
+        let (data_size, type_id, id1, id2) = reader.seek(SeekFrom::Start(file_offset))
+            .map_err(DgcErrorKind::from)
+            .and_then(|_| read_file_header(reader, endianness))
+            .map_err(locate)?;
+        let offset = file_offset + 16;
+        let available = (chunk_end - offset) as usize;
+        if data_size > available {
+            return Err(locate(DgcErrorKind::FileOutOfBounds {
+                size: data_size,
+                available,
+            }));
+        }
+        file_offset = offset + data_size as u64;
+        files.push(DgcFileHeader {
+            type_id,
+            id1,
+            id2,
+            offset,
+            data_size,
+        });
+    }
+    Ok(DgcChunkHeader {
+        files,
+    })
+}
@@ -0,0 +1,299 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use dgc::{DgcError, PackError, CHUNK_ALIGN, LEGAL_NOTICE_SIZE};
+use extract::MergeConflict;
+use ngc::{NameCollisionError, NameDiagnostic, NgcError};
+use serde_json;
+
+/// A Result type for any error produced by this crate.
+pub type CResult<T> = Result<T, Error>;
+
+/// Any error produced by this crate.
+/// Errors that happen while working with a file record its path, and codec
+/// errors record the entry that failed, so that the cause of an error can be
+/// shown to the user. The underlying error is available through `source`.
+#[derive(Debug)]
+pub enum Error {
+    /// An IO error occurred, on the given file if it is known.
+    Io {
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+    /// A .DGC archive is malformed.
+    Dgc {
+        path: Option<PathBuf>,
+        source: DgcError,
+    },
+    /// A .NGC archive is malformed.
+    Ngc {
+        path: Option<PathBuf>,
+        source: NgcError,
+    },
+    /// An archive's files could not be packed into chunks.
+    Pack(PackError),
+    /// A name has the same id as a different name that is already in use.
+    NameCollision(NameDiagnostic),
+    /// Writing an archive was refused, since distinct names in it share an id.
+    Collisions(NameCollisionError),
+    /// A codec plugin failed to import or export an entry.
+    Codec {
+        /// Name of the entry that failed.
+        entry: String,
+        /// Type of the entry, which selects the codec.
+        type_name: String,
+        source: Box<Error>,
+    },
+    /// The manifest of an extracted archive is invalid.
+    Manifest {
+        path: Option<PathBuf>,
+        source: ManifestError,
+    },
+    /// A file with the given name already exists in the archive.
+    FileExists(String),
+    /// Files were changed both in an extracted folder and in the archive that is merged into it.
+    MergeConflicts(Vec<MergeConflict>),
+    /// There is no file with the given id in the archive.
+    FileNotFound(i32),
+    /// A .DGC header is longer than the space for it. Contains the header's size.
+    HeaderTooLong(usize),
+    /// Input given by the user is invalid, e.g. a command line argument.
+    InvalidInput(String),
+    /// The GUI toolkit failed. Only produced when the `gui` feature is enabled, but always
+    /// present so that the shape of this enum does not depend on the crate's features.
+    Gui(String),
+}
+
+/// A problem with the manifest of an extracted archive.
+#[derive(Debug)]
+pub enum ManifestError {
+    /// The manifest is not valid JSON, or does not have the expected fields.
+    Json(serde_json::Error),
+    /// Padding data is not a valid hex string.
+    InvalidHex(String),
+    /// Padding data is larger than the size of the padding.
+    PaddingTooLarge {
+        size: usize,
+        data_size: usize,
+    },
+    /// The chunk size is zero, or not a multiple of 0x800.
+    InvalidChunkSize(usize),
+    /// The file name of an entry is absolute, or leads out of the extracted folder.
+    OutsideFolder(String),
+}
+
+impl Error {
+    /// Record the path of the file that this error happened on, unless it
+    /// already has one. Errors that are not about a file are unchanged.
+    pub fn with_path(self, path: &Path) -> Error {
+        match self {
+            Error::Io { path: None, source } => Error::Io {
+                path: Some(path.to_owned()),
+                source,
+            },
+            Error::Dgc { path: None, source } => Error::Dgc {
+                path: Some(path.to_owned()),
+                source,
+            },
+            Error::Ngc { path: None, source } => Error::Ngc {
+                path: Some(path.to_owned()),
+                source,
+            },
+            Error::Manifest { path: None, source } => Error::Manifest {
+                path: Some(path.to_owned()),
+                source,
+            },
+            other => other,
+        }
+    }
+
+    /// Wrap an error from a codec plugin, recording the entry that failed.
+    pub fn codec<E: Into<Error>>(entry: &str, type_name: &str, err: E) -> Error {
+        Error::Codec {
+            entry: entry.to_owned(),
+            type_name: type_name.to_owned(),
+            source: Box::new(err.into()),
+        }
+    }
+
+    /// Get the path of the file that this error happened on, if it is known.
+    pub fn path(&self) -> Option<&Path> {
+        match *self {
+            Error::Io { ref path, .. } | Error::Dgc { ref path, .. }
+            | Error::Ngc { ref path, .. } | Error::Manifest { ref path, .. } => path.as_deref(),
+            Error::Codec { ref source, .. } => source.path(),
+            _ => None,
+        }
+    }
+
+    /// Get the name of the entry that this error happened on, if it is known.
+    pub fn entry(&self) -> Option<&str> {
+        match *self {
+            Error::Codec { ref entry, .. } => Some(entry),
+            Error::FileExists(ref name) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Describe this error and every error that caused it, one per line.
+    pub fn chain(&self) -> String {
+        let mut text = self.to_string();
+        let mut source = error::Error::source(self);
+        while let Some(err) = source {
+            text.push_str(&format!("\nCaused by: {}", err));
+            source = err.source();
+        }
+        text
+    }
+}
+
+/// Attach the path of the file that an operation was working on to its error.
+pub trait PathContext<T> {
+    fn at_path<P: AsRef<Path>>(self, path: P) -> CResult<T>;
+}
+
+impl<T, E: Into<Error>> PathContext<T> for Result<T, E> {
+    fn at_path<P: AsRef<Path>>(self, path: P) -> CResult<T> {
+        self.map_err(|err| err.into().with_path(path.as_ref()))
+    }
+}
+
+/// Format an optional path for an error message.
+fn describe_path(path: &Option<PathBuf>) -> String {
+    match *path {
+        Some(ref path) => format!(" {}", path.display()),
+        None => String::new(),
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io { ref path, .. } => match *path {
+                Some(ref path) => write!(f, "Could not access {}", path.display()),
+                None => write!(f, "IO error"),
+            },
+            Error::Dgc { ref path, .. } =>
+                write!(f, "Malformed .DGC archive{}", describe_path(path)),
+            Error::Ngc { ref path, .. } =>
+                write!(f, "Malformed .NGC archive{}", describe_path(path)),
+            Error::Pack(_) => write!(f, "Could not pack the archive"),
+            Error::NameCollision(ref diagnostic) => write!(f, "Name collision: {}", diagnostic),
+            Error::Collisions(ref err) => write!(f, "{}", err),
+            Error::Codec { ref entry, ref type_name, .. } =>
+                write!(f, "Could not convert {} (type {})", entry, type_name),
+            Error::Manifest { ref path, .. } =>
+                write!(f, "Invalid manifest{}", describe_path(path)),
+            Error::FileExists(ref name) => write!(f, "A file named {} already exists", name),
+            Error::MergeConflicts(ref conflicts) => {
+                let names: Vec<String> = conflicts.iter().map(|c| c.to_string()).collect();
+                write!(f, "{} files were changed both in the folder and in the archive: {}",
+                       conflicts.len(), names.join(", "))
+            },
+            Error::FileNotFound(id) => write!(f, "There is no file with id {:08X}", id as u32),
+            Error::HeaderTooLong(size) =>
+                write!(f, "Header is {} bytes, but at most {} bytes fit in an archive", size, LEGAL_NOTICE_SIZE),
+            Error::InvalidInput(ref msg) => write!(f, "{}", msg),
+            Error::Gui(ref msg) => write!(f, "GUI error: {}", msg),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io { ref source, .. } => Some(source),
+            Error::Dgc { ref source, .. } => Some(source),
+            Error::Ngc { ref source, .. } => Some(source),
+            Error::Pack(ref err) => Some(err),
+            Error::Codec { ref source, .. } => Some(source.as_ref()),
+            Error::Manifest { ref source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ManifestError::Json(ref err) => write!(f, "{}", err),
+            ManifestError::InvalidHex(ref data) => write!(f, "invalid hex string: {}", data),
+            ManifestError::PaddingTooLarge { size, data_size } =>
+                write!(f, "padding data is {} bytes, but its size is only {}", data_size, size),
+            ManifestError::InvalidChunkSize(size) =>
+                write!(f, "chunk size {} is not a non-zero multiple of 0x{:X}", size, CHUNK_ALIGN),
+            ManifestError::OutsideFolder(ref file_name) =>
+                write!(f, "file name {} leads out of the extracted folder", file_name),
+        }
+    }
+}
+
+impl error::Error for ManifestError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ManifestError::Json(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io {
+            path: None,
+            source: err,
+        }
+    }
+}
+
+impl From<DgcError> for Error {
+    fn from(err: DgcError) -> Error {
+        Error::Dgc {
+            path: None,
+            source: err,
+        }
+    }
+}
+
+impl From<NgcError> for Error {
+    fn from(err: NgcError) -> Error {
+        Error::Ngc {
+            path: None,
+            source: err,
+        }
+    }
+}
+
+impl From<PackError> for Error {
+    fn from(err: PackError) -> Error {
+        Error::Pack(err)
+    }
+}
+
+impl From<NameDiagnostic> for Error {
+    fn from(err: NameDiagnostic) -> Error {
+        Error::NameCollision(err)
+    }
+}
+
+impl From<NameCollisionError> for Error {
+    fn from(err: NameCollisionError) -> Error {
+        Error::Collisions(err)
+    }
+}
+
+impl From<ManifestError> for Error {
+    fn from(err: ManifestError) -> Error {
+        Error::Manifest {
+            path: None,
+            source: err,
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        ManifestError::Json(err).into()
+    }
+}
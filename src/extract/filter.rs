@@ -0,0 +1,75 @@
+use util;
+
+/// Selects entries of an archive by their type, subtype, name or id.
+/// An entry is selected if it matches every kind of condition that is given, and any one of the
+/// conditions of each kind. Types, subtypes and names are patterns where `*` stands for any
+/// text and `?` for any single character, and case is ignored. Entries without a subtype never
+/// match a subtype pattern. The empty filter selects every entry.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EntryFilter {
+    pub types: Vec<String>,
+    pub subtypes: Vec<String>,
+    pub names: Vec<String>,
+    /// Ids that the entry's id1 must be one of.
+    pub ids: Vec<i32>,
+}
+
+impl EntryFilter {
+    /// Returns true if this filter selects every entry.
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty() && self.subtypes.is_empty() && self.names.is_empty()
+            && self.ids.is_empty()
+    }
+
+    /// Returns true if an entry with the given type, subtype, name and id is selected.
+    pub fn matches(&self, type_name: &str, subtype: Option<&str>, name: &str, id: i32) -> bool {
+        let any = |patterns: &[String], text: &str| {
+            patterns.is_empty() || patterns.iter().any(|pattern| glob_match(pattern, text))
+        };
+        any(&self.types, type_name)
+            && (self.subtypes.is_empty() || subtype.is_some_and(|subtype| any(&self.subtypes, subtype)))
+            && any(&self.names, name)
+            && (self.ids.is_empty() || self.ids.contains(&id))
+    }
+
+    /// Parse an id, given as a hex number starting with `0x`, a placeholder name such as
+    /// `#1A2B3C4D`, or a decimal number as listed in .NGC files.
+    pub fn parse_id(value: &str) -> Result<i32, String> {
+        let result = if value.starts_with("0x") || value.starts_with("0X") {
+            u32::from_str_radix(&value[2..], 16).map(|id| id as i32).ok()
+        } else if let Some(id) = util::parse_placeholder(value) {
+            Some(id)
+        } else {
+            value.parse::<i32>().ok().or_else(|| value.parse::<u32>().ok().map(|id| id as i32))
+        };
+        result.ok_or_else(|| format!("Invalid id '{}'", value))
+    }
+}
+
+/// Returns true if the given text matches the given pattern, where `*` stands for any text and
+/// `?` for any single character. Case is ignored.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().flat_map(char::to_lowercase).collect();
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    // Position in the pattern after the last `*`, and the position in the text that it is
+    // currently matched up to
+    let mut star: Option<(usize, usize)> = None;
+    let (mut p, mut t) = (0, 0);
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p + 1, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // Let the last `*` match one more character
+            p = star_p;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
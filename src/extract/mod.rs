@@ -1,32 +1,251 @@
-use dgc;
+use crc::crc32;
+use dgc::{self, FileKey};
+use names::NameOverlay;
 use ngc;
 use plugin;
 use serde_json;
+use std::borrow::Cow;
 use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
 use std::fs::{self, File};
-use std::io::Write;
-use std::path::Path;
-use util::{self, ChumArchive, CResult};
+use std::io::{Read, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+use error::{CResult, Error, ManifestError, PathContext};
+use util::{self, ChumArchive, ChumReader};
+
+pub mod filter;
+
+pub use self::filter::EntryFilter;
+
+/// Version of the manifest that is written by `extract_archive`. Manifests without a version
+/// are version 1, which only lists each file's names and the archive's layout. Version 3 lists
+/// the lines of the .NGC file that are not in the standard format exactly, see `JsonName`.
+pub const MANIFEST_VERSION: u32 = 3;
+
+/// How extracted files are arranged in the output folder.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExtractLayout {
+    /// Every file is put directly into the output folder, next to meta.json.
+    #[default]
+    Flat,
+    /// Every file is put into a folder named after its type, and into a folder named after its
+    /// subtype within that if it has one, i.e. `<type>/<subtype>/<name>`.
+    ByType,
+}
+
+impl ExtractLayout {
+    /// Every layout, in the order that they should be presented to the user.
+    pub const ALL: [ExtractLayout; 2] = [
+        ExtractLayout::Flat,
+        ExtractLayout::ByType,
+    ];
+
+    /// The name of this layout, as accepted by `from_str`.
+    pub fn name(self) -> &'static str {
+        match self {
+            ExtractLayout::Flat => "flat",
+            ExtractLayout::ByType => "by-type",
+        }
+    }
+
+    /// A short, human readable description of this layout.
+    pub fn label(self) -> &'static str {
+        match self {
+            ExtractLayout::Flat => "All files in one folder",
+            ExtractLayout::ByType => "A folder for each type and subtype",
+        }
+    }
+
+    /// Get the folder that a file with the given type and subtype is extracted to, relative to
+    /// the output folder, with a trailing `/` unless it is the output folder itself.
+    fn folder(self, type_name: &str, subtype: Option<&str>) -> String {
+        match self {
+            ExtractLayout::Flat => String::new(),
+            ExtractLayout::ByType => {
+                let mut folder = String::new();
+                for name in Some(type_name).into_iter().chain(subtype) {
+                    folder.push_str(&util::escape_file_name(name));
+                    folder.push('/');
+                }
+                folder
+            }
+        }
+    }
+}
+
+impl FromStr for ExtractLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ExtractLayout, String> {
+        ExtractLayout::ALL.iter()
+            .find(|layout| layout.name() == s)
+            .cloned()
+            .ok_or_else(|| format!("Unknown extraction layout '{}'", s))
+    }
+}
+
+/// What to do with a file that was changed both in the output folder and in the archive since
+/// it was last extracted, when merging.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Keep the file in the folder.
+    #[default]
+    Ours,
+    /// Overwrite the file with the archive's version.
+    Theirs,
+    /// Do not extract anything, and fail with every conflict.
+    Fail,
+}
+
+impl MergePolicy {
+    /// Every policy, in the order that they should be presented to the user.
+    pub const ALL: [MergePolicy; 3] = [
+        MergePolicy::Ours,
+        MergePolicy::Theirs,
+        MergePolicy::Fail,
+    ];
+
+    /// The name of this policy, as accepted by `from_str`.
+    pub fn name(self) -> &'static str {
+        match self {
+            MergePolicy::Ours => "ours",
+            MergePolicy::Theirs => "theirs",
+            MergePolicy::Fail => "fail",
+        }
+    }
+
+    /// A short, human readable description of this policy.
+    pub fn label(self) -> &'static str {
+        match self {
+            MergePolicy::Ours => "Keep the edited files",
+            MergePolicy::Theirs => "Overwrite the edited files",
+            MergePolicy::Fail => "Stop without extracting",
+        }
+    }
+}
+
+impl FromStr for MergePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<MergePolicy, String> {
+        MergePolicy::ALL.iter()
+            .find(|policy| policy.name() == s)
+            .cloned()
+            .ok_or_else(|| format!("Unknown merge policy '{}'", s))
+    }
+}
+
+/// Options for extracting an archive.
+#[derive(Clone, Debug, Default)]
+pub struct ExtractOptions {
+    /// Merge with the meta.json file that is already in the output folder.
+    pub merge: bool,
+    /// What to do with files that conflict when merging.
+    pub policy: MergePolicy,
+    pub layout: ExtractLayout,
+    /// Only extract the entries that this filter selects.
+    pub filter: EntryFilter,
+    /// Only work out which files would be written, without changing anything on disk.
+    pub dry_run: bool,
+    /// Names for ids that the archive has no name for. These are only used to name the
+    /// extracted files and their folders; meta.json keeps the archive's own names.
+    pub names: NameOverlay,
+}
+
+/// An entry whose file was edited in the output folder, while the archive's version of it
+/// changed as well since it was last extracted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MergeConflict {
+    /// Name of the entry.
+    pub entry: String,
+    /// Path of the file, relative to the output folder.
+    pub file_name: String,
+    /// True if the file was overwritten with the archive's version, false if it was kept.
+    pub overwritten: bool,
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({}), {}", self.entry, self.file_name,
+               if self.overwritten { "overwritten" } else { "kept" })
+    }
+}
+
+/// The result of extracting an archive.
+#[derive(Debug, Default)]
+pub struct ExtractReport {
+    /// Names of the entries whose files were edited in the output folder and kept, since the
+    /// archive's version of them did not change.
+    pub kept: Vec<String>,
+    /// Entries that were changed both in the output folder and in the archive. What happened to
+    /// their files depends on the merge policy.
+    pub conflicts: Vec<MergeConflict>,
+    /// Paths of the files that were written, or would be written by a dry run, relative to the
+    /// output folder. meta.json is always written and not listed.
+    pub written: Vec<String>,
+}
 
 /// Represents the data stored in the .json file.
 /// This is necessary for serializing archive data into a json file, as the
 /// information in the DgcArchive and NgcArchive need to be merged, and some
 /// data that is stored can be safely removed (e.g. splitting files into
 /// chunks, chunk sizes, the actual file's data, etc.).
+/// The layout fields record how the original archive was laid out, so that
+/// an unmodified archive can be packed back to exactly the same bytes.
 #[derive(Serialize, Deserialize)]
 pub struct JsonData {
+    /// Version of the manifest. Missing in version 1 manifests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+    /// True if only some of the archive's entries were extracted. A partial folder is applied
+    /// onto the archive that it was extracted from, see `import_onto`.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub partial: bool,
+    /// Legal notice at the start of the .DGC header, without its NUL padding. Bytes that are
+    /// not valid text are escaped, see `util::escape_bytes`. Version 1 manifests have the whole
+    /// notice as lossy text instead, NUL padding included.
     pub header: String,
     pub files: Vec<JsonDataFile>,
+    /// Byte order of the archive. Big endian if not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endianness: Option<dgc::Endianness>,
+    /// Chunk size of the original archive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_size: Option<usize>,
+    /// Junk padding at the end of the .DGC header.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header_padding: Option<JsonPadding>,
+    /// Padding at the end of each chunk of the original archive.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chunk_padding: Vec<JsonPadding>,
+    /// Every line of the .NGC file, in their original order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub names: Vec<JsonName>,
+    /// Padding at the end of the .NGC file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub names_padding: Option<JsonPadding>,
 }
 
 impl JsonData {
-    pub fn exists(&self, name: &str) -> bool {
-        for f in &self.files {
-            if f.id == name {
-                return true;
-            }
+    /// Get the version of the manifest.
+    pub fn version(&self) -> u32 {
+        self.version.unwrap_or(1)
+    }
+
+    /// Get the warning about the version of the manifest, if it is not a known version.
+    fn version_warnings(&self) -> Vec<ManifestWarning> {
+        if self.version() > MANIFEST_VERSION {
+            vec![ManifestWarning::UnknownVersion(self.version())]
+        } else {
+            vec![]
         }
-        false
+    }
+
+    /// Return true if an entry with the same type, subtype and name as the given entry is listed.
+    pub fn contains(&self, file: &JsonDataFile) -> bool {
+        self.files.iter().any(|f| f.same_entry(file))
     }
 }
 
@@ -35,98 +254,807 @@ impl JsonData {
 pub struct JsonDataFile {
     pub id: String,
     pub type_id: String,
-    pub subtype_id: String,
+    /// Subtype of this file. Files without a subtype store their own id as their subtype.
+    /// Older manifests give the file's own name instead, which has the same effect.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtype_id: Option<String>,
+    /// Path of the extracted file, relative to the folder of the .json file. Folders are
+    /// separated by `/`.
     pub file_name: String,
+    /// Index of the chunk this file was stored in in the original archive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk: Option<usize>,
+    /// Position of this file in its chunk in the original archive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<usize>,
+    /// Size of this file's data in the original archive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<usize>,
+    /// Ids of this file in the original archive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ids: Option<JsonIds>,
+    /// Size of the extracted file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_size: Option<usize>,
+    /// Hash of the extracted file, see `content_hash`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// Plugin that the file was exported with, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<String>,
 }
 
-/// Extract the given archive to the given folder
+/// The raw ids of a file element in the .json file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonIds {
+    pub type_id: i32,
+    pub id1: i32,
+    pub id2: i32,
+}
+
+impl From<FileKey> for JsonIds {
+    fn from(key: FileKey) -> JsonIds {
+        JsonIds {
+            type_id: key.type_id,
+            id1: key.id1,
+            id2: key.id2,
+        }
+    }
+}
+
+impl From<JsonIds> for FileKey {
+    fn from(ids: JsonIds) -> FileKey {
+        FileKey {
+            type_id: ids.type_id,
+            id1: ids.id1,
+            id2: ids.id2,
+        }
+    }
+}
+
+impl JsonDataFile {
+    /// Return true if both elements stand for the same archive entry, i.e. they have the same
+    /// type, subtype and name. Several entries can share a name if their types differ.
+    pub fn same_entry(&self, other: &JsonDataFile) -> bool {
+        self.id == other.id && self.type_id == other.type_id && self.subtype_id == other.subtype_id
+    }
+
+    /// Get the ids that this file's names stand for.
+    pub fn key(&self) -> FileKey {
+        util::entry_key(&self.type_id, self.subtype_id.as_deref(), &self.id)
+    }
+
+    /// Get the ids of this file in an archive with the given names. Names that still have the ids
+    /// that the file was extracted with keep those ids, even if they are not the hash of the name,
+    /// e.g. a name that is not valid UTF-8 or one whose .NGC line has a different id. Other names
+    /// give their hash, like `key`.
+    pub fn key_in(&self, names: &ngc::NgcArchive) -> FileKey {
+        let key = self.key();
+        let ids = match self.ids {
+            Some(ids) => FileKey::from(ids),
+            None => return key,
+        };
+        let keep = |id: i32, name: &str| names.get(id) == Some(name);
+        let id1 = if keep(ids.id1, &self.id) { ids.id1 } else { key.id1 };
+        FileKey {
+            type_id: if keep(ids.type_id, &self.type_id) { ids.type_id } else { key.type_id },
+            id1,
+            id2: match self.subtype_id {
+                Some(ref subtype) if keep(ids.id2, subtype) => ids.id2,
+                // Older manifests give the file's own name as its subtype
+                Some(ref subtype) if *subtype == self.id => id1,
+                Some(_) => key.id2,
+                None => id1,
+            },
+        }
+    }
+
+    /// Return true if the given contents of the extracted file differ from the contents that
+    /// were extracted. Older manifests do not record the contents, so their files are never
+    /// known to be edited.
+    pub fn is_edited(&self, data: &[u8]) -> bool {
+        match (self.hash.as_ref(), self.file_size) {
+            (Some(hash), _) => *hash != content_hash(data),
+            (None, Some(size)) => size != data.len(),
+            (None, None) => false,
+        }
+    }
+}
+
+/// Get the hash of an extracted file's contents, as stored in the .json file.
+pub fn content_hash(data: &[u8]) -> String {
+    format!("crc32:{:08x}", crc32::checksum_ieee(data))
+}
+
+/// A problem with an extracted folder that does not stop it from being imported.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ManifestWarning {
+    /// The manifest has a newer version than this program knows. Fields that were added since
+    /// are ignored.
+    UnknownVersion(u32),
+    /// The names of an entry were changed in the manifest, so they no longer match the ids that
+    /// the entry was extracted with. The ids of the new names are used.
+    StaleIds {
+        entry: String,
+    },
+    /// An entry was exported with a different plugin than the one that imports it now, or with
+    /// no plugin at all.
+    PluginChanged {
+        entry: String,
+        exported: Option<String>,
+        imported: Option<String>,
+    },
+}
+
+impl fmt::Display for ManifestWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let plugin = |name: &Option<String>| name.clone().unwrap_or_else(|| "no plugin".to_owned());
+        match *self {
+            ManifestWarning::UnknownVersion(version) =>
+                write!(f, "The manifest has version {}, but only versions up to {} are known",
+                       version, MANIFEST_VERSION),
+            ManifestWarning::StaleIds { ref entry } =>
+                write!(f, "The names of {} were changed in the manifest, so it gets new ids",
+                       entry),
+            ManifestWarning::PluginChanged { ref entry, ref exported, ref imported } =>
+                write!(f, "{} was exported with {}, but is imported with {}",
+                       entry, plugin(exported), plugin(imported)),
+        }
+    }
+}
+
+/// The result of importing an extracted folder.
+pub struct ImportReport {
+    pub archive: ChumArchive,
+    /// Problems with the folder that did not stop the import.
+    pub warnings: Vec<ManifestWarning>,
+    /// Names of the entries whose files were edited since they were extracted.
+    pub edited: Vec<String>,
+}
+
+/// Represents a line of the .NGC file in the .json file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum JsonName {
+    /// A line in the standard `<ID> "NAME"` format, whose id is the hash of its name.
+    Name(String),
+    /// Any other line, e.g. one with a name that is not valid UTF-8, an id that is not the hash
+    /// of its name, or other line endings. The name and the line, including its line ending and
+    /// any blank lines before it, are escaped with `util::escape_bytes`, and the line is written
+    /// back exactly as it was read.
+    Line {
+        id: i32,
+        name: String,
+        raw: String,
+    },
+}
+
+impl From<&ngc::NgcLine> for JsonName {
+    fn from(line: &ngc::NgcLine) -> JsonName {
+        if line.is_standard() {
+            JsonName::Name(String::from_utf8_lossy(&line.name).into_owned())
+        } else {
+            JsonName::Line {
+                id: line.id,
+                name: util::escape_bytes(&line.name),
+                raw: util::escape_bytes(&line.raw),
+            }
+        }
+    }
+}
+
+/// Represents padding bytes in the .json file.
+/// Padding is usually all zeros, so only the bytes up to the last non-zero
+/// byte are stored, as a hex string.
+#[derive(Serialize, Deserialize)]
+pub struct JsonPadding {
+    pub size: usize,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub data: String,
+}
+
+impl JsonPadding {
+    /// Create padding from the given bytes
+    pub fn from_bytes(data: &[u8]) -> JsonPadding {
+        let len = data.iter().rposition(|&b| b != 0).map_or(0, |pos| pos + 1);
+        JsonPadding {
+            size: data.len(),
+            data: util::to_hex(&data[..len]),
+        }
+    }
+
+    /// Get the padding's bytes
+    pub fn to_bytes(&self) -> CResult<Vec<u8>> {
+        let mut data = util::from_hex(&self.data)?;
+        if data.len() > self.size {
+            return Err(ManifestError::PaddingTooLarge {
+                size: self.size,
+                data_size: data.len(),
+            }.into());
+        }
+        data.resize(self.size, 0);
+        Ok(data)
+    }
+}
+
+/// Extract the given archive to the given folder, with every file directly in the folder.
 /// If merge is true, then this function will look for an existing meta.json file in the given
 /// directory to merge with.
-pub fn extract_archive(archive: &ChumArchive, output_folder: &Path, merge: bool) -> CResult<()> {
-    let id_lookup = &archive.ngc.names;
+pub fn extract_archive(archive: &ChumArchive, output_folder: &Path, merge: bool)
+        -> CResult<ExtractReport> {
+    extract_with_options(archive, output_folder, &ExtractOptions {
+        merge,
+        ..ExtractOptions::default()
+    })
+}
+
+/// Extract the given archive to the given folder.
+/// If `options.merge` is true, then this function will look for an existing meta.json file in
+/// the given directory to merge with. Entries of that file that are not extracted again, because
+/// they are not part of the archive or not selected by `options.filter`, are kept.
+/// Entries that are extracted again keep their file. The file is overwritten if it was not
+/// edited since it was last extracted, going by the hash in meta.json. Edited files are kept if
+/// the archive's version of them did not change; if it did, the entry is a conflict, which is
+/// resolved by `options.policy`. A `MergePolicy::Fail` merge with conflicts writes nothing, and
+/// neither does a dry run.
+/// If a filter is given, then only the entries that it selects are extracted, and the .json file
+/// is marked as partial unless it is merged with a complete one.
+/// Ids that have no name in the .NGC archive are written as placeholder names, e.g.
+/// `#1A2B3C4D`, which are turned back into the same ids when the folder is imported. Their files
+/// are named after `options.names` instead if it has a name for them.
+/// Files are named after their escaped name, see `util::get_file_string`, with the extension of
+/// their codec plugin, in the folder given by `options.layout`. The path of each file is recorded
+/// in meta.json, which is how `import_archive` finds the files of any layout.
+pub fn extract_with_options(archive: &ChumArchive, output_folder: &Path, options: &ExtractOptions)
+        -> CResult<ExtractReport> {
+    extract_entries(&mut &archive.dgc, &archive.ngc, output_folder, options)
+}
+
+/// Extract the contents of an archive whose data is read on demand into the given folder, like
+/// `extract_with_options`. Only the data of the files that are extracted is read, one file at a
+/// time.
+pub fn extract_from_reader(archive: &mut ChumReader, output_folder: &Path, options: &ExtractOptions)
+        -> CResult<ExtractReport> {
+    extract_entries(&mut archive.dgc, &archive.ngc, output_folder, options)
+}
+
+/// The .DGC file of an archive that is extracted, which is either loaded or read on demand.
+trait EntrySource {
+    fn header(&self) -> &dgc::DgcHeader;
+    fn chunk_size(&self) -> usize;
+    fn endianness(&self) -> dgc::Endianness;
+    /// Get the key and data size of each file, by chunk.
+    fn layout(&self) -> Vec<Vec<(FileKey, usize)>>;
+    /// Get the padding of each chunk, truncated or zero-filled to the chunk's free space.
+    fn chunk_padding(&mut self) -> CResult<Vec<Vec<u8>>>;
+    /// Get the data of the file at the given position of the given chunk.
+    fn read_data(&mut self, chunk: usize, position: usize) -> CResult<Cow<'_, [u8]>>;
+}
+
+impl EntrySource for &dgc::DgcArchive {
+    fn header(&self) -> &dgc::DgcHeader {
+        &self.header
+    }
+
+    fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    fn endianness(&self) -> dgc::Endianness {
+        self.endianness
+    }
+
+    fn layout(&self) -> Vec<Vec<(FileKey, usize)>> {
+        self.data.iter()
+            .map(|chunk| chunk.data.iter().map(|f| (f.key(), f.data.len())).collect())
+            .collect()
+    }
+
+    fn chunk_padding(&mut self) -> CResult<Vec<Vec<u8>>> {
+        Ok(self.data.iter().map(|chunk| {
+            let mut padding = chunk.padding.clone();
+            padding.resize(self.chunk_size - chunk.get_size(), 0);
+            padding
+        }).collect())
+    }
+
+    fn read_data(&mut self, chunk: usize, position: usize) -> CResult<Cow<'_, [u8]>> {
+        Ok(Cow::Borrowed(&self.data[chunk].data[position].data))
+    }
+}
+
+impl<R: Read + Seek> EntrySource for dgc::DgcReader<R> {
+    fn header(&self) -> &dgc::DgcHeader {
+        &self.header
+    }
+
+    fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    fn endianness(&self) -> dgc::Endianness {
+        self.endianness
+    }
+
+    fn layout(&self) -> Vec<Vec<(FileKey, usize)>> {
+        self.chunks.iter()
+            .map(|chunk| chunk.files.iter().map(|f| (f.key(), f.data_size)).collect())
+            .collect()
+    }
+
+    fn chunk_padding(&mut self) -> CResult<Vec<Vec<u8>>> {
+        let chunks = self.chunks.clone();
+        let mut padding = Vec::with_capacity(chunks.len());
+        for (i, chunk) in chunks.iter().enumerate() {
+            padding.push(self.read_padding(i, chunk)?);
+        }
+        Ok(padding)
+    }
+
+    fn read_data(&mut self, chunk: usize, position: usize) -> CResult<Cow<'_, [u8]>> {
+        let file = self.chunks[chunk].files[position].clone();
+        Ok(Cow::Owned(dgc::DgcReader::read_data(self, &file)?))
+    }
+}
+
+/// Extract the files of the given source, named by the given .NGC archive, see
+/// `extract_with_options`.
+fn extract_entries<S: EntrySource>(source: &mut S, names: &ngc::NgcArchive, output_folder: &Path,
+                                   options: &ExtractOptions) -> CResult<ExtractReport> {
     let json_path = output_folder.join("meta.json");
-    fs::create_dir_all(&output_folder)?;
-    let plugin_manager = plugin::PluginManager::new();
+    let plugin_manager = plugin::PluginManager::with_endianness(source.endianness());
+    let layout = source.layout();
 
     let mut json_data = JsonData {
-        header: String::from_utf8_lossy(&archive.dgc.header.legal_notice).to_string(),
+        version: Some(MANIFEST_VERSION),
+        partial: !options.filter.is_empty(),
+        header: util::escape_bytes(source.header().notice()),
         files: vec![],
+        endianness: Some(source.endianness()),
+        chunk_size: Some(source.chunk_size()),
+        header_padding: Some(JsonPadding::from_bytes(&source.header().padding)),
+        chunk_padding: source.chunk_padding()?.iter()
+            .map(|padding| JsonPadding::from_bytes(padding))
+            .collect(),
+        names: names.kept_lines().map(JsonName::from).collect(),
+        names_padding: Some(JsonPadding::from_bytes(&names.padding)),
     };
 
-    for chunk in &archive.dgc.data {
-        for file in &chunk.data {
-            let ftype = &id_lookup[&file.type_id];
-            let fname = util::get_file_string(&id_lookup[&file.id1], file.id1 as u32);
-            let fpath = output_folder.join(fname);
-            let mut fh = File::create(&fpath)?;
+    // Entries of the old manifest that are not part of this archive are kept when merging, and
+    // so are the files that they point to. Entries that are extracted again are compared with
+    // their files to find the ones that were edited.
+    let mut kept_files = Vec::new();
+    let mut previous = HashMap::new();
+    if options.merge {
+        let keys: HashSet<FileKey> = layout.iter().flatten().map(|&(key, _)| key).collect();
+        let old_json_data = read_manifest(output_folder)?;
+        json_data.partial &= old_json_data.partial;
+        for mut file in old_json_data.files {
+            let key = file.key_in(names);
+            if !keys.contains(&key) {
+                // The file is not part of this archive's layout
+                file.chunk = None;
+                file.position = None;
+                file.size = None;
+                kept_files.push(file);
+            } else if !selects(&options.filter, names, key) {
+                kept_files.push(file);
+            } else if is_inside(&file.file_name) {
+                previous.insert(key, file);
+            }
+        }
+    }
+    let mut used_names: HashSet<String> = kept_files.iter().chain(previous.values())
+        .map(|file| file.file_name.to_lowercase())
+        .collect();
+    let mut reused_names = HashSet::new();
+
+    let mut report = ExtractReport::default();
+    let mut writes = Vec::new();
+    for (chunk_index, chunk) in layout.iter().enumerate() {
+        for (position, &(key, size)) in chunk.iter().enumerate() {
+            if !selects(&options.filter, names, key) {
+                continue;
+            }
+            let ftype = names.resolve(key.type_id);
+            let name = names.resolve(key.id1);
+            let subtype = names.resolve_subtype(key.id1, key.id2);
             let mut data = Vec::new();
-            plugin_manager.export(ftype, &mut &file.data[..], &mut data)?;
-            fh.write_all(&mut &data[..])?;
+            plugin_manager.export(&ftype, &mut &source.read_data(chunk_index, position)?[..], &mut data)
+                .map_err(|err| Error::codec(&name, &ftype, err))?;
+            let plugin = plugin_manager.get_codec(&ftype).map(|p| p.get_plugin_string().to_owned());
+
+            // A file that was extracted before keeps its path, so that edits to it are found
+            let old = previous.get(&key)
+                .filter(|old| reused_names.insert(old.file_name.to_lowercase()));
+            let fname = match old {
+                Some(old) => old.file_name.clone(),
+                None => {
+                    let found = &options.names;
+                    let folder = options.layout.folder(&found.resolve(names, key.type_id),
+                        found.resolve_subtype(names, key.id1, key.id2).as_deref());
+                    unique_file_name(&mut used_names, &folder, &found.resolve(names, key.id1),
+                                     plugin_manager.get_file_extension(&ftype))
+                }
+            };
+            let fpath = output_folder.join(&fname);
+            let local = match old {
+                Some(_) if fpath.is_file() => Some(fs::read(&fpath).at_path(&fpath)?),
+                _ => None,
+            };
+            let write = match (old, local) {
+                (Some(old), Some(local)) if old.is_edited(&local) => {
+                    if local == data {
+                        false
+                    } else if old.is_edited(&data) {
+                        let overwritten = options.policy == MergePolicy::Theirs;
+                        report.conflicts.push(MergeConflict {
+                            entry: name.clone(),
+                            file_name: fname.clone(),
+                            overwritten,
+                        });
+                        overwritten
+                    } else {
+                        report.kept.push(name.clone());
+                        false
+                    }
+                },
+                _ => true,
+            };
+
+            if write {
+                report.written.push(fname.clone());
+            }
             json_data.files.push(JsonDataFile {
-                id: id_lookup[&file.id1].to_owned(),
-                type_id: id_lookup[&file.type_id].to_owned(),
-                subtype_id: id_lookup[&file.id2].to_owned(),
-                file_name: fpath.file_name().unwrap().to_str().unwrap().to_owned(),
+                id: name,
+                type_id: ftype,
+                subtype_id: subtype,
+                file_name: fname,
+                chunk: Some(chunk_index),
+                position: Some(position),
+                size: Some(size),
+                ids: Some(key.into()),
+                file_size: Some(data.len()),
+                hash: Some(content_hash(&data)),
+                plugin,
             });
+            if write {
+                writes.push((fpath, data));
+            }
         }
     }
 
-    if merge {
-        let json_file = File::open(&json_path)?;
-        let temp_json_data: JsonData = serde_json::from_reader(json_file)?;
-        for file in temp_json_data.files {
-            if !json_data.exists(&file.id) {
-                json_data.files.push(file);
-            }
+    if options.policy == MergePolicy::Fail && !report.conflicts.is_empty() {
+        return Err(Error::MergeConflicts(report.conflicts));
+    }
+    if options.dry_run {
+        return Ok(report);
+    }
+
+    fs::create_dir_all(output_folder).at_path(output_folder)?;
+    for (fpath, data) in writes {
+        if let Some(fdir) = fpath.parent() {
+            fs::create_dir_all(fdir).at_path(fdir)?;
         }
+        let mut fh = File::create(&fpath).at_path(&fpath)?;
+        fh.write_all(&data[..]).at_path(&fpath)?;
     }
 
-    let mut json_file = File::create(&json_path)?;
-    serde_json::to_writer_pretty(&mut json_file, &json_data)?;
+    json_data.files.extend(kept_files);
+
+    let mut json_file = File::create(&json_path).at_path(&json_path)?;
+    serde_json::to_writer_pretty(&mut json_file, &json_data).at_path(&json_path)?;
+
+    Ok(report)
+}
+
+/// Returns true if the given filter selects the file with the given key, named by the given .NGC
+/// archive.
+fn selects(filter: &EntryFilter, names: &ngc::NgcArchive, key: FileKey) -> bool {
+    filter.matches(&names.resolve(key.type_id), names.resolve_subtype(key.id1, key.id2).as_deref(),
+                   &names.resolve(key.id1), key.id1)
+}
 
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Get a path in the given folder for a file with the given name that is not one of the used
+/// paths, and mark it as used. Paths are compared case-insensitively, since files whose names
+/// only differ in case overwrite each other on some file systems.
+fn unique_file_name(used: &mut HashSet<String>, folder: &str, name: &str, extension: Option<&str>)
+        -> String {
+    let mut number = 1;
+    let mut file_name = folder.to_owned() + &util::get_file_string(name, extension, number);
+    while used.contains(&file_name.to_lowercase()) {
+        number += 1;
+        file_name = folder.to_owned() + &util::get_file_string(name, extension, number);
+    }
+    used.insert(file_name.to_lowercase());
+    file_name
+}
+
+/// Returns true if the given path of an extracted file stays inside the extracted folder.
+fn is_inside(file_name: &str) -> bool {
+    Path::new(file_name).components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+/// Read the .json file of the given extracted folder.
+pub fn read_manifest(input_folder: &Path) -> CResult<JsonData> {
+    let json_path = input_folder.join("meta.json");
+    let json_file = File::open(&json_path).at_path(&json_path)?;
+    serde_json::from_reader(json_file).at_path(&json_path)
+}
+
+/// Get the files of the given extracted folder that its .json file lists and that exist,
+/// followed by the .json file itself. These are the files that replacing the folder removes;
+/// anything else in the folder was put there by someone else and is kept. Files whose path
+/// leads out of the folder are never listed.
+pub fn listed_files(folder: &Path) -> CResult<Vec<PathBuf>> {
+    let json_data = read_manifest(folder)?;
+    let mut files: Vec<PathBuf> = json_data.files.iter()
+        .filter(|f| is_inside(&f.file_name))
+        .map(|f| folder.join(&f.file_name))
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+    files.dedup();
+    files.push(folder.join("meta.json"));
+    Ok(files)
+}
+
+/// Remove the given files of an extracted folder, as listed by `listed_files`. Folders that
+/// they were in are removed as well if nothing else is left in them.
+pub fn remove_listed_files(folder: &Path, files: &[PathBuf]) -> CResult<()> {
+    for path in files {
+        fs::remove_file(path).at_path(path)?;
+    }
+    for path in files {
+        let mut dir = path.parent();
+        while let Some(current) = dir {
+            if current == folder || !current.starts_with(folder) || fs::remove_dir(current).is_err() {
+                break;
+            }
+            dir = current.parent();
+        }
+    }
     Ok(())
 }
 
-/// Import an archive from the given path
+/// Read the extracted file of the given entry and convert it back into the archive's format.
+/// Also returns whether the file was edited since it was extracted, and adds any problems with
+/// the entry to the given warnings. Fails if the entry's file name leads out of the folder.
+/// The entry is given the ids in `key`.
+fn import_entry(plugin_manager: &plugin::PluginManager, input_folder: &Path, f: &JsonDataFile,
+                key: FileKey, warnings: &mut Vec<ManifestWarning>) -> CResult<(Vec<u8>, bool)> {
+    if !is_inside(&f.file_name) {
+        let json_path = input_folder.join("meta.json");
+        return Err(Error::from(ManifestError::OutsideFolder(f.file_name.clone())).with_path(&json_path));
+    }
+    let fpath = input_folder.join(&f.file_name);
+    let contents = fs::read(&fpath).at_path(&fpath)?;
+    let mut data = Vec::new();
+    plugin_manager.import(&f.type_id, &mut &contents[..], &mut data)
+        .map_err(|err| Error::codec(&f.id, &f.type_id, err.with_path(&fpath)))?;
+    if f.ids.is_some_and(|ids| FileKey::from(ids) != key) {
+        warnings.push(ManifestWarning::StaleIds { entry: f.id.clone() });
+    }
+    // Only files that were extracted by this version record how they were exported
+    if f.hash.is_some() {
+        let plugin = plugin_manager.get_codec(&f.type_id).map(|p| p.get_plugin_string());
+        if f.plugin.as_deref() != plugin {
+            warnings.push(ManifestWarning::PluginChanged {
+                entry: f.id.clone(),
+                exported: f.plugin.clone(),
+                imported: plugin.map(str::to_owned),
+            });
+        }
+    }
+    Ok((data, f.is_edited(&contents)))
+}
+
+/// Apply the entries of the given extracted folder onto the given archive, and return it.
+/// Entries that the archive has are replaced, and other entries are added. The archive's other
+/// files and its header are unchanged, and files keep their place if they still fit, so a folder
+/// that only has some of the archive's entries, e.g. one that was extracted with a filter, can be
+/// applied back onto the archive that it came from. Entries whose names were changed in the
+/// .json file are renamed in the archive if it has them under their original ids.
+pub fn import_onto(mut archive: ChumArchive, input_folder: &Path) -> CResult<ImportReport> {
+    let json_data = read_manifest(input_folder)?;
+    let plugin_manager = plugin::PluginManager::with_endianness(archive.dgc.endianness);
+    let mut warnings = json_data.version_warnings();
+    let mut edited = Vec::new();
+    for f in &json_data.files {
+        let key = f.key_in(&archive.ngc);
+        let (data, is_edited) = import_entry(&plugin_manager, input_folder, f, key, &mut warnings)?;
+        if is_edited {
+            edited.push(f.id.clone());
+        }
+        let original = f.ids.map(FileKey::from)
+            .filter(|&ids| ids != key && archive.dgc.get_file(ids).is_some());
+        if let (Some(mut original), None) = (original, archive.dgc.get_file(key)) {
+            if archive.ngc.resolve(original.id1) != f.id {
+                original = original.with_id1(archive.rename_file(original, &f.id)?);
+            }
+            if original != key {
+                archive.retype_file(original, &f.type_id, f.subtype_id.as_deref())?;
+            }
+        }
+        match archive.dgc.get_file(key).map(|file| file.data != data) {
+            Some(true) => {
+                archive.replace_data(key, data);
+            },
+            Some(false) => {},
+            None => {
+                archive.add_file(&f.type_id, f.subtype_id.as_deref(), &f.id, data)?;
+            }
+        }
+    }
+    Ok(ImportReport {
+        archive,
+        warnings,
+        edited,
+    })
+}
+
+/// Import an archive from the given path.
+/// Files are added in the order that they are listed in the .json file, so importing the same
+/// folder always gives the same archive.
+/// If the .json file records the original archive's layout, then files are put back into the
+/// chunks that they came from, at their original position. If a chunk's files no longer fit, then
+/// files that were edited are moved out of it first. Files that were moved out and new files
+/// are then added to the first chunk that they fit in.
 pub fn import_archive(input_folder: &Path) -> CResult<ChumArchive> {
-    let json_path = input_folder.join("meta.json");
-    let json_file = File::open(&json_path)?;
+    import_folder(input_folder).map(|report| report.archive)
+}
 
-    let json_data: JsonData = serde_json::from_reader(json_file)?;
-    let plugin_manager = plugin::PluginManager::new();
+/// Import an archive from the given path, like `import_archive`, and report which files were
+/// edited since they were extracted and any problems with the .json file.
+pub fn import_folder(input_folder: &Path) -> CResult<ImportReport> {
+    let json_path = input_folder.join("meta.json");
+    let json_data = read_manifest(input_folder)?;
+    let endianness = json_data.endianness.unwrap_or_default();
+    let plugin_manager = plugin::PluginManager::with_endianness(endianness);
+    let mut warnings = json_data.version_warnings();
+    let mut edited = Vec::new();
 
-    let mut files = Vec::new();
+    // Lines of the .NGC file are added first, so that they keep their ids
     let mut ngc = ngc::NgcArchive::new();
+    for name in &json_data.names {
+        match *name {
+            JsonName::Name(ref name) => {
+                ngc.push(name);
+            },
+            JsonName::Line { id, ref name, ref raw } => ngc.push_line(ngc::NgcLine {
+                id,
+                name: util::unescape_bytes(name),
+                raw: util::unescape_bytes(raw),
+            }),
+        }
+    }
+    let mut files = Vec::new();
     for f in &json_data.files {
-        let mut fh = File::open(&input_folder.join(&f.file_name))?;
-        let mut data = Vec::new();
-        plugin_manager.import(&f.type_id, &mut fh, &mut data)?;
-        let id_hash        = util::hash_name(&f.id);
-        let subtypeid_hash = util::hash_name(&f.subtype_id);
-        let typeid_hash    = util::hash_name(&f.type_id);
-        ngc.names.insert(id_hash,        f.id.to_owned());
-        ngc.names.insert(subtypeid_hash, f.subtype_id.to_owned());
-        ngc.names.insert(typeid_hash,    f.type_id.to_owned());
-        files.push(dgc::DgcFile {
-            data: data,
-            id1: id_hash,
-            id2: subtypeid_hash,
-            type_id: typeid_hash,
-        });
+        let key = f.key_in(&ngc);
+        let (data, is_edited) = import_entry(&plugin_manager, input_folder, f, key, &mut warnings)?;
+        if is_edited {
+            edited.push(f.id.clone());
+        }
+        // Names that the .NGC lines do not give are added with their hash. Placeholder names give
+        // their raw id, and are not added to the .NGC file
+        let names = [Some((key.id1, &f.id)), f.subtype_id.as_ref().map(|s| (key.id2, s)),
+                     Some((key.type_id, &f.type_id))];
+        for &(id, name) in names.iter().flatten() {
+            if ngc.get(id) != Some(name.as_str()) {
+                ngc.insert(name);
+            }
+        }
+        let changed = is_edited || f.size != Some(data.len());
+        files.push((dgc::DgcFile {
+            data,
+            id1: key.id1,
+            id2: key.id2,
+            type_id: key.type_id,
+        }, f.chunk, f.position, changed));
+    }
+    if let Some(ref padding) = json_data.names_padding {
+        ngc.padding = padding.to_bytes().at_path(&json_path)?;
     }
 
     let max_file_size = files.iter().fold(0,
-        |acc, f| cmp::max(acc, f.data.len()));
+        |acc, f| cmp::max(acc, f.0.data.len()));
 
-    let mut dgc = dgc::DgcArchive::new(&json_data.header, max_file_size);
+    let mut dgc = if json_data.version() == 1 {
+        // Version 1 manifests have the notice as lossy text with its NUL padding, which is
+        // taken as it is and truncated to the size of the notice
+        dgc::DgcArchive::new(&json_data.header, max_file_size)
+    } else {
+        let mut dgc = dgc::DgcArchive::new("", max_file_size);
+        dgc.header.set_notice(&util::unescape_bytes(&json_data.header))?;
+        dgc
+    };
+    dgc.endianness = endianness;
+    if let Some(ref padding) = json_data.header_padding {
+        dgc.header.padding = padding.to_bytes().at_path(&json_path)?;
+    }
 
-    for f in files {
+    // Put files back into their original chunks where possible
+    let mut unplaced = Vec::new();
+    // Index in the manifest of each chunk that is kept
+    let mut origins = Vec::new();
+    if let Some(chunk_size) = json_data.chunk_size {
+        if chunk_size == 0 || chunk_size % dgc::CHUNK_ALIGN != 0 {
+            return Err(Error::from(ManifestError::InvalidChunkSize(chunk_size)).with_path(&json_path));
+        }
+        dgc.chunk_size = chunk_size;
+        let mut chunks: Vec<Vec<(dgc::DgcFile, Option<usize>, bool)>> = Vec::new();
+        chunks.resize_with(json_data.chunk_padding.len(), Vec::new);
+        for (f, chunk, position, changed) in files {
+            match chunk {
+                Some(chunk) => {
+                    if chunks.len() <= chunk {
+                        chunks.resize_with(chunk + 1, Vec::new);
+                    }
+                    chunks[chunk].push((f, position, changed));
+                },
+                None => unplaced.push(f),
+            }
+        }
+        for (index, mut chunk_files) in chunks.into_iter().enumerate() {
+            // Files without a position keep the order they are listed in, after the others
+            chunk_files.sort_by_key(|f| f.1.unwrap_or(usize::MAX));
+            // Move changed files out of the chunk until the rest fit, then any other files
+            let mut size = chunk_files.iter().fold(4, |acc, f| acc + f.0.get_size());
+            let mut keep = vec![true; chunk_files.len()];
+            for &move_changed in &[true, false] {
+                for (i, &(ref f, _, changed)) in chunk_files.iter().enumerate().rev() {
+                    if size > chunk_size && keep[i] && changed == move_changed {
+                        keep[i] = false;
+                        size -= f.get_size();
+                    }
+                }
+            }
+            let mut chunk = dgc::DgcChunk::new();
+            for ((f, _, _), keep) in chunk_files.into_iter().zip(keep) {
+                if keep {
+                    chunk.add_file(f);
+                } else {
+                    unplaced.push(f);
+                }
+            }
+            // Chunks whose entries were all removed are not written
+            if chunk.get_num_files() > 0 {
+                dgc.data.push(chunk);
+                origins.push(index);
+            }
+        }
+    } else {
+        unplaced.extend(files.into_iter().map(|(f, _, _, _)| f));
+    }
+    for f in unplaced {
         dgc.add_file(f);
     }
+    // Chunks whose free space did not change keep their original padding. If
+    // the chunk size changed, then every file has been redistributed.
+    if json_data.chunk_size == Some(dgc.chunk_size) {
+        let chunk_size = dgc.chunk_size;
+        for (chunk, &index) in dgc.data.iter_mut().zip(&origins) {
+            let padding = match json_data.chunk_padding.get(index) {
+                Some(padding) => padding,
+                None => continue,
+            };
+            if chunk_size.checked_sub(chunk.get_size()) == Some(padding.size) {
+                chunk.padding = padding.to_bytes().at_path(&json_path)?;
+            }
+        }
+    }
 
-    Ok(ChumArchive {
-        dgc: dgc,
-        ngc: ngc,
+    Ok(ImportReport {
+        archive: ChumArchive {
+            dgc,
+            ngc,
+        },
+        warnings,
+        edited,
     })
 
     // let path = Path::new(matches.value_of_os("OUTPUT").unwrap());
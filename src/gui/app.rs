@@ -1,12 +1,14 @@
 use gtk::{self, MenuItemExt, Button, HeaderBar, Notebook, FileChooserAction};
 use gtk::prelude::*;
+use super::dialog;
 use super::page::{Page, ArchiveFile};
-use util::{self, CResult, ChumArchive};
+use util::{self, CResult, ChumReader};
+use dgc::{PackOptions, PackStrategy};
+use names::NameDictionary;
 use std::rc::Rc;
 use std::cell::RefCell;
 use std::env;
-use std::fs;
-use extract;
+use extract::{self, ExtractLayout, MergePolicy};
 
 /// Represents an application
 /// The pages property maps page tab ids to Page objects
@@ -16,35 +18,78 @@ pub struct Application {
     pub notebook: Notebook,
     pub archive_buttons: Vec<gtk::Widget>,
     pub selected: usize,
+    /// How files are assigned to chunks when saving. If there is no strategy, then files keep
+    /// their place in the archive.
+    pub pack_strategy: Option<PackStrategy>,
+    /// How files are arranged in folders when extracting.
+    pub extract_layout: ExtractLayout,
+    /// What to do with files that were changed both in the folder and in the archive when
+    /// merging.
+    pub merge_policy: MergePolicy,
+    /// Known names, used to name ids that an archive has no name for.
+    pub names: NameDictionary,
 }
 
 pub fn action_open_file(app: &Rc<RefCell<Application>>) -> CResult<()> {
     let path = env::current_dir()?;
-    let value = util::open_gc(&path, &app.borrow().window, FileChooserAction::Open);
+    let value = dialog::open_gc(&path, &app.borrow().window, FileChooserAction::Open);
     if let Some(paths) = value {
         for page in &app.borrow().pages {
             if page.borrow().paths == paths {
                 return Ok(());
             }
         }
-        let page = Page::new(&app, paths)?;
-        Application::add_page(&app, &page);
+        let page = Page::new(app, paths)?;
+        app.borrow_mut().save_names();
+        Application::add_page(app, &page);
+        let missing = page.borrow().archive.source.missing_ids();
+        if !missing.is_empty() {
+            dialog::show_warning(&app.borrow().window, &util::describe_missing_ids(&missing));
+        }
+        let diagnostics = page.borrow().archive.source.check_names();
+        if !diagnostics.is_empty() {
+            dialog::show_warning(&app.borrow().window, &util::describe_name_diagnostics(&diagnostics));
+        }
     }
     Ok(())
 }
 
+/// Ask the user whether the current page should be saved, if distinct names in it share an id.
+/// Returns None if the page should not be saved, otherwise whether it has to be saved even though
+/// names share an id.
+fn confirm_collisions(app: &Rc<RefCell<Application>>, page: &Rc<RefCell<Page>>) -> Option<bool> {
+    let collisions = page.borrow().archive.collisions();
+    if collisions.is_empty() {
+        return Some(false);
+    }
+    let confirmed = dialog::ask_confirmation(&app.borrow().window, &format!(
+        "{}\nOnly the first name of each pair will be saved. Save anyway?",
+        util::describe_name_diagnostics(&collisions)));
+    if confirmed { Some(true) } else { None }
+}
+
 pub fn action_save_file(app: &Rc<RefCell<Application>>) -> CResult<()> {
     let current_page = app.borrow().get_current_page().unwrap().clone();
-    current_page.borrow_mut().save()?;
+    let force = match confirm_collisions(app, &current_page) {
+        Some(force) => force,
+        None => return Ok(()),
+    };
+    let options = app.borrow().get_pack_options();
+    current_page.borrow_mut().save(options.as_ref(), force)?;
     Ok(())
 }
 
 pub fn action_saveas(app: &Rc<RefCell<Application>>) -> CResult<()> {
     let current_page = app.borrow().get_current_page().unwrap().clone();
     let path = current_page.borrow().paths.d.parent().unwrap().to_owned();
-    let value = util::open_gc(&path, &app.borrow().window, FileChooserAction::Save);
+    let value = dialog::open_gc(&path, &app.borrow().window, FileChooserAction::Save);
     if let Some(paths) = value {
-        current_page.borrow_mut().save_as(paths)?;
+        let force = match confirm_collisions(app, &current_page) {
+            Some(force) => force,
+            None => return Ok(()),
+        };
+        let options = app.borrow().get_pack_options();
+        current_page.borrow_mut().save_as(paths, options.as_ref(), force)?;
     }
     Ok(())
 }
@@ -52,26 +97,32 @@ pub fn action_saveas(app: &Rc<RefCell<Application>>) -> CResult<()> {
 pub fn action_extract_all(app: &Rc<RefCell<Application>>) -> CResult<()> {
     let current_page = app.borrow().get_current_page().unwrap().clone();
     let path = current_page.borrow().paths.d.parent().unwrap().to_owned();
-    let value = util::open_any(&path, "Select folder to extract files to",
+    let value = dialog::open_any(&path, "Select folder to extract files to",
         &app.borrow().window, FileChooserAction::CreateFolder);
     // println!("{:?}", );
     if let Some(path) = value {
         let mut do_merge = false;
         let mut do_extract = true;
         if path.join("meta.json").exists() {
-            match util::ask_merge(&app.borrow().window) {
+            match dialog::ask_merge(&app.borrow().window) {
                 Some(true) => {
                     do_extract = true;
                     do_merge = true;
                 },
                 Some(false) => {
-                    for path in fs::read_dir(&path)? {
-                        let path = path?;
-                        if path.file_type()?.is_file() {
-                            fs::remove_file(&path.path())?;
-                        }
+                    // Only files that the folder's meta.json lists are removed
+                    let files = extract::listed_files(&path)?;
+                    let names: Vec<String> = files.iter()
+                        .map(|file| file.strip_prefix(&path).unwrap_or(file).display().to_string())
+                        .collect();
+                    let msg = format!("The following {} files that were extracted to this folder \
+                                       will be removed.\nOther files in the folder are kept.",
+                                      files.len());
+                    do_extract = dialog::ask_confirmation_list(&app.borrow().window,
+                        "Replace Folder", &msg, &names);
+                    if do_extract {
+                        extract::remove_listed_files(&path, &files)?;
                     }
-                    do_extract = true;
                     do_merge = false;
                 }
                 None => {
@@ -81,12 +132,35 @@ pub fn action_extract_all(app: &Rc<RefCell<Application>>) -> CResult<()> {
             }
         }
         if do_extract {
-            let archives = current_page.borrow().archive.into_archives();
-            let archive = ChumArchive {
-                dgc: archives.0,
-                ngc: archives.1,
+            let mut page = current_page.borrow_mut();
+            let options = extract::ExtractOptions {
+                merge: do_merge,
+                policy: app.borrow().merge_policy,
+                layout: app.borrow().extract_layout,
+                names: page.display_names.clone(),
+                ..extract::ExtractOptions::default()
+            };
+            let report = if page.need_save {
+                // unsaved changes are only in the page, so every file has to be read
+                let archive = page.archive.sync()?;
+                extract::extract_with_options(archive, &path, &options)?
+            } else {
+                let mut archive = ChumReader::open_pair(&page.paths)?;
+                extract::extract_from_reader(&mut archive, &path, &options)?
             };
-            extract::extract_archive(&archive, &path, do_merge)?;
+            let mut lines = Vec::new();
+            if !report.kept.is_empty() {
+                lines.push(format!("Kept {} files that were edited in the folder: {}",
+                                   report.kept.len(), report.kept.join(", ")));
+            }
+            if !report.conflicts.is_empty() {
+                lines.push(format!("{} files were changed both in the folder and in the archive:",
+                                   report.conflicts.len()));
+                lines.extend(report.conflicts.iter().map(|c| c.to_string()));
+            }
+            if !lines.is_empty() {
+                dialog::show_warning(&app.borrow().window, &lines.join("\n"));
+            }
         }
     }
     Ok(())
@@ -95,32 +169,50 @@ pub fn action_extract_all(app: &Rc<RefCell<Application>>) -> CResult<()> {
 pub fn action_import_all(app: &Rc<RefCell<Application>>) -> CResult<()> {
     let current_page = app.borrow().get_current_page().unwrap().clone();
     let path = current_page.borrow().paths.d.parent().unwrap().to_owned();
-    let value = util::open_any(&path, "Select folder to import files from",
+    let value = dialog::open_any(&path, "Select folder to import files from",
         &app.borrow().window, FileChooserAction::SelectFolder);
     if let Some(path) = value {
-        let archive = extract::import_archive(&path)?;
+        let report = extract::import_folder(&path)?;
+        if !report.warnings.is_empty() {
+            let lines: Vec<String> = report.warnings.iter().map(|w| w.to_string()).collect();
+            dialog::show_warning(&app.borrow().window, &lines.join("\n"));
+        }
+        let archive = report.archive;
+        let missing = archive.missing_ids();
+        let found = app.borrow_mut().names.resolve_missing(&archive.ngc, &missing);
+        app.borrow_mut().save_names();
+        let missing: Vec<i32> = missing.into_iter().filter(|&id| !found.contains(id)).collect();
+        if !missing.is_empty() {
+            dialog::show_warning(&app.borrow().window, &util::describe_missing_ids(&missing));
+        }
         let mut num_replace = 0;
         let mut num_total = 0;
         let current_file = current_page.borrow().get_active_file();
         {
             let mut page = current_page.borrow_mut();
-            for file in archive.dgc.iter_files() {
-                if page.archive.exists(&archive.ngc.names[&file.id1]) {
+            page.display_names.extend(found);
+            let names = &archive.ngc;
+            let files: Vec<ArchiveFile> = archive.dgc.data.into_iter()
+                .flat_map(|chunk| chunk.data.into_iter())
+                .map(|file| ArchiveFile {
+                    name: names.resolve(file.id1),
+                    subtypeid: names.resolve_subtype(file.id1, file.id2),
+                    typeid: names.resolve(file.type_id),
+                    data: Some(file.data),
+                    key: None,
+                })
+                .collect();
+            for file in &files {
+                if page.archive.find_entry(file).is_some() {
                     num_replace += 1;
                 }
                 num_total += 1;
             }
-            if util::ask_confirmation(&app.borrow().window,
+            if dialog::ask_confirmation(&app.borrow().window,
             &format!("{} files will be replaced and {} new files will be added.\nIs this okay?",
             num_replace, num_total-num_replace)) {
-                for file in archive.dgc.data.into_iter().flat_map(|chunk| chunk.data.into_iter()) {
-                    let fdata = ArchiveFile {
-                        data: file.data,
-                        name: archive.ngc.names[&file.id1].to_owned(),
-                        subtypeid: archive.ngc.names[&file.id2].to_owned(),
-                        typeid: archive.ngc.names[&file.type_id].to_owned(),
-                    };
-                    page.archive.add(fdata);
+                for file in files {
+                    page.archive.add(file);
                 }
             } else {
                 return Ok(());
@@ -132,6 +224,55 @@ pub fn action_import_all(app: &Rc<RefCell<Application>>) -> CResult<()> {
     Ok(())
 }
 
+pub fn action_suggest_names(app: &Rc<RefCell<Application>>) -> CResult<()> {
+    let current_page = app.borrow().get_current_page().unwrap().clone();
+    let page = current_page.borrow();
+    let file = match page.get_active_file() {
+        Some(file) => file,
+        None => {
+            dialog::show_info(&app.borrow().window, "Select a file to suggest names for.");
+            return Ok(());
+        }
+    };
+    let endianness = page.archive.source.dgc.endianness;
+    let suggestions = app.borrow().names.suggest(&page.archive.data_of(&file.borrow())?, endianness);
+    let name = page.display_name(&file.borrow());
+    let msg = if suggestions.is_empty() {
+        format!("No known names were found in {}.", name)
+    } else {
+        let mut msg = format!("Known names found in {}:", name);
+        for suggestion in suggestions.iter().take(MAX_SUGGESTIONS) {
+            msg.push_str(&format!("\n+0x{:X}: {:08X} {}",
+                suggestion.offset, suggestion.id as u32, suggestion.name));
+        }
+        if suggestions.len() > MAX_SUGGESTIONS {
+            msg.push_str(&format!("\nand {} more", suggestions.len() - MAX_SUGGESTIONS));
+        }
+        msg
+    };
+    dialog::show_info(&app.borrow().window, &msg);
+    Ok(())
+}
+
+/// Let the user edit the legal notice in the header of the current archive.
+pub fn action_edit_header(app: &Rc<RefCell<Application>>) -> CResult<()> {
+    let current_page = app.borrow().get_current_page().unwrap().clone();
+    let notice = util::escape_bytes(current_page.borrow().archive.source.dgc.header.notice());
+    let text = dialog::ask_text(&app.borrow().window, "Edit Header",
+        "Legal notice, at most 256 bytes. Other bytes can be escaped as \\xNN.", &notice);
+    if let Some(text) = text {
+        if text != notice {
+            let mut page = current_page.borrow_mut();
+            page.archive.source.dgc.header.set_notice(&util::unescape_bytes(&text))?;
+            page.set_need_save(true);
+        }
+    }
+    Ok(())
+}
+
+/// The maximum number of name suggestions shown at once.
+const MAX_SUGGESTIONS: usize = 20;
+
 impl Application {
     /// Get the current page ID
     pub fn get_current_page_id(&self) -> Option<u32> {
@@ -144,6 +285,27 @@ impl Application {
                      .and_then(|id| self.pages.get(id as usize))
     }
 
+    /// Get the options that archives are packed with when saving, if they are repacked
+    pub fn get_pack_options(&self) -> Option<PackOptions> {
+        self.pack_strategy.map(|strategy| PackOptions {
+            strategy,
+            ..PackOptions::default()
+        })
+    }
+
+    /// Save the name dictionary if names were added to it.
+    pub fn save_names(&mut self) {
+        if !self.names.is_changed() {
+            return;
+        }
+        if let Some(path) = NameDictionary::default_path() {
+            if let Err(err) = self.names.save(&path) {
+                dialog::show_warning(&self.window,
+                    &format!("Could not save the name dictionary {}: {}", path.display(), err));
+            }
+        }
+    }
+
     /// Create a new application window
     pub fn new() -> Rc<RefCell<Application>> {
         // create window
@@ -168,9 +330,52 @@ impl Application {
         let item_saveas = gtk::MenuItem::new_with_label("Save As");
         let item_extract = gtk::MenuItem::new_with_label("Extract All Files");
         let item_import = gtk::MenuItem::new_with_label("Import Folder");
+        let item_suggest = gtk::MenuItem::new_with_label("Suggest Names");
+        let item_header = gtk::MenuItem::new_with_label("Edit Header");
+        let item_packing = gtk::MenuItem::new_with_label("Chunk Packing");
+        let menu_packing = gtk::Menu::new();
+        let item_keep = gtk::RadioMenuItem::new_with_label("Keep current layout");
+        item_keep.set_active(true);
+        menu_packing.append(&item_keep);
+        let mut packing_items = vec![(None, item_keep.clone())];
+        for &strategy in &PackStrategy::ALL {
+            let item = gtk::RadioMenuItem::new_with_label_from_widget(&item_keep, strategy.label());
+            menu_packing.append(&item);
+            packing_items.push((Some(strategy), item));
+        }
+        item_packing.set_submenu(Some(&menu_packing));
+        let item_layout = gtk::MenuItem::new_with_label("Extract Layout");
+        let menu_layout = gtk::Menu::new();
+        let item_flat = gtk::RadioMenuItem::new_with_label(ExtractLayout::Flat.label());
+        item_flat.set_active(true);
+        menu_layout.append(&item_flat);
+        let mut layout_items = vec![(ExtractLayout::Flat, item_flat.clone())];
+        for &layout in ExtractLayout::ALL.iter().skip(1) {
+            let item = gtk::RadioMenuItem::new_with_label_from_widget(&item_flat, layout.label());
+            menu_layout.append(&item);
+            layout_items.push((layout, item));
+        }
+        item_layout.set_submenu(Some(&menu_layout));
+        let item_policy = gtk::MenuItem::new_with_label("Merge Conflicts");
+        let menu_policy = gtk::Menu::new();
+        let item_ours = gtk::RadioMenuItem::new_with_label(MergePolicy::Ours.label());
+        item_ours.set_active(true);
+        menu_policy.append(&item_ours);
+        let mut policy_items = vec![(MergePolicy::Ours, item_ours.clone())];
+        for &policy in MergePolicy::ALL.iter().skip(1) {
+            let item = gtk::RadioMenuItem::new_with_label_from_widget(&item_ours, policy.label());
+            menu_policy.append(&item);
+            policy_items.push((policy, item));
+        }
+        item_policy.set_submenu(Some(&menu_policy));
         menu.append(&item_saveas);
         menu.append(&item_extract);
         menu.append(&item_import);
+        menu.append(&item_suggest);
+        menu.append(&item_header);
+        menu.append(&item_packing);
+        menu.append(&item_layout);
+        menu.append(&item_policy);
         menu.show_all();
         btn_menu.set_popup(Some(&menu));
         // Add notebook tabs to the application
@@ -180,33 +385,41 @@ impl Application {
         // Confirmation when the user closes the window
         let evdel_window = window.clone();
         window.connect_delete_event(move |_, _| {
-            if util::ask_confirmation(&evdel_window, "Are you sure you want to quit?") {
+            if dialog::ask_confirmation(&evdel_window, "Are you sure you want to quit?") {
                 gtk::main_quit();
                 Inhibit(false)
             } else {
                 Inhibit(true)
             }
         });
+        // load the name dictionary
+        let names = NameDictionary::default_path()
+            .and_then(|path| NameDictionary::load(&path).ok())
+            .unwrap_or_default();
         // create app
         let app = Rc::new(RefCell::new(Application {
-            window: window,
+            window,
             pages: Vec::new(),
             notebook: notebook.clone(),
             archive_buttons: vec![
                 btn_save.clone().upcast(), btn_menu.clone().upcast()],
             selected: 0,
+            pack_strategy: None,
+            extract_layout: ExtractLayout::default(),
+            merge_policy: MergePolicy::default(),
+            names,
         }));
         // handle open button
         let btn_open_app = Rc::downgrade(&app);
         btn_open.connect_clicked(move |_| {
             let app = btn_open_app.upgrade().unwrap();
-            util::handle_result(action_open_file(&app), "Error opening file", &app.borrow().window);
+            dialog::handle_result(action_open_file(&app), "Error opening file", &app.borrow().window);
         });
         // handle save button
         let btn_save_app = Rc::downgrade(&app);
         btn_save.connect_clicked(move |_| {
             let app = btn_save_app.upgrade().unwrap();
-            util::handle_result(action_save_file(&app), "Error saving file", &app.borrow().window);
+            dialog::handle_result(action_save_file(&app), "Error saving file", &app.borrow().window);
         });
         // These callbacks are needed in order to keep pages consistend with
         // the notebook tab order.
@@ -233,18 +446,55 @@ impl Application {
         let btn_saveas_app = Rc::downgrade(&app);
         item_saveas.connect_activate(move |_| {
             let app = btn_saveas_app.upgrade().unwrap();
-            util::handle_result(action_saveas(&app), "Error saving file", &app.borrow().window);
+            dialog::handle_result(action_saveas(&app), "Error saving file", &app.borrow().window);
         });
         let btn_import_app = Rc::downgrade(&app);
         item_import.connect_activate(move |_| {
             let app = btn_import_app.upgrade().unwrap();
-            util::handle_result(action_import_all(&app), "Error importing files", &app.borrow().window);
+            dialog::handle_result(action_import_all(&app), "Error importing files", &app.borrow().window);
         });
         let btn_extract_app = Rc::downgrade(&app);
         item_extract.connect_activate(move |_| {
             let app = btn_extract_app.upgrade().unwrap();
-            util::handle_result(action_extract_all(&app), "Error extracting files", &app.borrow().window);
+            dialog::handle_result(action_extract_all(&app), "Error extracting files", &app.borrow().window);
         });
+        let btn_suggest_app = Rc::downgrade(&app);
+        item_suggest.connect_activate(move |_| {
+            let app = btn_suggest_app.upgrade().unwrap();
+            dialog::handle_result(action_suggest_names(&app), "Error suggesting names", &app.borrow().window);
+        });
+        let btn_header_app = Rc::downgrade(&app);
+        item_header.connect_activate(move |_| {
+            let app = btn_header_app.upgrade().unwrap();
+            dialog::handle_result(action_edit_header(&app), "Error editing header", &app.borrow().window);
+        });
+        for (strategy, item) in packing_items {
+            let item_packing_app = Rc::downgrade(&app);
+            item.connect_toggled(move |item| {
+                if item.get_active() {
+                    let app = item_packing_app.upgrade().unwrap();
+                    app.borrow_mut().pack_strategy = strategy;
+                }
+            });
+        }
+        for (layout, item) in layout_items {
+            let item_layout_app = Rc::downgrade(&app);
+            item.connect_toggled(move |item| {
+                if item.get_active() {
+                    let app = item_layout_app.upgrade().unwrap();
+                    app.borrow_mut().extract_layout = layout;
+                }
+            });
+        }
+        for (policy, item) in policy_items {
+            let item_policy_app = Rc::downgrade(&app);
+            item.connect_toggled(move |item| {
+                if item.get_active() {
+                    let app = item_policy_app.upgrade().unwrap();
+                    app.borrow_mut().merge_policy = policy;
+                }
+            });
+        }
         // Update save button
         app.borrow().update_save_button();
         app
@@ -252,11 +502,7 @@ impl Application {
 
     // Disables the save button if there are no open files
     pub fn update_save_button(&self) {
-        let value = if let Some(_value) = self.notebook.get_current_page() {
-            true
-        } else {
-            false
-        };
+        let value = self.notebook.get_current_page().is_some();
 
         for btn in &self.archive_buttons {
             btn.set_sensitive(value);
@@ -287,7 +533,7 @@ impl Application {
         let btn_close_win = app.borrow().window.clone();
         let weakpg = Rc::downgrade(page);
         btn_close.connect_clicked(move |_| {
-            if util::ask_confirmation(&btn_close_win, "Are you sure you want to close this tab?") {
+            if dialog::ask_confirmation(&btn_close_win, "Are you sure you want to close this tab?") {
                 let page = weakpg.upgrade().unwrap();
                 page.borrow().container.destroy();
                 tab.destroy();
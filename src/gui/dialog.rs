@@ -0,0 +1,216 @@
+use gtk::prelude::*;
+use gtk::{self, FileChooserDialog, FileChooserAction, FileFilter, ResponseType};
+use std::path::{Path, PathBuf};
+use error::{CResult, Error};
+use util::ArchivePathPair;
+
+/// Opens any file, doesn't care about file types
+pub fn open_any<W>(base_path: &Path, prompt: &str, parent: &W, action: FileChooserAction)
+-> Option<PathBuf>
+where W: gtk::IsA<gtk::Window> {
+    open_named(base_path, prompt, parent, action, None)
+}
+
+/// Opens any file like `open_any`, and suggests the given file name when saving
+pub fn open_named<W>(base_path: &Path, prompt: &str, parent: &W, action: FileChooserAction,
+                     name: Option<&str>) -> Option<PathBuf>
+where W: gtk::IsA<gtk::Window> {
+    let btn: &str = match action {
+        FileChooserAction::Open | FileChooserAction::SelectFolder => &gtk::STOCK_OPEN,
+        _ => &gtk::STOCK_SAVE,
+    };
+    let dialog = FileChooserDialog::with_buttons(
+        Some(prompt),  Some(parent), action,
+        &[(&gtk::STOCK_CANCEL, ResponseType::Cancel), (btn, ResponseType::Accept)]);
+    match action {
+        FileChooserAction::Open | FileChooserAction::Save => {
+            let file_filter = FileFilter::new();
+            file_filter.add_pattern("*.*");
+            gtk::FileFilterExt::set_name(&file_filter, "Any file");
+            dialog.add_filter(&file_filter);
+        }
+        _ => {}
+    }
+    dialog.set_current_folder(base_path);
+    if let Some(name) = name {
+        dialog.set_current_name(name);
+    }
+
+    let result = match dialog.run().into() {
+        ResponseType::Accept => dialog.get_filename(),
+        _ => None
+    };
+
+    dialog.destroy();
+
+    result
+}
+
+/// Open a DGC file and construct a DGC/NGC file path pair
+pub fn open_gc<W>(base_path: &Path, parent: &W, action: FileChooserAction)
+-> Option<ArchivePathPair>
+where W: gtk::IsA<gtk::Window> {
+    let btn: &str = match action {
+        FileChooserAction::Open | FileChooserAction::SelectFolder => &gtk::STOCK_OPEN,
+        _ => &gtk::STOCK_SAVE,
+    };
+    let dialog = FileChooserDialog::with_buttons(
+        Some("Open File"),  Some(parent), action,
+        &[(&gtk::STOCK_CANCEL, ResponseType::Cancel), (btn, ResponseType::Accept)]);
+    match action {
+        FileChooserAction::Open | FileChooserAction::Save => {
+            let file_filter = FileFilter::new();
+            // Disc dumps may use any case for the extension
+            file_filter.add_pattern("*.[Dd][Gg][Cc]");
+            gtk::FileFilterExt::set_name(&file_filter, "DGC files");
+            dialog.add_filter(&file_filter);
+        }
+        _ => {}
+    }
+    dialog.set_current_folder(base_path);
+
+    let result = match dialog.run().into() {
+        ResponseType::Accept => dialog.get_filename().map(|dname| {
+            let dpath: PathBuf = dname;
+            ArchivePathPair::resolve(&dpath)
+        }),
+        _ => None
+    };
+
+    dialog.destroy();
+
+    result
+}
+
+/// Confirm that the user wants to perform an action.
+/// Returns true if the action should be performed.
+pub fn ask_confirmation<W>(parent: &W, msg: &str) -> bool
+where W: gtk::IsA<gtk::Window> {
+    let flags = gtk::DialogFlags::DESTROY_WITH_PARENT;
+    let dialog = gtk::MessageDialog::new(
+        Some(parent), flags, gtk::MessageType::Warning,
+        gtk::ButtonsType::YesNo, msg);
+    let value = dialog.run();
+    dialog.destroy();
+    matches!(value.into(), gtk::ResponseType::Yes)
+}
+
+/// Ask the user if they would like to merge with the exissting folder
+/// Some(true) to merge
+/// Some(false) to replace
+/// None to cancel
+pub fn ask_merge<W>(parent: &W) -> Option<bool>
+where W: gtk::IsA<gtk::Window> {
+    let flags = gtk::DialogFlags::DESTROY_WITH_PARENT;
+    let dialog = gtk::MessageDialog::new(
+        Some(parent), flags, gtk::MessageType::Warning,
+        gtk::ButtonsType::None,
+"The given folder is not empty.
+Do you want to merge with the existing folder?
+(Note: Replacing the folder will remove the files that were extracted to it)");
+    dialog.add_button("Replace", ResponseType::No.into());
+    dialog.add_button("Cancel", ResponseType::Cancel.into());
+    dialog.add_button("Merge", ResponseType::Yes.into());
+    let value = dialog.run();
+    dialog.destroy();
+    match value.into() {
+        gtk::ResponseType::Yes => Some(true),
+        gtk::ResponseType::No => Some(false),
+        _ => None,
+    }
+}
+
+/// Ask the user to confirm the given message, showing the given items in a scrollable list below
+/// it. Returns true if the user confirmed.
+pub fn ask_confirmation_list<W>(parent: &W, title: &str, msg: &str, items: &[String]) -> bool
+where W: gtk::IsA<gtk::Window> {
+    let flags = gtk::DialogFlags::DESTROY_WITH_PARENT | gtk::DialogFlags::MODAL;
+    let dialog = gtk::Dialog::new_with_buttons(
+        Some(title), Some(parent), flags,
+        &[("Cancel", ResponseType::Cancel.into()), ("OK", ResponseType::Ok.into())]);
+    dialog.set_default_response(ResponseType::Cancel.into());
+    let content = dialog.get_content_area();
+    content.set_spacing(4);
+    content.add(&gtk::Label::new(msg));
+    let scroll = gtk::ScrolledWindow::new(None, None);
+    scroll.set_min_content_height(200);
+    scroll.set_min_content_width(400);
+    let list = gtk::Label::new(items.join("\n").as_str());
+    list.set_selectable(true);
+    list.set_halign(gtk::Align::Start);
+    list.set_valign(gtk::Align::Start);
+    scroll.add(&list);
+    scroll.set_vexpand(true);
+    content.add(&scroll);
+    content.show_all();
+    let value = dialog.run();
+    dialog.destroy();
+    matches!(value.into(), ResponseType::Ok)
+}
+
+/// Ask the user to enter a line of text, starting with the given text.
+/// Returns None if the user cancelled.
+pub fn ask_text<W>(parent: &W, title: &str, msg: &str, text: &str) -> Option<String>
+where W: gtk::IsA<gtk::Window> {
+    let flags = gtk::DialogFlags::DESTROY_WITH_PARENT | gtk::DialogFlags::MODAL;
+    let dialog = gtk::Dialog::new_with_buttons(
+        Some(title), Some(parent), flags,
+        &[("Cancel", ResponseType::Cancel.into()), ("OK", ResponseType::Ok.into())]);
+    dialog.set_default_response(ResponseType::Ok.into());
+    let content = dialog.get_content_area();
+    content.set_spacing(4);
+    content.add(&gtk::Label::new(msg));
+    let entry = gtk::Entry::new();
+    entry.set_text(text);
+    entry.set_activates_default(true);
+    content.add(&entry);
+    content.show_all();
+    let result = match dialog.run().into() {
+        ResponseType::Ok => entry.get_text(),
+        _ => None,
+    };
+    dialog.destroy();
+    result
+}
+
+/// Handle the given result and show an error window if it is a Result::Err
+pub fn handle_result<W>(err: CResult<()>, base_msg: &str, parent: &W)
+where W: gtk::IsA<gtk::Window> {
+    match err {
+        Ok(_) => {},
+        Err(ref err) => show_error(err, base_msg, parent),
+    }
+}
+
+/// Show a warning to the user
+pub fn show_warning<W>(parent: &W, msg: &str)
+where W: gtk::IsA<gtk::Window> {
+    let flags = gtk::DialogFlags::DESTROY_WITH_PARENT;
+    let dialog = gtk::MessageDialog::new(
+        Some(parent), flags, gtk::MessageType::Warning,
+        gtk::ButtonsType::Ok, msg);
+    dialog.run();
+    dialog.destroy();
+}
+
+/// Show a message to the user
+pub fn show_info<W>(parent: &W, msg: &str)
+where W: gtk::IsA<gtk::Window> {
+    let flags = gtk::DialogFlags::DESTROY_WITH_PARENT;
+    let dialog = gtk::MessageDialog::new(
+        Some(parent), flags, gtk::MessageType::Info,
+        gtk::ButtonsType::Ok, msg);
+    dialog.run();
+    dialog.destroy();
+}
+
+/// Show an error to the user
+pub fn show_error<W>(err: &Error, base_msg: &str, parent: &W)
+where W: gtk::IsA<gtk::Window> {
+    let flags = gtk::DialogFlags::DESTROY_WITH_PARENT;
+    let dialog = gtk::MessageDialog::new(
+        Some(parent), flags, gtk::MessageType::Error,
+        gtk::ButtonsType::Ok, &format!("{}:\n{}", base_msg, err.chain()));
+    dialog.run();
+    dialog.destroy();
+}
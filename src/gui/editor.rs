@@ -1,23 +1,49 @@
 use gtk::{self, Widget};
 use gtk::prelude::*;
+use super::dialog;
 use super::page::{Page, ArchiveFile};
 use std::rc::Rc;
 use std::cell::RefCell;
-use util;
 use std::fs;
 use std::io;
-use ::CResult;
+use error::{CResult, Error, PathContext};
+use util;
+
+/// Show a warning icon on the given entry if its text has the same id as a different name in the
+/// page's archive.
+fn check_entry_name(entry: &gtk::Entry, page: &Page) {
+    let text = entry.get_text().unwrap_or_default();
+    let position = gtk::EntryIconPosition::Secondary;
+    match page.archive.check_name(&text) {
+        Some(collision) => {
+            entry.set_icon_from_icon_name(position, "dialog-warning");
+            entry.set_icon_tooltip_text(position, collision.to_string().as_str());
+        },
+        None => {
+            entry.set_icon_from_icon_name(position, None);
+            entry.set_icon_tooltip_text(position, None);
+        }
+    }
+}
 
 pub fn action_extract(page: &Rc<RefCell<Page>>, file: &Rc<RefCell<ArchiveFile>>) -> CResult<()> {
     let app = page.borrow().parent.upgrade().unwrap().clone();
     let window = app.borrow().window.clone();
     let path = page.borrow().paths.d.parent().unwrap().to_owned();
-    if let Some(path) = util::open_any(&path, "Extract file", &window, gtk::FileChooserAction::Save) {
+    let file_name = {
+        let file = file.borrow();
+        let page = page.borrow();
+        util::get_file_string(&page.display_name(&file), page.plugin_manager.get_file_extension(&file.typeid), 1)
+    };
+    if let Some(path) = dialog::open_named(&path, "Extract file", &window, gtk::FileChooserAction::Save,
+                                           Some(&file_name)) {
         let mut data = Vec::new();
         let file = file.borrow();
-        page.borrow().plugin_manager.export(&file.typeid, &mut &file.data[..], &mut data)?;
-        let mut fh = fs::File::create(path)?;
-        io::copy(&mut &data[..], &mut fh)?;
+        let page = page.borrow();
+        page.plugin_manager.export(&file.typeid, &mut &page.archive.data_of(&file)?[..], &mut data)
+            .map_err(|err| Error::codec(&file.name, &file.typeid, err))?;
+        let mut fh = fs::File::create(&path).at_path(&path)?;
+        io::copy(&mut &data[..], &mut fh).at_path(&path)?;
     }
     Ok(())
 }
@@ -26,13 +52,17 @@ pub fn action_replace(page: &Rc<RefCell<Page>>, file: &Rc<RefCell<ArchiveFile>>)
     let app = &page.borrow().parent.upgrade().unwrap();
     let window = app.borrow().window.clone();
     let path = page.borrow().paths.d.parent().unwrap().to_owned();
-    if let Some(path) = util::open_any(&path, "Open file", &window, gtk::FileChooserAction::Open) {
+    if let Some(path) = dialog::open_any(&path, "Open file", &window, gtk::FileChooserAction::Open) {
         let mut newvec = Vec::new();
-        let mut fh = fs::File::open(path)?;
-        page.borrow().plugin_manager.import(&file.borrow().typeid, &mut fh, &mut newvec)?;
+        let mut fh = fs::File::open(&path).at_path(&path)?;
+        {
+            let file = file.borrow();
+            page.borrow().plugin_manager.import(&file.typeid, &mut fh, &mut newvec)
+                .map_err(|err| Error::codec(&file.name, &file.typeid, err.with_path(&path)))?;
+        }
         // fh.read_to_end(&mut newvec)?;
-        file.borrow_mut().data = newvec;
-        Page::reset_file_editor(&page);
+        file.borrow_mut().data = Some(newvec);
+        Page::reset_file_editor(page);
     }
     page.borrow_mut().set_need_save(true);
     Ok(())
@@ -59,11 +89,14 @@ pub fn construct_editor(parent: Rc<RefCell<Page>>, file: Rc<RefCell<ArchiveFile>
     entry_type.set_text(&file.borrow().typeid);
     let entry_subtype = gtk::Entry::new();
     entry_subtype.set_hexpand(true);
-    entry_subtype.set_text(&file.borrow().subtypeid);
+    entry_subtype.set_text(file.borrow().subtypeid.as_deref().unwrap_or(""));
     grid.attach(&entry_name,    1, 0, 1, 1);
     grid.attach(&entry_type,    1, 1, 1, 1);
     grid.attach(&entry_subtype, 1, 2, 1, 1);
     hbox.add(&grid);
+    for entry in &[&entry_name, &entry_type, &entry_subtype] {
+        check_entry_name(entry, &parent.borrow());
+    }
     // Connect entries to change file name
     let fname = Rc::downgrade(&file);
     let pname = Rc::downgrade(&parent);
@@ -73,6 +106,7 @@ pub fn construct_editor(parent: Rc<RefCell<Page>>, file: Rc<RefCell<ArchiveFile>
         fname.borrow_mut().name = s.get_text().unwrap();
         pname.borrow_mut().set_file_name(id, &s.get_text().unwrap());
         pname.borrow_mut().set_need_save(true);
+        check_entry_name(s, &pname.borrow());
     });
     let ftype = Rc::downgrade(&file);
     let ptype = Rc::downgrade(&parent);
@@ -81,14 +115,17 @@ pub fn construct_editor(parent: Rc<RefCell<Page>>, file: Rc<RefCell<ArchiveFile>
         let ptype = ptype.upgrade().unwrap();
         ftype.borrow_mut().typeid = s.get_text().unwrap();
         ptype.borrow_mut().set_need_save(true);
+        check_entry_name(s, &ptype.borrow());
     });
     let fsubtype = Rc::downgrade(&file);
     let psubtype = Rc::downgrade(&parent);
     entry_subtype.connect_changed(move |s|{
         let fsubtype = fsubtype.upgrade().unwrap();
         let psubtype = psubtype.upgrade().unwrap();
-        fsubtype.borrow_mut().subtypeid = s.get_text().unwrap();
+        // An empty subtype means that the file has none
+        fsubtype.borrow_mut().subtypeid = s.get_text().filter(|text| !text.is_empty());
         psubtype.borrow_mut().set_need_save(true);
+        check_entry_name(s, &psubtype.borrow());
     });
     // create buttons
     let buttonbox = gtk::Box::new(gtk::Orientation::Horizontal, 4);
@@ -104,7 +141,7 @@ pub fn construct_editor(parent: Rc<RefCell<Page>>, file: Rc<RefCell<ArchiveFile>
         let pextract = pextract.upgrade().unwrap();
         let fextract = fextract.upgrade().unwrap();
         let app = pextract.borrow().parent.upgrade().unwrap().clone();
-        util::handle_result(action_extract(&pextract, &fextract), "Error saving file", &app.borrow().window);
+        dialog::handle_result(action_extract(&pextract, &fextract), "Error saving file", &app.borrow().window);
     });
     let freplace = Rc::downgrade(&file);
     let preplace = Rc::downgrade(&parent);
@@ -112,7 +149,7 @@ pub fn construct_editor(parent: Rc<RefCell<Page>>, file: Rc<RefCell<ArchiveFile>
         let preplace = preplace.upgrade().unwrap();
         let freplace = freplace.upgrade().unwrap();
         let app = &preplace.borrow().parent.upgrade().unwrap();
-        util::handle_result(action_replace(&preplace, &freplace), "Error opening file", &app.borrow().window);
+        dialog::handle_result(action_replace(&preplace, &freplace), "Error opening file", &app.borrow().window);
     });
     // Add editor plugin
     hbox.add(&parent.borrow().plugin_manager.create_editor(&parent, &file));
@@ -0,0 +1,24 @@
+use gtk::Widget;
+use gui::page::{Page, ArchiveFile};
+use plugin::PluginManager;
+use std::rc::Rc;
+use std::cell::RefCell;
+use ::CResult;
+
+pub mod text;
+
+/// A plugin that provides an editor gui for a file type.
+/// Editors are registered separately from codecs, so that file types can be
+/// imported and exported without any GUI code.
+pub trait EditorPlugin {
+    /// Create an editor gui for the given file
+    fn create_editor(&self, parent: &Rc<RefCell<Page>>, file: &Rc<RefCell<ArchiveFile>>) -> CResult<Widget>;
+    /// Get the plugin type string. This should match the string of the codec
+    /// plugin that handles the same file types.
+    fn get_plugin_string(&self) -> &'static str;
+}
+
+/// Register all of the built-in editors with the given plugin manager
+pub fn register_editors(manager: &mut PluginManager) {
+    manager.register_editor(Box::new(text::EditorLengthText));
+}
@@ -0,0 +1,70 @@
+use gtk::{self, Widget};
+use gtk::prelude::*;
+use gui::page::{Page, ArchiveFile};
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::io::Write;
+use ::CResult;
+use std::str;
+use super::EditorPlugin;
+
+/// An editor for text files with the following format:
+/// size: u32;
+/// data: char[size];
+pub struct EditorLengthText;
+
+impl EditorPlugin for EditorLengthText {
+    fn create_editor(&self, parent: &Rc<RefCell<Page>>, file: &Rc<RefCell<ArchiveFile>>) -> CResult<Widget> {
+        let scroll = gtk::ScrolledWindow::new(None, None);
+        scroll.set_margin_start(4);
+        scroll.set_margin_end(4);
+        scroll.set_margin_top(4);
+        scroll.set_margin_bottom(4);
+        scroll.set_hexpand(true);
+        scroll.set_vexpand(true);
+        let text = gtk::TextView::new();
+        text.set_editable(true);
+        let endianness = parent.borrow().archive.source.dgc.endianness;
+        {
+            let bfile = file.borrow();
+            let page = parent.borrow();
+            let data = page.archive.data_of(&bfile)?;
+            let mut slice = &data[..];
+            let _size = endianness.read_u32(&mut slice)? as usize;
+            // If the string can not be converted from utf8, OR if the string
+            // contains any null characters, then the text box should not be
+            // editable since that would destroy data.
+            let mut s: String = if let Ok(s) = str::from_utf8(slice) {
+                s.to_owned()
+            } else {
+                text.set_editable(false);
+                String::from_utf8_lossy(slice).to_string()
+            };
+            if s.contains('\x00') {
+                s = s.replace('\x00', "\u{FFFD}");
+                text.set_editable(false);
+            }
+            text.get_buffer().unwrap().set_text(&s);
+        }
+        let ftext = Rc::downgrade(file);
+        let ptext = Rc::downgrade(parent);
+        text.get_buffer().unwrap().connect_changed(move |b| {
+            let ftext = ftext.upgrade().unwrap();
+            let ptext = ptext.upgrade().unwrap();
+            let mut vec = Vec::new();
+            let text: String = b.get_text(&b.get_start_iter(), &b.get_end_iter(), true).unwrap();
+            endianness.write_u32(&mut vec, text.len() as u32).unwrap();
+            vec.write_all(text.as_ref()).unwrap();
+            ftext.borrow_mut().data = Some(vec);
+            ptext.borrow_mut().set_need_save(true);
+        });
+
+        scroll.add(&text);
+
+        Ok(scroll.upcast::<Widget>())
+    }
+
+    fn get_plugin_string(&self) -> &'static str {
+        "length-text"
+    }
+}
@@ -2,11 +2,13 @@ use gtk;
 use gtk::prelude::*;
 
 pub mod app;
+pub mod dialog;
 pub mod page;
 pub mod editor;
+pub mod editors;
 
 pub fn begin() -> super::CResult<()> {
-    gtk::init()?;
+    gtk::init().map_err(|err| super::Error::Gui(err.to_string()))?;
 
     let app = app::Application::new();
     app.borrow().window.show_all();
@@ -1,31 +1,55 @@
 use gtk::{self, Container, Label, ScrolledWindow, Paned, ListBox};
 use gtk::prelude::*;
-use ::CResult;
-use util::{self, ArchivePathPair};
-use ngc::NgcArchive;
-use dgc::{DgcArchive, DgcFile};
+use error::CResult;
+use util::{self, ArchivePathPair, ChumArchive, ChumReader};
+use names::NameOverlay;
+use ngc::{NameDiagnostic, NgcArchive};
+use dgc::{DgcArchive, DgcChunk, DgcFile, DgcReader, FileKey, PackOptions};
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::fs::File;
+use std::io::BufReader;
 use std::rc::{Rc, Weak};
 use std::cell::RefCell;
+use std::cmp::Ordering;
 use super::editor;
+use super::editors;
 use super::app::Application;
 use plugin;
 
 /// Single DGC file, but with IDs replaced with names.
 pub struct ArchiveFile {
-    pub data: Vec<u8>,
+    /// Data of this file if it was changed or added since the archive was last synced. Otherwise
+    /// the file's data is only kept in the source archive, or is not read yet, see
+    /// `Archive::data_of`.
+    pub data: Option<Vec<u8>>,
     pub name: String,
     pub typeid: String,
-    pub subtypeid: String,
+    /// Subtype of this file. Files without a subtype use their own id as their subtype.
+    pub subtypeid: Option<String>,
+    /// Ids of the entry in the source archive that this file was read from, or None if the file
+    /// was added since. Several files can share a name, so this is how the file is found again.
+    pub key: Option<FileKey>,
+}
+
+impl ArchiveFile {
+    /// Return true if both files have the same type, subtype and name.
+    pub fn same_entry(&self, other: &ArchiveFile) -> bool {
+        self.name == other.name && self.typeid == other.typeid && self.subtypeid == other.subtypeid
+    }
 }
 
 /// Represents a DGC/NGC archive pair, except files include their name and type information as
 /// strings. Files are also given the Rc+RefCell pattern so that they can be shared by editors.
 pub struct Archive {
     pub files: Vec<Rc<RefCell<ArchiveFile>>>,
-    pub header: String,
+    /// The archive that was opened. Changes to the file list are applied to it in place, so that
+    /// files that were not changed keep their place in the archive. Until the archive is synced,
+    /// and again once it is saved, its files and chunks are only a layout without data, which is
+    /// read from `reader` on demand.
+    pub source: ChumArchive,
+    reader: Option<RefCell<DgcReader<BufReader<File>>>>,
 }
-
 /// Represents a single archive file page.
 pub struct Page {
     pub paths: ArchivePathPair,
@@ -37,95 +61,209 @@ pub struct Page {
     pub tool: gtk::Box,
     pub need_save: bool,
     pub plugin_manager: plugin::PluginManager,
+    /// Names that the name dictionary found for ids that the archive has no name for. These are
+    /// only shown, and never saved to the archive.
+    pub display_names: NameOverlay,
     stop_recurse: bool,
 }
 
 impl Archive {
-    /// Create an archive from a DGC/NGC file pair
-    pub fn from_archives(data: DgcArchive, names: NgcArchive) -> Archive {
-        Archive {
-            header: String::from_utf8_lossy(&data.header.legal_notice).into(),
-            files: data.data.into_iter().flat_map(|chunk| chunk.data.into_iter()).map(|f| {
-                let name = names.names[&f.id1].clone();
-                let mut subtypeid = names.names[&f.id2].clone();
-                if name == subtypeid {
-                    subtypeid = "".to_string();
-                }
-                Rc::new(RefCell::new(ArchiveFile {
-                    name: name,
-                    typeid: names.names[&f.type_id].clone(),
-                    subtypeid: subtypeid,
-                    data: f.data,
-                }))
+    /// Create an archive from a DGC/NGC file pair. Only the headers of the files are read; their
+    /// data is read when it is first needed.
+    pub fn from_reader(reader: ChumReader) -> Archive {
+        let files = reader.dgc.iter_files().map(|f| {
+            Rc::new(RefCell::new(ArchiveFile {
+                name: reader.ngc.resolve(f.id1),
+                typeid: reader.ngc.resolve(f.type_id),
+                subtypeid: reader.ngc.resolve_subtype(f.id1, f.id2),
+                data: None,
+                key: Some(f.key()),
+            }))
+        }).collect();
+        let mut archive = Archive {
+            files,
+            source: ChumArchive {
+                dgc: DgcArchive::new("", 0),
+                ngc: NgcArchive::new(),
+            },
+            reader: None,
+        };
+        archive.reopen(reader);
+        archive
+    }
+
+    /// Replace the source archive with the given DGC/NGC file pair, which must have the same
+    /// entries as the synced source archive, e.g. because it was just saved. The data that was
+    /// read into the source archive is dropped, and read from the files on demand again.
+    fn reopen(&mut self, reader: ChumReader) {
+        let dgcr = reader.dgc;
+        let layout = DgcArchive {
+            header: dgcr.header.clone(),
+            data: dgcr.chunks.iter().map(|chunk| DgcChunk {
+                data: chunk.files.iter().map(|f| DgcFile {
+                    data: Vec::new(),
+                    type_id: f.type_id,
+                    id1: f.id1,
+                    id2: f.id2,
+                }).collect(),
+                padding: Vec::new(),
             }).collect(),
+            chunk_size: dgcr.chunk_size,
+            endianness: dgcr.endianness,
+        };
+        self.source = ChumArchive {
+            dgc: layout,
+            ngc: reader.ngc,
+        };
+        self.reader = Some(RefCell::new(dgcr));
+    }
+
+    /// Get the data of the given file: its changed data if it has any, otherwise its data in the
+    /// source archive, which is read from the archive's file if the archive was not synced yet.
+    pub fn data_of<'a>(&'a self, file: &'a ArchiveFile) -> CResult<Cow<'a, [u8]>> {
+        let key = match (&file.data, file.key) {
+            (Some(data), _) => return Ok(Cow::Borrowed(data)),
+            (None, Some(key)) => key,
+            (None, None) => return Ok(Cow::Borrowed(&[])),
+        };
+        if let Some(reader) = &self.reader {
+            let mut reader = reader.borrow_mut();
+            let header = reader.iter_files().find(|f| f.key() == key).cloned();
+            return match header {
+                Some(header) => Ok(Cow::Owned(reader.read_data(&header)?)),
+                None => Ok(Cow::Borrowed(&[])),
+            };
+        }
+        Ok(Cow::Borrowed(self.source.dgc.get_file(key).map_or(&[], |f| &f.data[..])))
+    }
+
+    /// Read the data of every file into the source archive, if it was not read yet. The header
+    /// of the source archive is kept, since it may have been edited. The data is kept until the
+    /// archive is saved, see `Page::save`.
+    fn load(&mut self) -> CResult<()> {
+        if let Some(reader) = &self.reader {
+            let mut dgc = reader.borrow_mut().read_archive()?;
+            dgc.header = self.source.dgc.header.clone();
+            self.source.dgc = dgc;
+        }
+        self.reader = None;
+        Ok(())
+    }
+
+    /// Apply the changes made to the file list to the source archive, and return it.
+    /// Files that were removed from the list are removed from the archive, and files that were
+    /// added are added to the archive, sorted by type, subtype and name. Files that were changed
+    /// are updated in place. Changed data is moved into the source archive, so that afterwards
+    /// it is the only copy of every file's data. This reads every file, so only sync to save or
+    /// extract the archive.
+    pub fn sync(&mut self) -> CResult<&mut ChumArchive> {
+        self.load()?;
+        let source = &mut self.source;
+        let mut files: Vec<_> = self.files.iter().map(|f| f.borrow_mut()).collect();
+        files.sort_by(|a, b| compare_files(a, b));
+        let keys: HashSet<FileKey> = files.iter().filter_map(|f| f.key).collect();
+        let removed: Vec<FileKey> = source.dgc.iter_files()
+            .map(|f| f.key())
+            .filter(|key| !keys.contains(key))
+            .collect();
+        for key in removed {
+            source.remove_file(key);
+        }
+        for file in &mut files {
+            let subtype = file.subtypeid.clone();
+            let subtype = subtype.as_deref();
+            let mut key = match file.key {
+                Some(key) => key,
+                None => {
+                    let data = file.data.clone().unwrap_or_default();
+                    source.add_file(&file.typeid, subtype, &file.name, data)?;
+                    file.key = Some(util::entry_key(&file.typeid, subtype, &file.name));
+                    file.data = None;
+                    continue;
+                }
+            };
+            // Compare names rather than ids, so that names whose listed id is not their hash
+            // keep their id unless they were edited
+            if file.name != source.ngc.resolve(key.id1) {
+                let id1 = source.rename_file(key, &file.name)?;
+                key = key.with_id1(id1);
+                file.key = Some(key);
+            }
+            if file.typeid != source.ngc.resolve(key.type_id)
+                    || file.subtypeid != source.ngc.resolve_subtype(key.id1, key.id2) {
+                key = source.retype_file(key, &file.typeid, subtype)?;
+                file.key = Some(key);
+            }
+            if let Some(data) = file.data.take() {
+                if source.dgc.get_file(key).is_some_and(|existing| existing.data != data) {
+                    source.replace_data(key, data);
+                }
+            }
+        }
+        Ok(source)
+    }
+
+    /// Check whether the given name has the same id as a different name that is used by this
+    /// archive. Returns the collision if it does.
+    pub fn check_name(&self, name: &str) -> Option<NameDiagnostic> {
+        if name.is_empty() || util::parse_placeholder(name).is_some() {
+            return None;
         }
+        let id = util::hash_name(name);
+        let collides = |other: &str| other != name && util::hash_name(other) == id
+            && util::parse_placeholder(other).is_none();
+        let model_names = self.files.iter().flat_map(|f| {
+            let f = f.borrow();
+            let mut names = vec![f.name.clone(), f.typeid.clone()];
+            names.extend(f.subtypeid.clone());
+            names
+        });
+        let source_names = self.source.ngc.names.values().cloned();
+        model_names.chain(source_names)
+            .find(|other| collides(other))
+            .map(|other| NameDiagnostic::Collision {
+                id,
+                kept: other,
+                dropped: name.to_owned(),
+            })
     }
 
-    /// Create a DGC/NGC file pair from this archive
-    pub fn into_archives(&self) -> (DgcArchive, NgcArchive) {
-        let mut dgc = DgcArchive::new(&self.header, 0);
-        let mut ngc = NgcArchive::new();
+    /// Get every pair of distinct names that share an id, sorted by id: the collisions in the
+    /// source archive, and the names of the file list that collide, see `check_name`. The source
+    /// archive is not synced.
+    pub fn collisions(&self) -> Vec<NameDiagnostic> {
+        let mut collisions = self.source.collisions();
         for file in &self.files {
             let file = file.borrow();
-            let id1: i32 = util::hash_name(&file.name);
-            let (id2, subtypeid): (i32, String) = if file.subtypeid == "" {
-                (id1, file.name.clone())
-            } else {
-                (util::hash_name(&file.subtypeid), file.subtypeid.clone())
-            };
-            let type_id: i32 = util::hash_name(&file.typeid);
-            dgc.add_file(DgcFile {
-                data: file.data.clone(),
-                id1: id1,
-                id2: id2,
-                type_id: type_id,
-            });
-            ngc.names.insert(id1, file.name.clone());
-            ngc.names.insert(id2, subtypeid);
-            ngc.names.insert(type_id, file.typeid.clone());
+            let names = Some(&file.name).into_iter().chain(Some(&file.typeid)).chain(&file.subtypeid);
+            collisions.extend(names.filter_map(|name| self.check_name(name)));
         }
-        (dgc, ngc)
+        collisions.sort_by_key(NameDiagnostic::id);
+        collisions.dedup_by_key(|collision| collision.id());
+        collisions
     }
 
     /// Sort all of the files in this archive by name
     pub fn sort_files(&mut self) {
-        self.files.sort_by(|a, b| {
-            let a = a.borrow();
-            let b = b.borrow();
-            if a.typeid != b.typeid {
-                a.typeid.cmp(&b.typeid)
-            } else if a.subtypeid != b.subtypeid {
-                a.subtypeid.cmp(&b.subtypeid)
-            } else {
-                a.name.cmp(&b.name)
-            }
-        });
+        self.files.sort_by(|a, b| compare_files(&a.borrow(), &b.borrow()));
     }
 
-    /// Return true if the file exists
-    pub fn exists(&self, name: &str) -> bool{
-        for f in &self.files {
-            if f.borrow().name == name {
-                return true;
-            }
-        }
-        false
+    /// Find the file with the same type, subtype and name as the given file
+    pub fn find_entry(&self, file: &ArchiveFile) -> Option<usize> {
+        self.files.iter().position(|f| f.borrow().same_entry(file))
     }
 
-    /// Find the file in the archive
-    pub fn find(&self, name: &str) -> Option<usize> {
-        for i in 0..self.files.len() {
-            if self.files[i].borrow().name == name {
-                return Some(i);
-            }
-        }
-        return None;
+    /// Find the given file in the archive
+    pub fn find(&self, file: &Rc<RefCell<ArchiveFile>>) -> Option<usize> {
+        self.files.iter().position(|f| Rc::ptr_eq(f, file))
     }
 
     /// Add the file to this archive
-    /// Returns the file that was replaced by this file
-    pub fn add(&mut self, file: ArchiveFile) -> Option<ArchiveFile> {
-        if let Some(i) = self.find(&file.name) {
+    /// Returns the file with the same type, subtype and name that was replaced by this file
+    pub fn add(&mut self, mut file: ArchiveFile) -> Option<ArchiveFile> {
+        if let Some(i) = self.find_entry(&file) {
+            // The replaced file's entry in the source archive is now this file's entry
+            file.key = self.files[i].borrow().key;
             Some(self.files[i].replace(file))
         } else {
             self.files.push(Rc::new(RefCell::new(file)));
@@ -134,6 +272,17 @@ impl Archive {
     }
 }
 
+/// Order files by type, then subtype, then name
+fn compare_files(a: &ArchiveFile, b: &ArchiveFile) -> Ordering {
+    if a.typeid != b.typeid {
+        a.typeid.cmp(&b.typeid)
+    } else if a.subtypeid != b.subtypeid {
+        a.subtypeid.cmp(&b.subtypeid)
+    } else {
+        a.name.cmp(&b.name)
+    }
+}
+
 impl Page {
     /// Set whether or not this file needs to be saved
     pub fn set_need_save(&mut self, new_need_save: bool) {
@@ -152,11 +301,12 @@ impl Page {
     /// Create a new archive page
     pub fn new(parent: &Rc<RefCell<Application>>, paths: ArchivePathPair) -> CResult<Rc<RefCell<Page>>> {
         let label = Label::new(paths.d.file_name().unwrap().to_str().unwrap());
-        // load files
-        let mut name_file = File::open(&paths.n)?;
-        let mut data_file = File::open(&paths.d)?;
-        let dgca = DgcArchive::read_from(&mut data_file)?;
-        let ngca = NgcArchive::read_from(&mut name_file)?;
+        // index files, their data is read on demand
+        let reader = ChumReader::open_pair(&paths)?;
+        let endianness = reader.dgc.endianness;
+        // name the ids that the .NGC file has no name for from the name dictionary
+        let missing = reader.missing_ids();
+        let display_names = parent.borrow_mut().names.resolve_missing(&reader.ngc, &missing);
         // create pane
         let pane = Paned::new(gtk::Orientation::Horizontal);
         let list_scroll = ScrolledWindow::new(None, None);
@@ -168,17 +318,21 @@ impl Page {
         pane.pack2(&tool, true, false);
         tool.set_size_request(64, 64);
         tool.set_property_expand(true);
+        // create plugin manager with both codecs and editors
+        let mut plugin_manager = plugin::PluginManager::with_endianness(endianness);
+        editors::register_editors(&mut plugin_manager);
         // create page
         let page = Rc::new(RefCell::new(Page {
-            paths: paths,
+            paths,
             container: pane.clone().upcast::<Container>(),
             label: label.clone(),
             list: list.clone(),
-            archive: Archive::from_archives(dgca, ngca),
+            archive: Archive::from_reader(reader),
             parent: Rc::downgrade(parent),
-            tool: tool,
+            tool,
             need_save: true,
-            plugin_manager: plugin::PluginManager::new(),
+            plugin_manager,
+            display_names,
             stop_recurse: false,
         }));
         Page::update_file_list(&page);
@@ -236,7 +390,7 @@ impl Page {
         // Generate new, better files
         for file in &page.archive.files {
             let file = file.borrow();
-            let row_label = Label::new(file.name.as_str());
+            let row_label = Label::new(page.display_name(&file).as_str());
             row_label.set_justify(gtk::Justification::Left);
             row_label.set_halign(gtk::Align::Start);
             list.add(&row_label);
@@ -254,11 +408,19 @@ impl Page {
             // let row_label = Label::new(file.name.as_str());
             let row_label = page.list.get_row_at_index(i as i32)
                 .unwrap().get_children()[0].clone().downcast::<Label>().unwrap();
-            row_label.set_text(file.name.as_str());
+            row_label.set_text(&page.display_name(&file));
         }
         page.list.show_all();
     }
 
+    /// Get the name to show for the given file: the name that the name dictionary found for it if
+    /// the archive has no name for it, otherwise its own name.
+    pub fn display_name(&self, file: &ArchiveFile) -> String {
+        util::parse_placeholder(&file.name)
+            .and_then(|id| self.display_names.get(id))
+            .map_or_else(|| file.name.clone(), str::to_owned)
+    }
+
     pub fn get_active_file(&self) -> Option<Rc<RefCell<ArchiveFile>>> {
         let row = self.list.get_selected_row();
         row.map(|row| self.archive.files[row.get_index() as usize].clone())
@@ -267,7 +429,7 @@ impl Page {
     pub fn set_active_file(page: &Rc<RefCell<Page>>, file: Option<&Rc<RefCell<ArchiveFile>>>) {
         let row = {
             let page = page.borrow();
-            let rowid = file.and_then(|file| page.archive.find(&file.borrow().name));
+            let rowid = file.and_then(|file| page.archive.find(file));
             rowid.and_then(|id| page.list.get_row_at_index(id as i32))
         };
         let list = page.borrow().list.clone();
@@ -277,18 +439,25 @@ impl Page {
     /// Set the name of the given file in the file list
     pub fn set_file_name(&self, id: i32, name: &str) {
         let list = self.list.clone();
-        let widget = list.get_row_at_index(id).unwrap().get_children().get(0).unwrap().clone();//.dynamic_cast::<Label>();//.unwrap().set_text(name);
+        let widget = list.get_row_at_index(id).unwrap().get_children().first().unwrap().clone();//.dynamic_cast::<Label>();//.unwrap().set_text(name);
         widget.downcast::<Label>().unwrap().set_text(name);
     }
 
-    /// Save the archive
-    pub fn save(&mut self) -> CResult<()> {
-        let (dgc, ngc) = self.archive.into_archives();
-
-        let mut name_file = File::create(&self.paths.n)?;
-        let mut data_file = File::create(&self.paths.d)?;
-        dgc.write_to(&mut data_file)?;
-        ngc.write_to(&mut name_file)?;
+    /// Save the archive. If packing options are given, then every file is packed into new
+    /// chunks; otherwise only the files that changed are moved. Refuses to save if distinct names
+    /// share an id, unless `force` is true, in which case only one name of each pair is saved.
+    pub fn save(&mut self, options: Option<&PackOptions>, force: bool) -> CResult<()> {
+        let archive = self.archive.sync()?;
+        if let Some(options) = options {
+            archive.dgc.pack(options)?;
+        }
+        if force {
+            archive.save_pair_unchecked(&self.paths)?;
+        } else {
+            archive.save_pair(&self.paths)?;
+        }
+        // read the saved files on demand, rather than keeping every file's data
+        self.archive.reopen(ChumReader::open_pair(&self.paths)?);
 
         self.set_need_save(false);
 
@@ -296,12 +465,13 @@ impl Page {
     }
 
     /// Save the archive as another file
-    pub fn save_as(&mut self, new_path: ArchivePathPair) -> CResult<()> {
+    pub fn save_as(&mut self, new_path: ArchivePathPair, options: Option<&PackOptions>,
+                   force: bool) -> CResult<()> {
         let prev_path = self.paths.clone();
         self.paths = new_path;
-        let result = self.save();
+        let result = self.save(options, force);
         // If there's an error, revert to previous path
-        if let Err(_) = &result {
+        if result.is_err() {
             self.paths = prev_path;
         }
         result
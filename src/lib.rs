@@ -0,0 +1,27 @@
+//! Library for reading, writing and editing Revenge of the Flying Dutchman
+//! .DGC/.NGC archives.
+//!
+//! The `chum_world` binary is a thin command line and GUI front end over this
+//! library. The GTK interface is only built when the `gui` feature (enabled by
+//! default) is turned on.
+extern crate byteorder;
+extern crate crc;
+#[cfg(feature = "gui")]
+extern crate gtk;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
+pub mod dgc;
+pub mod error;
+pub mod extract;
+#[cfg(feature = "gui")]
+pub mod gui;
+pub mod names;
+pub mod ngc;
+pub mod plugin;
+pub mod util;
+
+pub use error::{CResult, Error};
+pub use util::ChumArchive;
@@ -1,71 +1,126 @@
-#![windows_subsystem = "windows"]
-extern crate byteorder;
+#![cfg_attr(feature = "gui", windows_subsystem = "windows")]
+extern crate chum_world;
 extern crate clap;
-extern crate crc;
-extern crate gtk;
-extern crate serde;
-#[macro_use]
-extern crate serde_derive;
-extern crate serde_json;
-
-pub mod dgc;
-pub mod extract;
-pub mod gui;
-pub mod ngc;
-pub mod plugin;
-pub mod util;
 
+use chum_world::dgc::{DgcFileHeader, PackOptions, PackStrategy};
+use chum_world::extract;
+#[cfg(feature = "gui")]
+use chum_world::gui;
+use chum_world::names::{NameDictionary, NameOverlay};
+use chum_world::error::{CResult, Error, PathContext};
+use chum_world::ngc::{NameCollisionError, NgcArchive};
+use chum_world::util::{self, ArchivePathPair, ChumArchive, ChumReader};
 use std::cmp;
-use std::error;
 use std::fs::{self, File};
-use std::path::{Path, PathBuf};
-use util::{CResult, ChumArchive};
-
-fn load_archive(path: &Path) -> CResult<ChumArchive> {
-    let path = PathBuf::from(path);
-    let ngc_path = path.with_extension("NGC");
-    let dgc_path = path.with_extension("DGC");
-
-    let mut name_file = File::open(ngc_path)?;
-    let mut data_file = File::open(dgc_path)?;
-    let dgca = dgc::DgcArchive::read_from(&mut data_file)?;
-    let ngca = ngc::NgcArchive::read_from(&mut name_file)?;
-
-    Ok(ChumArchive {
-        dgc: dgca,
-        ngc: ngca
-    })
+use std::io::BufReader;
+use std::path::Path;
+
+/// Load the name dictionary, and add the word lists given on the command line to it. Names that
+/// are only in those word lists are not saved to the dictionary.
+fn load_dictionary(matches: &clap::ArgMatches) -> CResult<NameDictionary> {
+    let mut names = match NameDictionary::default_path() {
+        Some(path) => NameDictionary::load(&path).unwrap_or_else(|err| {
+            eprintln!("Warning: could not load the name dictionary {}: {}", path.display(), err);
+            NameDictionary::new()
+        }),
+        None => NameDictionary::new(),
+    };
+    if let Some(paths) = matches.values_of_os("names") {
+        for path in paths {
+            let file = File::open(path).at_path(path)?;
+            names.add_session_word_list(BufReader::new(file)).at_path(path)?;
+        }
+    }
+    Ok(names)
+}
+
+/// Save the name dictionary if any names were added to it.
+fn save_dictionary(names: &mut NameDictionary) {
+    if !names.is_changed() {
+        return;
+    }
+    if let Some(path) = NameDictionary::default_path() {
+        if let Err(err) = names.save(&path) {
+            eprintln!("Warning: could not save the name dictionary {}: {}", path.display(), err);
+        }
+    }
+}
+
+/// Learn the names of the given .NGC archive, then use the name dictionary to name the given ids
+/// that it has no name for. Warns the user about ids that still have no name, and about
+/// problems with the archive's names. The names that were found are only for showing to the
+/// user and naming extracted files, and are not added to the archive.
+fn resolve_names(names: &mut NameDictionary, ngc: &NgcArchive, missing: &[i32]) -> NameOverlay {
+    for diagnostic in ngc.diagnostics() {
+        eprintln!("Warning: {}", diagnostic);
+    }
+    let found = names.resolve_missing(ngc, missing);
+    if !found.is_empty() {
+        eprintln!("Found {} missing names in the name dictionary", found.len());
+    }
+    let missing: Vec<i32> = missing.iter().filter(|&&id| !found.contains(id)).cloned().collect();
+    if !missing.is_empty() {
+        eprintln!("Warning: {}", util::describe_missing_ids(&missing));
+    }
+    found
+}
+
+/// The --dgc and --ngc options, which give the paths of an archive's files explicitly.
+fn archive_path_args<'a, 'b>() -> [clap::Arg<'a, 'b>; 2] {
+    [
+        clap::Arg::with_name("dgc")
+            .help("Path of the .DGC file, if it does not share a file stem with the .NGC file")
+            .long("dgc")
+            .takes_value(true),
+        clap::Arg::with_name("ngc")
+            .help("Path of the .NGC file, if it does not share a file stem with the .DGC file")
+            .long("ngc")
+            .takes_value(true),
+    ]
+}
+
+/// Get the archive files given by the argument with the given name, and by the --dgc and --ngc
+/// options, which take precedence.
+fn archive_paths(matches: &clap::ArgMatches, arg: &str) -> CResult<ArchivePathPair> {
+    ArchivePathPair::from_parts(
+        matches.value_of_os(arg).map(Path::new),
+        matches.value_of_os("dgc").map(Path::new),
+        matches.value_of_os("ngc").map(Path::new))
 }
 
 /// Info command.
 /// Gets information about the given archive.
 fn cmd_info(matches: &clap::ArgMatches) -> CResult<()> {
-    let archive = load_archive(Path::new(matches.value_of_os("FILE").unwrap()))?;
+    let archive = ChumReader::open_pair(&archive_paths(matches, "FILE")?)?;
 
     let chunk_size = archive.dgc.chunk_size;
     let mut max_file_size = 0usize;
-    let mut min_file_size = usize::max_value();
+    let mut min_file_size = usize::MAX;
     let mut num_files = 0;
     let mut total_size = 0;
-    for i in 0..archive.dgc.data.len() {
-        let chunk = &archive.dgc.data[i];
+    for i in 0..archive.dgc.chunks.len() {
+        let chunk = &archive.dgc.chunks[i];
         let mut chunk_total_size = 0;
-        for f in &chunk.data {
-            chunk_total_size += f.data.len();
-            total_size += f.data.len();
+        for f in &chunk.files {
+            chunk_total_size += f.data_size;
+            total_size += f.data_size;
             num_files += 1;
-            max_file_size = cmp::max(max_file_size, f.data.len());
+            max_file_size = cmp::max(max_file_size, f.data_size);
             min_file_size = if min_file_size == 0 {
-                f.data.len()
+                f.data_size
             } else {
-                cmp::min(min_file_size, f.data.len())
+                cmp::min(min_file_size, f.data_size)
             }
         }
         let padding_size = chunk_size - chunk_total_size;
         println!("Chunk {:>3}: {:>3} files {:>8}B data {:>8}B padding", i,
-                 chunk.data.len(), chunk_total_size, padding_size);
+                 chunk.files.len(), chunk_total_size, padding_size);
+    }
+    println!("Chunk size: {}B ({0:X}), {}", chunk_size, archive.dgc.endianness.name());
+    if num_files == 0 {
+        println!("Total size: 0B, num files: 0");
+        return Ok(());
     }
-    println!("Chunk size: {}B ({0:X})", chunk_size);
     let average_size = total_size / num_files;
     println!("Total size: {}B, num files: {}, average file size: {}B", total_size, num_files, average_size);
     println!("Minimum size: {}B, Maximum size: {}B", min_file_size, max_file_size);
@@ -75,19 +130,19 @@ fn cmd_info(matches: &clap::ArgMatches) -> CResult<()> {
 
 /// List command.
 /// Lists all of the files in the given archive.
-fn cmd_list(matches: &clap::ArgMatches) -> CResult<()> {
-    let archive = load_archive(Path::new(matches.value_of_os("FILE").unwrap()))?;
-    let id_lookup = &archive.ngc.names;
-    for chunk in archive.dgc.data {
-        for file in chunk.data {
-            // println!("Type: {}", id_lookup[&file.type_id].filename);
+fn cmd_list(matches: &clap::ArgMatches, names: &mut NameDictionary) -> CResult<()> {
+    let archive = ChumReader::open_pair(&archive_paths(matches, "FILE")?)?;
+    let missing = archive.missing_ids();
+    let found = resolve_names(names, &archive.ngc, &missing);
+    let names = &archive.ngc;
+    for chunk in &archive.dgc.chunks {
+        for file in &chunk.files {
             let id: u32 = file.id1 as u32;
-            let typestr = if file.id1 == file.id2 {
-                format!("{}", id_lookup[&file.type_id])
-            } else {
-                format!("{1}/{0}", id_lookup[&file.type_id], id_lookup[&file.id2])
+            let typestr = match found.resolve_subtype(names, file.id1, file.id2) {
+                Some(subtype) => format!("{}/{}", subtype, found.resolve(names, file.type_id)),
+                None => found.resolve(names, file.type_id),
             };
-            println!("{:8X} {:>35}: {}", id, typestr, &id_lookup[&file.id1]);
+            println!("{:8X} {:>35}: {}", id, typestr, found.resolve(names, file.id1));
         }
     }
     Ok(())
@@ -95,19 +150,34 @@ fn cmd_list(matches: &clap::ArgMatches) -> CResult<()> {
 
 /// Extract command.
 /// Extracts the data from an archive into a folder and a json file.
-fn cmd_extract(matches: &clap::ArgMatches) -> CResult<()> {
-    let archive = load_archive(Path::new(matches.value_of_os("INPUT").unwrap()))?;
-    let output_path = Path::new(matches.value_of_os("OUTPUT").unwrap());
+fn cmd_extract(matches: &clap::ArgMatches, names: &mut NameDictionary) -> CResult<()> {
+    // The input archive may be given only by --dgc and --ngc, leaving a single folder argument
+    let (input, output) = match matches.value_of_os("OUTPUT") {
+        Some(output) => (matches.value_of_os("INPUT"), Some(output)),
+        None => (None, matches.value_of_os("INPUT")),
+    };
+    let output_path = Path::new(output.ok_or_else(
+        || Error::InvalidInput("No output folder was given".to_owned()))?);
+    let paths = ArchivePathPair::from_parts(input.map(Path::new),
+        matches.value_of_os("dgc").map(Path::new), matches.value_of_os("ngc").map(Path::new))?;
+    // Only the data of the entries that are extracted is read
+    let mut archive = ChumReader::open_pair(&paths)?;
+    let missing = archive.missing_ids();
+    let found = resolve_names(names, &archive.ngc, &missing);
 
-    fs::create_dir_all(&output_path)?;
+    let dry_run = matches.is_present("dry-run");
     let mut merge = false;
     if output_path.join("meta.json").exists() {
         if matches.is_present("replace") {
-            for path in fs::read_dir(&output_path)? {
-                let path = path?;
-                if path.file_type()?.is_file() {
-                    fs::remove_file(&path.path())?;
-                }
+            // Only files that the folder's meta.json lists are removed
+            let files = extract::listed_files(output_path)?;
+            println!("{} {} files listed in meta.json:",
+                     if dry_run { "Would remove" } else { "Removing" }, files.len());
+            for path in &files {
+                println!("    {}", path.display());
+            }
+            if !dry_run {
+                extract::remove_listed_files(output_path, &files)?;
             }
         }
         else if matches.is_present("merge") {
@@ -121,73 +191,297 @@ fn cmd_extract(matches: &clap::ArgMatches) -> CResult<()> {
         }
     }
 
-    extract::extract_archive(&archive, &output_path, merge)?;
+    let options = extract::ExtractOptions {
+        merge,
+        policy: match matches.value_of("policy") {
+            Some(name) => name.parse().map_err(Error::InvalidInput)?,
+            None => extract::MergePolicy::default(),
+        },
+        layout: match matches.value_of("layout") {
+            Some(name) => name.parse().map_err(Error::InvalidInput)?,
+            None => extract::ExtractLayout::default(),
+        },
+        filter: entry_filter(matches)?,
+        dry_run,
+        names: found,
+    };
+    let report = extract::extract_from_reader(&mut archive, output_path, &options)?;
+    if dry_run {
+        println!("Would write {} files and meta.json:", report.written.len());
+        for name in &report.written {
+            println!("    {}", output_path.join(name).display());
+        }
+        return Ok(());
+    }
+    if !report.kept.is_empty() {
+        println!("Kept {} files that were edited in the folder: {}",
+                 report.kept.len(), report.kept.join(", "));
+    }
+    if !report.conflicts.is_empty() {
+        eprintln!("Warning: {} files were changed both in the folder and in the archive:",
+                  report.conflicts.len());
+        for conflict in &report.conflicts {
+            eprintln!("    {}", conflict);
+        }
+    }
 
     println!("Extraction successful");
 
     Ok(())
 }
 
+/// Get the filter given by the --type, --subtype, --name and --id options.
+fn entry_filter(matches: &clap::ArgMatches) -> CResult<extract::EntryFilter> {
+    let values = |name: &str| -> Vec<String> {
+        matches.values_of(name).map_or(Vec::new(), |values| values.map(str::to_owned).collect())
+    };
+    Ok(extract::EntryFilter {
+        types: values("type"),
+        subtypes: values("subtype"),
+        names: values("name"),
+        ids: values("id").iter()
+            .map(|id| extract::EntryFilter::parse_id(id))
+            .collect::<Result<_, _>>()
+            .map_err(Error::InvalidInput)?,
+    })
+}
+
+/// Parse a size that is given either in decimal or in hexadecimal with a 0x prefix.
+fn parse_size(value: &str) -> CResult<usize> {
+    let result = if value.starts_with("0x") || value.starts_with("0X") {
+        usize::from_str_radix(&value[2..], 16)
+    } else {
+        value.parse()
+    };
+    result.map_err(|err| Error::InvalidInput(format!("Invalid number '{}': {}", value, err)))
+}
+
 /// Pack command.
 /// Pack the extracted .json and data folder back into archive files.
-fn cmd_pack(matches: &clap::ArgMatches) -> CResult<()> {
+/// Files are put back into their original chunks unless any packing option is given.
+/// A folder that only has some of an archive's entries is applied onto the archive given by
+/// --onto, or onto the output archive.
+fn cmd_pack(matches: &clap::ArgMatches, names: &mut NameDictionary) -> CResult<()> {
     let input_path = Path::new(matches.value_of_os("INPUT").unwrap());
 
-    let archive = extract::import_archive(&input_path)?;
+    let onto = match matches.value_of_os("onto") {
+        Some(path) => Some(ArchivePathPair::from_parts(Some(Path::new(path)), None, None)?),
+        None if extract::read_manifest(input_path)?.partial => Some(archive_paths(matches, "OUTPUT")?),
+        None => None,
+    };
+    let report = match onto {
+        Some(paths) => {
+            println!("Applying the extracted entries onto {}", paths.d.display());
+            extract::import_onto(ChumArchive::load_pair(&paths)?, input_path)?
+        },
+        None => extract::import_folder(input_path)?,
+    };
+    for warning in &report.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    if !report.edited.is_empty() {
+        eprintln!("{} files were edited since they were extracted: {}",
+                  report.edited.len(), report.edited.join(", "));
+    }
+    let mut archive = report.archive;
+    let missing = archive.missing_ids();
+    resolve_names(names, &archive.ngc, &missing);
+    if matches.is_present("strategy") || matches.is_present("chunk-size")
+            || matches.is_present("max-chunks") {
+        let options = PackOptions {
+            strategy: match matches.value_of("strategy") {
+                Some(name) => name.parse().map_err(Error::InvalidInput)?,
+                None => PackStrategy::default(),
+            },
+            chunk_size: matches.value_of("chunk-size").map(parse_size).transpose()?,
+            max_chunks: matches.value_of("max-chunks").map(parse_size).transpose()?,
+        };
+        archive.dgc.pack(&options)?;
+    }
+
+    let paths = archive_paths(matches, "OUTPUT")?;
+    let collisions = archive.collisions();
+    if collisions.is_empty() {
+        archive.save_pair(&paths)?;
+    }
+    else if matches.is_present("force") {
+        archive.save_pair_unchecked(&paths)?;
+    }
+    else {
+        eprintln!("Use --force to pack anyway");
+        return Err(NameCollisionError { collisions }.into());
+    }
 
-    let path = Path::new(matches.value_of_os("OUTPUT").unwrap());
-    let ngc_path = path.with_extension("NGC");
-    let dgc_path = path.with_extension("DGC");
+    println!("Packing successful");
 
-    let mut ngc_file = File::create(ngc_path)?;
-    archive.ngc.write_to(&mut ngc_file)?;
+    Ok(())
+}
 
-    let mut dgc_file = File::create(dgc_path)?;
-    archive.dgc.write_to(&mut dgc_file)?;
+/// Check command.
+/// Reports problems with the names of the given archive.
+fn cmd_check(matches: &clap::ArgMatches) -> CResult<()> {
+    let archive = ChumReader::open_pair(&archive_paths(matches, "FILE")?)?;
+    let diagnostics = archive.ngc.diagnostics();
+    for diagnostic in &diagnostics {
+        let kind = if diagnostic.is_collision() { "collision" } else { "mismatch" };
+        println!("{:08X} {:>9}: {}", diagnostic.id() as u32, kind, diagnostic);
+    }
+    let missing = archive.missing_ids();
+    if !missing.is_empty() {
+        println!("{}", util::describe_missing_ids(&missing));
+    }
+    if diagnostics.is_empty() && missing.is_empty() {
+        println!("No problems found");
+    }
+    let collisions: Vec<_> = diagnostics.into_iter().filter(|d| d.is_collision()).collect();
+    if !collisions.is_empty() {
+        return Err(NameCollisionError { collisions }.into());
+    }
+    Ok(())
+}
 
-    println!("Packing successful");
+/// Header command.
+/// Prints the legal notice in the header of the given archive, or replaces it.
+fn cmd_header(matches: &clap::ArgMatches) -> CResult<()> {
+    let paths = archive_paths(matches, "FILE")?;
+    let notice = match (matches.value_of("set"), matches.value_of_os("set-file")) {
+        (Some(text), _) => util::unescape_bytes(text),
+        (None, Some(path)) => fs::read(path).at_path(path)?,
+        (None, None) => {
+            let archive = ChumReader::open_pair(&paths)?;
+            println!("{}", util::escape_bytes(archive.dgc.header.notice()));
+            return Ok(());
+        }
+    };
+    let mut archive = ChumArchive::load_pair(&paths)?;
+    archive.dgc.header.set_notice(&notice)?;
+    // Only the header changed, so names are written back exactly as they were read
+    archive.save_pair_unchecked(&paths)?;
+    println!("Header updated");
+    Ok(())
+}
 
+/// Suggest command.
+/// Suggests names for known hash values that are found in the data of an archive's files.
+fn cmd_suggest(matches: &clap::ArgMatches, names: &mut NameDictionary) -> CResult<()> {
+    let paths = archive_paths(matches, "FILE")?;
+    let mut archive = ChumReader::open_pair(&paths)?;
+    let missing = archive.missing_ids();
+    let found = resolve_names(names, &archive.ngc, &missing);
+    let files: Vec<DgcFileHeader> = archive.dgc.iter_files().cloned().collect();
+    for file in &files {
+        let name = found.resolve(&archive.ngc, file.id1);
+        if matches.value_of("entry").is_some_and(|entry| entry != name) {
+            continue;
+        }
+        let data = archive.dgc.read_data(file).at_path(&paths.d)?;
+        for suggestion in names.suggest(&data, archive.dgc.endianness) {
+            println!("{} +0x{:X}: {:08X} {}", name, suggestion.offset, suggestion.id as u32,
+                     suggestion.name);
+        }
+    }
     Ok(())
 }
 
-fn main() -> Result<(), Box<error::Error>> {
+fn main() {
+    if let Err(err) = run() {
+        // Print the error along with every error that caused it
+        eprintln!("Error: {}", err.chain());
+        std::process::exit(1);
+    }
+}
+
+fn run() -> CResult<()> {
+    let strategy_names: Vec<&str> = PackStrategy::ALL.iter().map(|s| s.name()).collect();
+    let layout_names: Vec<&str> = extract::ExtractLayout::ALL.iter().map(|l| l.name()).collect();
+    let policy_names: Vec<&str> = extract::MergePolicy::ALL.iter().map(|p| p.name()).collect();
     // Generate commands
     let app = clap::App::new("Chum World")
         //.setting(clap::AppSettings::ArgRequiredElseHelp)
         .version("0.2.0")
         .author("James \"Jellonator\" B. <jellonator00@gmail.com>")
         .about("Edits Revenge of the Flying Dutchman archive files")
+        .arg(clap::Arg::with_name("names")
+             .help("Word list with one name per line, used to name ids that an archive has no name for")
+             .long("names")
+             .takes_value(true)
+             .multiple(true)
+             .number_of_values(1)
+             .global(true))
         .subcommand(clap::SubCommand::with_name("info")
             .about("Get information about the given archive")
             .arg(clap::Arg::with_name("FILE")
                  .help("The archive file to open")
-                 .required(true)
-                 .index(1)))
+                 .required_unless_one(&["dgc", "ngc"])
+                 .index(1))
+            .args(&archive_path_args()))
         .subcommand(clap::SubCommand::with_name("list")
             .about("Lists the contents of the given archive")
             .arg(clap::Arg::with_name("FILE")
                  .help("The archive file to open")
-                 .required(true)
-                 .index(1)))
+                 .required_unless_one(&["dgc", "ngc"])
+                 .index(1))
+            .args(&archive_path_args()))
         .subcommand(clap::SubCommand::with_name("extract")
             .about("Extract the contents of an archive to a folder")
             .arg(clap::Arg::with_name("INPUT")
-                 .help("The archive file to open")
+                 .help("The archive file to open, may be left out if --dgc or --ngc is given")
                  .required(true)
                  .index(1))
             .arg(clap::Arg::with_name("OUTPUT")
                  .help("The folder to extract the archive's contents to")
-                 .required(true)
+                 .required_unless_one(&["dgc", "ngc"])
                  .index(2))
+            .args(&archive_path_args())
             .arg(clap::Arg::with_name("merge")
                  .help("Merge with existing")
                  .long("merge")
                  .short("m"))
+            .arg(clap::Arg::with_name("policy")
+                 .help("What to do with files that were changed both in the folder and in the archive")
+                 .long("policy")
+                 .takes_value(true)
+                 .possible_values(&policy_names)
+                 .requires("merge"))
             .arg(clap::Arg::with_name("replace")
-                 .help("Replace existing folder")
+                 .help("Replace existing folder, removing only the files that its meta.json lists")
                  .long("replace")
                  .short("p")
-                 .conflicts_with("merge")))
+                 .conflicts_with("merge"))
+            .arg(clap::Arg::with_name("dry-run")
+                 .help("List the files that --replace would remove and write, without changing anything")
+                 .long("dry-run")
+                 .requires("replace"))
+            .arg(clap::Arg::with_name("layout")
+                 .help("How extracted files are arranged in folders")
+                 .long("layout")
+                 .short("l")
+                 .takes_value(true)
+                 .possible_values(&layout_names))
+            .arg(clap::Arg::with_name("type")
+                 .help("Only extract entries of this type, may use * and ? wildcards")
+                 .long("type")
+                 .takes_value(true)
+                 .multiple(true)
+                 .number_of_values(1))
+            .arg(clap::Arg::with_name("subtype")
+                 .help("Only extract entries of this subtype, may use * and ? wildcards")
+                 .long("subtype")
+                 .takes_value(true)
+                 .multiple(true)
+                 .number_of_values(1))
+            .arg(clap::Arg::with_name("name")
+                 .help("Only extract entries with this name, may use * and ? wildcards")
+                 .long("name")
+                 .takes_value(true)
+                 .multiple(true)
+                 .number_of_values(1))
+            .arg(clap::Arg::with_name("id")
+                 .help("Only extract the entry with this id, e.g. 0x1A2B3C4D")
+                 .long("id")
+                 .takes_value(true)
+                 .multiple(true)
+                 .number_of_values(1)))
         .subcommand(clap::SubCommand::with_name("pack")
             .about("Pack the extracted contents of an archive back into an archive")
             .arg(clap::Arg::with_name("INPUT")
@@ -196,23 +490,100 @@ fn main() -> Result<(), Box<error::Error>> {
                  .index(1))
             .arg(clap::Arg::with_name("OUTPUT")
                  .help("The output archive file")
-                 .required(true)
-                 .index(2)));
+                 .required_unless_one(&["dgc", "ngc"])
+                 .index(2))
+            .args(&archive_path_args())
+            .arg(clap::Arg::with_name("strategy")
+                 .help("How files are assigned to chunks")
+                 .long("strategy")
+                 .short("s")
+                 .takes_value(true)
+                 .possible_values(&strategy_names))
+            .arg(clap::Arg::with_name("chunk-size")
+                 .help("Chunk size in bytes, a multiple of 0x800")
+                 .long("chunk-size")
+                 .takes_value(true))
+            .arg(clap::Arg::with_name("max-chunks")
+                 .help("Maximum number of chunks")
+                 .long("max-chunks")
+                 .takes_value(true))
+            .arg(clap::Arg::with_name("onto")
+                 .help("Apply the extracted entries onto this archive, keeping its other entries")
+                 .long("onto")
+                 .takes_value(true))
+            .arg(clap::Arg::with_name("force")
+                 .help("Pack even if distinct names share an id, which drops one of the names")
+                 .long("force")
+                 .short("f")))
+        .subcommand(clap::SubCommand::with_name("check")
+            .about("Check the given archive for names that share an id or do not match their id")
+            .arg(clap::Arg::with_name("FILE")
+                 .help("The archive file to open")
+                 .required_unless_one(&["dgc", "ngc"])
+                 .index(1))
+            .args(&archive_path_args()))
+        .subcommand(clap::SubCommand::with_name("header")
+            .about("Print or replace the legal notice in the header of the given archive")
+            .arg(clap::Arg::with_name("FILE")
+                 .help("The archive file to open")
+                 .required_unless_one(&["dgc", "ngc"])
+                 .index(1))
+            .args(&archive_path_args())
+            .arg(clap::Arg::with_name("set")
+                 .help("New legal notice, at most 256 bytes; bytes can be escaped as \\xNN")
+                 .long("set")
+                 .takes_value(true))
+            .arg(clap::Arg::with_name("set-file")
+                 .help("File that contains the new legal notice, at most 256 bytes")
+                 .long("set-file")
+                 .takes_value(true)
+                 .conflicts_with("set")))
+        .subcommand(clap::SubCommand::with_name("suggest")
+            .about("Suggest names for known hash values found in the data of an archive's files")
+            .arg(clap::Arg::with_name("FILE")
+                 .help("The archive file to open")
+                 .required_unless_one(&["dgc", "ngc"])
+                 .index(1))
+            .args(&archive_path_args())
+            .arg(clap::Arg::with_name("entry")
+                 .help("Only search the file with this name")
+                 .long("entry")
+                 .short("e")
+                 .takes_value(true)));
+    // Without the GUI there is nothing to do when no command is given
+    #[cfg(not(feature = "gui"))]
+    let app = app.setting(clap::AppSettings::SubcommandRequiredElseHelp);
     // Run given command
     let matches = app.get_matches();
-    if let Some(cmdlist) = matches.subcommand_matches("list") {
-        cmd_list(cmdlist)?;
-    }
-    else if let Some(cmdlist) = matches.subcommand_matches("info") {
+    if let Some(cmdlist) = matches.subcommand_matches("info") {
         cmd_info(cmdlist)?;
+        return Ok(());
+    }
+    if let Some(cmdlist) = matches.subcommand_matches("check") {
+        cmd_check(cmdlist)?;
+        return Ok(());
     }
-    else if let Some(cmdlist) = matches.subcommand_matches("extract") {
-        cmd_extract(cmdlist)?;
+    if let Some(cmdlist) = matches.subcommand_matches("header") {
+        cmd_header(cmdlist)?;
+        return Ok(());
     }
-    else if let Some(cmdlist) = matches.subcommand_matches("pack") {
-        cmd_pack(cmdlist)?;
+    let command: Option<fn(&clap::ArgMatches, &mut NameDictionary) -> CResult<()>> =
+        match matches.subcommand_name() {
+            Some("list") => Some(cmd_list),
+            Some("extract") => Some(cmd_extract),
+            Some("pack") => Some(cmd_pack),
+            Some("suggest") => Some(cmd_suggest),
+            _ => None,
+        };
+    if let Some(command) = command {
+        let cmdlist = matches.subcommand().1.unwrap();
+        let mut names = load_dictionary(cmdlist)?;
+        let result = command(cmdlist, &mut names);
+        save_dictionary(&mut names);
+        result?;
     }
     else {
+        #[cfg(feature = "gui")]
         gui::begin()?;
     }
     Ok(())
@@ -0,0 +1,268 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use dgc::Endianness;
+use ngc::NgcArchive;
+use util;
+
+/// Environment variable that overrides the location of the name dictionary.
+pub const DICTIONARY_ENV: &str = "CHUM_WORLD_NAMES";
+
+/// Dictionary of known names, used to look up names for ids that an archive's
+/// .NGC file does not contain.
+/// Names are collected from every .NGC file that is opened, and from word lists
+/// provided by the user. The dictionary is stored as a word list, with one
+/// name per line.
+#[derive(Default)]
+pub struct NameDictionary {
+    names: HashMap<i32, String>,
+    /// Ids of names that only came from session word lists, which are never saved.
+    session: HashSet<i32>,
+    changed: bool,
+}
+
+/// Names that a dictionary found for ids that an archive has no name for. These are only used to
+/// show the ids to the user and to name extracted files. They are never added to the archive, so
+/// that what is saved does not depend on the dictionary of the machine that saves it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NameOverlay {
+    names: HashMap<i32, String>,
+}
+
+impl NameOverlay {
+    /// Create an empty overlay.
+    pub fn new() -> NameOverlay {
+        NameOverlay::default()
+    }
+
+    /// Get the name with the given id.
+    pub fn get(&self, id: i32) -> Option<&str> {
+        self.names.get(&id).map(|name| name.as_str())
+    }
+
+    /// Returns true if this overlay has a name for the given id.
+    pub fn contains(&self, id: i32) -> bool {
+        self.names.contains_key(&id)
+    }
+
+    /// Get the number of names in this overlay.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Returns true if this overlay has no names.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Add the names of another overlay to this one.
+    pub fn extend(&mut self, other: NameOverlay) {
+        self.names.extend(other.names);
+    }
+
+    /// Get the name of the given id in the given archive, or the name from this overlay if the
+    /// archive has none, or a placeholder if neither has a name for it.
+    pub fn resolve(&self, ngc: &NgcArchive, id: i32) -> String {
+        match (ngc.get(id), self.get(id)) {
+            (Some(name), _) | (None, Some(name)) => name.to_owned(),
+            (None, None) => util::placeholder_name(id),
+        }
+    }
+
+    /// Get the subtype of a file with the given id1 and id2, like `NgcArchive::resolve_subtype`,
+    /// with names from this overlay for ids that the archive has no name for.
+    pub fn resolve_subtype(&self, ngc: &NgcArchive, id1: i32, id2: i32) -> Option<String> {
+        if id1 == id2 {
+            None
+        } else {
+            Some(self.resolve(ngc, id2))
+        }
+    }
+}
+
+/// A name suggested for a hash value that was found in a file's data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NameSuggestion {
+    /// Offset of the hash value in the file's data.
+    pub offset: usize,
+    pub id: i32,
+    pub name: String,
+}
+
+impl NameDictionary {
+    /// Create an empty dictionary.
+    pub fn new() -> NameDictionary {
+        NameDictionary {
+            names: HashMap::new(),
+            session: HashSet::new(),
+            changed: false,
+        }
+    }
+
+    /// Get the default location of the dictionary. This is the path given by the
+    /// CHUM_WORLD_NAMES environment variable if it is set, otherwise names.txt in the user's
+    /// data directory.
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os(DICTIONARY_ENV) {
+            return Some(PathBuf::from(path));
+        }
+        let data_dir = if cfg!(windows) {
+            env::var_os("APPDATA").map(PathBuf::from)
+        } else {
+            env::var_os("XDG_DATA_HOME").map(PathBuf::from)
+                .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+        };
+        data_dir.map(|dir| dir.join("chum_world").join("names.txt"))
+    }
+
+    /// Load the dictionary from the given path. A missing file gives an empty dictionary.
+    pub fn load(path: &Path) -> io::Result<NameDictionary> {
+        let mut dictionary = NameDictionary::new();
+        match File::open(path) {
+            Ok(file) => {
+                dictionary.add_word_list(BufReader::new(file))?;
+            },
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => {},
+            Err(err) => return Err(err),
+        }
+        dictionary.changed = false;
+        Ok(dictionary)
+    }
+
+    /// Save the dictionary to the given path, sorted by name. Names that only came from session
+    /// word lists are not saved.
+    pub fn save(&mut self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut names: Vec<&String> = self.names.iter()
+            .filter(|&(id, _)| !self.session.contains(id))
+            .map(|(_, name)| name)
+            .collect();
+        names.sort();
+        let mut writer = BufWriter::new(File::create(path)?);
+        for name in names {
+            writeln!(writer, "{}", name)?;
+        }
+        writer.flush()?;
+        self.changed = false;
+        Ok(())
+    }
+
+    /// Returns true if names that are saved were added since the dictionary was loaded or saved.
+    pub fn is_changed(&self) -> bool {
+        self.changed
+    }
+
+    /// Get the number of names in this dictionary.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Returns true if this dictionary has no names.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Get the name with the given id.
+    pub fn get(&self, id: i32) -> Option<&str> {
+        self.names.get(&id).map(|name| name.as_str())
+    }
+
+    /// Add a name to this dictionary. Returns false if the dictionary already has a name with
+    /// the same id, in which case the existing name is kept. Placeholder names are ignored.
+    pub fn insert(&mut self, name: &str) -> bool {
+        self.add(name, false)
+    }
+
+    /// Add a name, which is only saved if it is not from a session word list. A name from a
+    /// session word list is saved once it is added again from anywhere else.
+    fn add(&mut self, name: &str, session: bool) -> bool {
+        if name.is_empty() || util::parse_placeholder(name).is_some() {
+            return false;
+        }
+        let id = util::hash_name(name);
+        if self.names.contains_key(&id) {
+            if !session && self.session.remove(&id) {
+                self.changed = true;
+            }
+            return false;
+        }
+        self.names.insert(id, name.to_owned());
+        if session {
+            self.session.insert(id);
+        } else {
+            self.changed = true;
+        }
+        true
+    }
+
+    /// Add every name from the given .NGC archive whose id is its hash. Returns the number of
+    /// new names.
+    pub fn harvest(&mut self, ngc: &NgcArchive) -> usize {
+        ngc.names.iter()
+            .filter(|&(&id, name)| util::hash_name(name) == id && self.insert(name))
+            .count()
+    }
+
+    /// Add every name from a word list, which has one name per line. Empty lines are skipped.
+    /// Returns the number of new names.
+    pub fn add_word_list<R: BufRead>(&mut self, reader: R) -> io::Result<usize> {
+        self.read_word_list(reader, false)
+    }
+
+    /// Add every name from a word list like `add_word_list`, but only for this session: the
+    /// names are used to look up ids, but are not saved with the dictionary.
+    pub fn add_session_word_list<R: BufRead>(&mut self, reader: R) -> io::Result<usize> {
+        self.read_word_list(reader, true)
+    }
+
+    fn read_word_list<R: BufRead>(&mut self, reader: R, session: bool) -> io::Result<usize> {
+        let mut count = 0;
+        for line in reader.lines() {
+            let line = line?;
+            if self.add(line.trim_end_matches('\r'), session) {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Look up names in this dictionary for each of the given ids that the given .NGC archive has
+    /// no name for. The archive is not changed; the names that were found are returned as an
+    /// overlay.
+    pub fn complete(&self, ngc: &NgcArchive, ids: &[i32]) -> NameOverlay {
+        let mut found = NameOverlay::new();
+        for &id in ids {
+            if ngc.names.contains_key(&id) {
+                continue;
+            }
+            if let Some(name) = self.names.get(&id) {
+                found.names.insert(id, name.clone());
+            }
+        }
+        found
+    }
+
+    /// Add the names of the given .NGC archive to this dictionary, then look up names in this
+    /// dictionary for each of the given ids that the archive has no name for, see `complete`.
+    pub fn resolve_missing(&mut self, ngc: &NgcArchive, ids: &[i32]) -> NameOverlay {
+        self.harvest(ngc);
+        self.complete(ngc, ids)
+    }
+
+    /// Find known hash values in the given data, and suggest their names. Values are read at
+    /// every 4 byte aligned offset, in the given byte order.
+    pub fn suggest(&self, data: &[u8], endianness: Endianness) -> Vec<NameSuggestion> {
+        data.chunks_exact(4).enumerate().filter_map(|(i, bytes)| {
+            let id = endianness.u32_from_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as i32;
+            self.names.get(&id).map(|name| NameSuggestion {
+                offset: i * 4,
+                id,
+                name: name.clone(),
+            })
+        }).collect()
+    }
+}
@@ -0,0 +1,68 @@
+use std::error::Error;
+use std::fmt;
+use util;
+
+/// A problem with the names of an archive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NameDiagnostic {
+    /// Two distinct names have the same id. Only one name can be stored for
+    /// an id, so the first name is kept and the other is dropped.
+    Collision {
+        id: i32,
+        kept: String,
+        dropped: String,
+    },
+    /// A name is listed with an id that is not the hash of the name.
+    Mismatch {
+        id: i32,
+        name: String,
+    },
+}
+
+impl NameDiagnostic {
+    /// The id that this problem concerns.
+    pub fn id(&self) -> i32 {
+        match *self {
+            NameDiagnostic::Collision { id, .. } => id,
+            NameDiagnostic::Mismatch { id, .. } => id,
+        }
+    }
+
+    /// Returns true if this is a collision, which loses a name when the
+    /// archive is written.
+    pub fn is_collision(&self) -> bool {
+        matches!(*self, NameDiagnostic::Collision { .. })
+    }
+}
+
+impl fmt::Display for NameDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NameDiagnostic::Collision { id, ref kept, ref dropped } =>
+                write!(f, "names \"{}\" and \"{}\" both have id {:08X}", kept, dropped, id as u32),
+            NameDiagnostic::Mismatch { id, ref name } =>
+                write!(f, "name \"{}\" is listed with id {:08X}, but its hash is {:08X}",
+                       name, id as u32, util::hash_name(name) as u32),
+        }
+    }
+}
+
+impl Error for NameDiagnostic {}
+
+/// Error produced when writing an archive where distinct names share an id.
+#[derive(Debug)]
+pub struct NameCollisionError {
+    pub collisions: Vec<NameDiagnostic>,
+}
+
+impl fmt::Display for NameCollisionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} name collision(s) would be written", self.collisions.len())?;
+        for collision in &self.collisions {
+            write!(f, "\n{}", collision)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for NameCollisionError {}
@@ -0,0 +1,67 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// The kind of problem found in a malformed .NGC archive.
+#[derive(Debug)]
+pub enum NgcErrorKind {
+    /// An IO error occurred while reading the archive.
+    Io(io::Error),
+    /// A line has an id, but no name after it.
+    MissingName,
+    /// A line does not start with a number. Contains the text that was
+    /// found instead.
+    InvalidId(String),
+    /// A name is not wrapped in double quotes.
+    UnquotedName,
+}
+
+impl NgcErrorKind {
+    /// Create an error of this kind, located at the given line number.
+    pub fn at(self, line: usize) -> NgcError {
+        NgcError {
+            kind: self,
+            line,
+        }
+    }
+}
+
+impl fmt::Display for NgcErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NgcErrorKind::Io(ref err) => write!(f, "{}", err),
+            NgcErrorKind::MissingName => write!(f, "id is not followed by a name"),
+            NgcErrorKind::InvalidId(ref text) => write!(f, "'{}' is not a valid id", text),
+            NgcErrorKind::UnquotedName => write!(f, "name is not wrapped in double quotes"),
+        }
+    }
+}
+
+/// Error produced when reading a malformed .NGC archive.
+/// Records the line that the problem was found on.
+#[derive(Debug)]
+pub struct NgcError {
+    pub kind: NgcErrorKind,
+    /// Line number of the problem, starting at 1. Zero if the problem is not
+    /// on a particular line.
+    pub line: usize,
+}
+
+impl fmt::Display for NgcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}", self.kind)
+        } else {
+            write!(f, "{} (line {})", self.kind, self.line)
+        }
+    }
+}
+
+impl Error for NgcError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self.kind {
+            NgcErrorKind::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
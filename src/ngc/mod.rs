@@ -1,13 +1,38 @@
-use std::io::{self, Read, BufRead, BufReader, Write};
-use std::error;
-use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::str;
+use std::collections::{HashMap, HashSet};
+use util;
+
+pub mod diagnostic;
+pub mod error;
+
+pub use self::diagnostic::{NameCollisionError, NameDiagnostic};
+pub use self::error::{NgcError, NgcErrorKind};
 
 /// .NGC archive
 /// Contains multiple NGC elements
 /// Format (one on each line for each element):
 /// <ID> "FILENAME"
+/// The list of elements may be followed by padding, e.g. NUL bytes.
+#[derive(Default)]
 pub struct NgcArchive {
     pub names: HashMap<i32, String>,
+    /// The lines of the original file, in their original order.
+    pub lines: Vec<NgcLine>,
+    /// Bytes that follow the list of names.
+    pub padding: Vec<u8>,
+    /// Names that were dropped because another name already had their id.
+    pub collisions: Vec<NameDiagnostic>,
+}
+
+/// A single line of a .NGC file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NgcLine {
+    pub id: i32,
+    /// The bytes between the quotes. These are usually, but not always, valid UTF-8.
+    pub name: Vec<u8>,
+    /// The original bytes of the line, including its line ending and any blank lines before it.
+    pub raw: Vec<u8>,
 }
 
 impl NgcArchive {
@@ -15,41 +40,286 @@ impl NgcArchive {
     pub fn new() ->NgcArchive {
         NgcArchive {
             names: HashMap::new(),
+            lines: Vec::new(),
+            padding: Vec::new(),
+            collisions: Vec::new(),
+        }
+    }
+
+    /// Get the name with the given id.
+    pub fn get(&self, id: i32) -> Option<&str> {
+        self.names.get(&id).map(|name| name.as_str())
+    }
+
+    /// Get the name with the given id, or a placeholder if the id has no name.
+    pub fn resolve(&self, id: i32) -> String {
+        match self.names.get(&id) {
+            Some(name) => name.clone(),
+            None => util::placeholder_name(id),
+        }
+    }
+
+    /// Get the subtype of a file with the given id1 and id2. A file has no subtype if its id2 is
+    /// the same as its id1.
+    pub fn resolve_subtype(&self, id1: i32, id2: i32) -> Option<String> {
+        if id1 == id2 {
+            None
+        } else {
+            Some(self.resolve(id2))
+        }
+    }
+
+    /// Add a name to this archive, and return its id. Placeholder names are not added, since
+    /// they stand for ids that have no name. If another name already has the same id, then that
+    /// name is kept and the collision is recorded in `collisions`.
+    pub fn insert(&mut self, name: &str) -> i32 {
+        if let Some(id) = util::parse_placeholder(name) {
+            return id;
+        }
+        let id = util::hash_name(name);
+        self.insert_with_id(id, name);
+        id
+    }
+
+    /// Add a name to this archive and list it after the lines that were already listed, then
+    /// return its id. Placeholder names are neither added nor listed.
+    pub fn push(&mut self, name: &str) -> i32 {
+        if let Some(id) = util::parse_placeholder(name) {
+            return id;
+        }
+        let id = self.insert(name);
+        self.lines.push(NgcLine::new(id, name));
+        id
+    }
+
+    /// Get the original bytes of the name with the given id. These only differ from the name
+    /// if the name that was read is not valid UTF-8.
+    pub fn get_bytes(&self, id: i32) -> Option<&[u8]> {
+        let name = self.names.get(&id)?;
+        Some(self.lines.iter()
+            .find(|line| line.id == id && String::from_utf8_lossy(&line.name) == name.as_str())
+            .map_or(name.as_bytes(), |line| &line.name[..]))
+    }
+
+    /// Add a line as it was read from a .NGC file, keeping its id and its original bytes even if
+    /// its id is not the hash of its name. If another name already has the id, then that name is
+    /// kept and the collision is recorded in `collisions`.
+    pub fn push_line(&mut self, line: NgcLine) {
+        self.insert_with_id(line.id, &String::from_utf8_lossy(&line.name));
+        self.lines.push(line);
+    }
+
+    /// Get the lines that are still part of this archive, in their original order. These are
+    /// the lines that `write_to` writes with their original bytes.
+    pub fn kept_lines(&self) -> impl Iterator<Item = &NgcLine> {
+        self.lines.iter().filter(move |line| self.is_kept(line))
+    }
+
+    /// Returns true if the given line is still part of this archive: its name is the name of its
+    /// id, or it is a name that collided with the name of its id.
+    fn is_kept(&self, line: &NgcLine) -> bool {
+        let name = String::from_utf8_lossy(&line.name);
+        match self.names.get(&line.id) {
+            Some(current) if *current == name => true,
+            Some(_) => self.collisions.iter().any(|collision| match *collision {
+                NameDiagnostic::Collision { id, ref dropped, .. } =>
+                    id == line.id && *dropped == name,
+                _ => false,
+            }),
+            None => false,
         }
     }
 
+    /// Add a name with the given id. If another name already has the id, then that name is kept
+    /// and the collision is recorded in `collisions`.
+    fn insert_with_id(&mut self, id: i32, name: &str) {
+        match self.names.get(&id) {
+            Some(existing) if existing != name => {
+                let collision = NameDiagnostic::Collision {
+                    id,
+                    kept: existing.clone(),
+                    dropped: name.to_owned(),
+                };
+                if !self.collisions.contains(&collision) {
+                    self.collisions.push(collision);
+                }
+            },
+            Some(_) => {},
+            None => {
+                self.names.insert(id, name.to_owned());
+            }
+        }
+    }
+
+    /// Check whether the given name can be added to this archive. Returns the collision that
+    /// adding it would cause, if another name already has the same id.
+    pub fn check_insert(&self, name: &str) -> Option<NameDiagnostic> {
+        if util::parse_placeholder(name).is_some() {
+            return None;
+        }
+        let id = util::hash_name(name);
+        match self.names.get(&id) {
+            Some(existing) if existing != name => Some(NameDiagnostic::Collision {
+                id,
+                kept: existing.clone(),
+                dropped: name.to_owned(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Find every problem with the names in this archive: names that collided with another name
+    /// when they were added, and names whose id is not their hash. Sorted by id.
+    pub fn diagnostics(&self) -> Vec<NameDiagnostic> {
+        let mut diagnostics = self.collisions.clone();
+        diagnostics.extend(self.names.iter()
+            .filter(|&(&id, _)| util::hash_bytes(self.get_bytes(id).unwrap()) != id)
+            .map(|(&id, name)| NameDiagnostic::Mismatch {
+                id,
+                name: name.clone(),
+            }));
+        diagnostics.sort_by_key(|d| d.id());
+        diagnostics
+    }
+
+    /// Get the given ids that have no name in this archive, sorted and without duplicates.
+    pub fn missing_ids<I: IntoIterator<Item = i32>>(&self, ids: I) -> Vec<i32> {
+        let mut missing: Vec<i32> = ids.into_iter()
+            .filter(|id| !self.names.contains_key(id))
+            .collect();
+        missing.sort_unstable();
+        missing.dedup();
+        missing
+    }
+
+    /// Remove the name with the given id from this archive, and return it.
+    pub fn remove(&mut self, id: i32) -> Option<String> {
+        self.lines.retain(|line| line.id != id);
+        self.collisions.retain(|collision| collision.id() != id);
+        self.names.remove(&id)
+    }
+
     /// Write the archive to the given Writer.
+    /// Lines that were read are written first, in their original order and
+    /// with their original bytes, as long as their name has not changed. They
+    /// are followed by every other name sorted by name and then by ID, and
+    /// finally the padding. The output only depends on the archive's contents.
     pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
-        for (id, name) in &self.names {
+        let mut written = HashSet::new();
+        let mut line_ended = true;
+        for line in self.kept_lines() {
+            if !line_ended {
+                writer.write_all(b"\n")?;
+            }
+            writer.write_all(&line.raw)?;
+            line_ended = line.raw.ends_with(b"\n");
+            written.insert(line.id);
+        }
+        let mut rest: Vec<(&String, &i32)> = self.names.iter()
+            .filter(|&(id, _)| !written.contains(id))
+            .map(|(id, name)| (name, id))
+            .collect();
+        rest.sort();
+        for (name, id) in rest {
+            if !line_ended {
+                writer.write_all(b"\n")?;
+                line_ended = true;
+            }
             writeln!(writer, "{} \"{}\"", id, name)?;
         }
+        writer.write_all(&self.padding)?;
         Ok(())
     }
 
-    pub fn read_from<R: Read>(reader: &mut R) -> Result<NgcArchive, Box<error::Error>> {
-        let file = BufReader::new(reader);
-        
-        let mut elements = HashMap::new();
+    /// Read an archive from the given Reader.
+    /// Each line is an id followed by whitespace and a name in double quotes.
+    /// Everything between the first and the last quote is part of the name,
+    /// including other quotes. Blank lines are skipped, and the list of names
+    /// ends at the first NUL byte, even one right after a name on the same
+    /// line, or at the end of the data. Everything after the last name is kept
+    /// as padding.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<NgcArchive, NgcError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).map_err(|err| NgcErrorKind::Io(err).at(0))?;
 
-        for line in file.lines() {
-            let line = line?;
-            if line.len() == 0 || line.starts_with('\0') {
+        let mut ngc = NgcArchive::new();
+        // Start of the next line, and start of the bytes that belong to the next name
+        let mut pos = 0;
+        let mut entry_start = 0;
+        let mut line_number = 0;
+        while pos < data.len() {
+            line_number += 1;
+            let mut end = match data[pos..].iter().position(|&c| c == b'\n') {
+                Some(offset) => pos + offset + 1,
+                None => data.len(),
+            };
+            // Padding can start right after the last name, on the same line
+            let padded = match data[pos..end].iter().position(|&c| c == 0) {
+                Some(offset) => {
+                    end = pos + offset;
+                    true
+                },
+                None => false,
+            };
+            let line = &data[pos..end];
+            let line = line.strip_suffix(b"\n").unwrap_or(line);
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            pos = end;
+            if !line.trim_ascii().is_empty() {
+                let (id, name) = NgcLine::parse(line, line_number)?;
+                ngc.push_line(NgcLine {
+                    id,
+                    name: name.to_vec(),
+                    raw: data[entry_start..end].to_vec(),
+                });
+                entry_start = end;
+            }
+            if padded {
                 break;
             }
-            // println!("{}", line);
-            let pos = line.find(char::is_whitespace).unwrap();
-            let id_str = &line[0..pos];
-            let file_str = &line[pos+1..];
-            let filelen = file_str.len();
-            let id = id_str.parse()?;
-            let filename = file_str[1..filelen-1].to_string();
-            elements.insert(id, filename);
-            // println!("    -> {}, {}", elements.last().unwrap().filename, elements.last().unwrap().id);
         }
 
-        Ok(NgcArchive{
-            names: elements,
-        })
+        ngc.padding = data[entry_start..].to_vec();
+        Ok(ngc)
     }
 }
 
+impl NgcLine {
+    /// Create a line in the standard `<ID> "NAME"` format.
+    pub fn new(id: i32, name: &str) -> NgcLine {
+        NgcLine {
+            id,
+            name: name.as_bytes().to_vec(),
+            raw: format!("{} \"{}\"\n", id, name).into_bytes(),
+        }
+    }
+
+    /// Returns true if this line is exactly the line that `new` gives for its name: its name is
+    /// valid UTF-8, its id is the hash of its name written as a signed number, and it ends with a
+    /// single `\n` and has no blank lines or extra whitespace.
+    pub fn is_standard(&self) -> bool {
+        match str::from_utf8(&self.name) {
+            Ok(name) => util::hash_name(name) == self.id && *self == NgcLine::new(self.id, name),
+            Err(_) => false,
+        }
+    }
+
+    /// Parse a single line without its line ending into its id and the bytes of its name.
+    fn parse(line: &[u8], line_number: usize) -> Result<(i32, &[u8]), NgcError> {
+        let split = line.iter().position(|c| c.is_ascii_whitespace())
+            .ok_or_else(|| NgcErrorKind::MissingName.at(line_number))?;
+        let id_text = String::from_utf8_lossy(&line[..split]);
+        // Ids are written as signed numbers, but unsigned ids are accepted as well
+        let id = id_text.parse::<i32>()
+            .or_else(|_| id_text.parse::<u32>().map(|id| id as i32))
+            .map_err(|_| NgcErrorKind::InvalidId(id_text.to_string()).at(line_number))?;
+        let rest = line[split..].trim_ascii();
+        if rest.is_empty() {
+            return Err(NgcErrorKind::MissingName.at(line_number));
+        }
+        if rest.len() < 2 || rest[0] != b'"' || rest[rest.len() - 1] != b'"' {
+            return Err(NgcErrorKind::UnquotedName.at(line_number));
+        }
+        Ok((id, &rest[1..rest.len() - 1]))
+    }
+}
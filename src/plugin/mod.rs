@@ -1,66 +1,113 @@
+#[cfg(feature = "gui")]
 use gtk::{Widget, Label};
+#[cfg(feature = "gui")]
 use gtk::prelude::*;
+#[cfg(feature = "gui")]
+use gui::editors::EditorPlugin;
+#[cfg(feature = "gui")]
 use gui::page::{Page, ArchiveFile};
+#[cfg(feature = "gui")]
 use std::rc::Rc;
+#[cfg(feature = "gui")]
 use std::cell::RefCell;
 use std::io::{self, Read, Write};
 use ::CResult;
+use dgc::Endianness;
 use std::collections::HashMap;
 
 pub mod text;
 
-/// A plugin that can be used to import, export, or edit files.
-pub trait FilePlugin {
+/// A plugin that can be used to import or export files.
+pub trait CodecPlugin {
     /// Take data from a reader and transform it into the actual archive's format
     /// For example, WAV -> DSP or raw image -> PNG
-    fn import_data(&self, input: &mut Read, output: &mut Write) -> CResult<()>;
+    fn import_data(&self, input: &mut dyn Read, output: &mut dyn Write) -> CResult<()>;
     /// Take data from a reader and transform it into a user-editable format
     /// For example, DSP -> WAV or PNG -> raw image
-    fn export_data(&self, input: &mut Read, output: &mut Write) -> CResult<()>;
-    /// Create an editor gui for the given file
-    fn create_editor(&self, parent: &Rc<RefCell<Page>>, file: &Rc<RefCell<ArchiveFile>>) -> CResult<Widget>;
+    fn export_data(&self, input: &mut dyn Read, output: &mut dyn Write) -> CResult<()>;
+    /// Get the file extension of exported files, without the leading dot
+    fn get_file_extension(&self) -> Option<&'static str> {
+        None
+    }
     /// Get the plugin type string
     fn get_plugin_string(&self) -> &'static str;
 }
 
 /// Manages plugins. Maps file types to individual plugin handlers.
+/// Codecs and editors are both looked up by the plugin string that a file
+/// type is registered to.
 pub struct PluginManager {
-    pub plugins: HashMap<String, Box<FilePlugin>>,
+    pub plugins: HashMap<String, Box<dyn CodecPlugin>>,
+    #[cfg(feature = "gui")]
+    pub editors: HashMap<String, Box<dyn EditorPlugin>>,
     pub ftypes: HashMap<String, String>
 }
 
+impl Default for PluginManager {
+    fn default() -> PluginManager {
+        PluginManager::new()
+    }
+}
+
 impl PluginManager {
-    /// Create a new plugin manager
+    /// Create a new plugin manager with all of the built-in codecs registered, for big endian
+    /// archives
     pub fn new() -> PluginManager {
+        PluginManager::with_endianness(Endianness::Big)
+    }
+
+    /// Create a new plugin manager with all of the built-in codecs registered, for archives of
+    /// the given byte order
+    pub fn with_endianness(endianness: Endianness) -> PluginManager {
         let mut ret = PluginManager {
             plugins: HashMap::new(),
+            #[cfg(feature = "gui")]
+            editors: HashMap::new(),
             ftypes: HashMap::new()
         };
-        let txt = ret.register_plugin(Box::new(text::FilePluginLengthText));
+        let txt = ret.register_plugin(Box::new(text::CodecLengthText::new(endianness)));
         ret.register_for_type(txt, "TXT");
         ret
     }
 
-    /// Register a new plugin
-    pub fn register_plugin(&mut self, plugin: Box<FilePlugin>) -> &'static str {
+    /// Register a new codec plugin
+    pub fn register_plugin(&mut self, plugin: Box<dyn CodecPlugin>) -> &'static str {
         let ret = plugin.get_plugin_string();
         self.plugins.insert(plugin.get_plugin_string().to_owned(), plugin);
         ret
     }
 
+    /// Register a new editor plugin
+    #[cfg(feature = "gui")]
+    pub fn register_editor(&mut self, editor: Box<dyn EditorPlugin>) -> &'static str {
+        let ret = editor.get_plugin_string();
+        self.editors.insert(editor.get_plugin_string().to_owned(), editor);
+        ret
+    }
+
     /// Associate a file type to a plugin string
     pub fn register_for_type(&mut self, fstr: &str, typestr: &str) {
         self.ftypes.insert(typestr.to_owned(), fstr.to_owned());
     }
 
+    /// Get the codec plugin used for the given file type
+    pub fn get_codec(&self, typestr: &str) -> Option<&dyn CodecPlugin> {
+        self.ftypes.get(typestr)
+            .and_then(|fstring| self.plugins.get(fstring))
+            .map(|plugin| plugin.as_ref())
+    }
+
+    /// Get the file extension that exported files of the given type should use
+    pub fn get_file_extension(&self, typestr: &str) -> Option<&'static str> {
+        self.get_codec(typestr).and_then(|plugin| plugin.get_file_extension())
+    }
+
     /// Create an editor pane for the given file
+    #[cfg(feature = "gui")]
     pub fn create_editor(&self, parent: &Rc<RefCell<Page>>, file: &Rc<RefCell<ArchiveFile>>) -> Widget {
         let typestr: &str = &file.borrow().typeid;
-        let result = match self.ftypes.get(typestr) {
-            Some(name) => {
-                let plugin = self.plugins.get(name).unwrap();
-                plugin.create_editor(parent, file)
-            },
+        let result = match self.ftypes.get(typestr).and_then(|name| self.editors.get(name)) {
+            Some(editor) => editor.create_editor(parent, file),
             _ => {
                 let ret = Label::new(format!("No editor for type {} exists.", typestr).as_str());
                 Ok(ret.upcast::<Widget>())
@@ -69,20 +116,20 @@ impl PluginManager {
         match result {
             Ok(widget) => widget,
             Err(err) => {
-                let ret = Label::new(format!("Error opening file:\n{}", err.description()).as_str());
+                let name = &file.borrow().name;
+                let msg = format!("Error opening {} (type {}):\n{}", name, typestr, err.chain());
+                let ret = Label::new(msg.as_str());
                 ret.upcast::<Widget>()
             }
         }
     }
 
     /// Import a file
-    pub fn import<'a>(&self, typestr: &str, input: &mut Read, output: &mut Write) -> CResult<()> {
-        if let Some(plugin) = self.ftypes.get(typestr).and_then(|fstring| {
-            self.plugins.get(fstring)
-        }) {
+    pub fn import(&self, typestr: &str, input: &mut dyn Read, output: &mut dyn Write) -> CResult<()> {
+        if let Some(plugin) = self.get_codec(typestr) {
             let mut data = Vec::new();
             plugin.import_data(input, &mut data)?;
-            let mut read: &mut io::Read = &mut &data[..];
+            let read: &mut dyn io::Read = &mut &data[..];
             io::copy(read, output)?;
         } else {
             io::copy(input, output)?;
@@ -91,13 +138,11 @@ impl PluginManager {
     }
 
     /// Export a file
-    pub fn export<'a>(&self, typestr: &str, input: &mut Read, output: &mut Write) -> CResult<()> {
-        if let Some(plugin) = self.ftypes.get(typestr).and_then(|fstring| {
-            self.plugins.get(fstring)
-        }) {
+    pub fn export(&self, typestr: &str, input: &mut dyn Read, output: &mut dyn Write) -> CResult<()> {
+        if let Some(plugin) = self.get_codec(typestr) {
             let mut data = Vec::new();
             plugin.export_data(input, &mut data)?;
-            let mut read: &mut io::Read = &mut &data[..];
+            let read: &mut dyn io::Read = &mut &data[..];
             io::copy(read, output)?;
         } else {
             io::copy(input, output)?;
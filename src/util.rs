@@ -1,12 +1,14 @@
-use gtk::prelude::*;
-use gtk::{self, FileChooserDialog, FileChooserAction, FileFilter, ResponseType};
 use std::path::{Path, PathBuf};
 use crc::crc32;
-use std::error::Error;
-use std::borrow::Borrow;
-use dgc;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::str;
+use dgc::{self, FileKey, FileSelector};
+use error::{Error, ManifestError, PathContext};
 use ngc;
 
+pub use error::CResult;
+
 /// Complete Chum archive.
 /// Contains both a .NGC archive and a .DGC archive.
 pub struct ChumArchive {
@@ -14,161 +16,516 @@ pub struct ChumArchive {
     pub ngc: ngc::NgcArchive,
 }
 
-/// A Result type that can be any error.
-pub type CResult<T> = Result<T, Box<Error>>;
+impl ChumArchive {
+    /// Load an archive from the .DGC/.NGC pair that shares the given path's
+    /// file stem. See `ArchivePathPair::resolve`.
+    pub fn load(path: &Path) -> CResult<ChumArchive> {
+        ChumArchive::load_pair(&ArchivePathPair::resolve(path))
+    }
+
+    /// Load an archive from the given .DGC and .NGC files.
+    pub fn load_pair(paths: &ArchivePathPair) -> CResult<ChumArchive> {
+        let mut name_file = File::open(&paths.n).at_path(&paths.n)?;
+        let mut data_file = File::open(&paths.d).at_path(&paths.d)?;
+        let dgca = dgc::DgcArchive::read_from(&mut data_file).at_path(&paths.d)?;
+        let ngca = ngc::NgcArchive::read_from(&mut name_file).at_path(&paths.n)?;
+
+        Ok(ChumArchive {
+            dgc: dgca,
+            ngc: ngca
+        })
+    }
+
+    /// Save this archive as a .DGC/.NGC pair that shares the given path's
+    /// file stem. Refuses to save if distinct names share an id, since only
+    /// one of them can be written.
+    pub fn save(&self, path: &Path) -> CResult<()> {
+        self.save_pair(&ArchivePathPair::resolve(path))
+    }
+
+    /// Save this archive to the given .DGC and .NGC files. Refuses to save if
+    /// distinct names share an id.
+    pub fn save_pair(&self, paths: &ArchivePathPair) -> CResult<()> {
+        let collisions = self.collisions();
+        if !collisions.is_empty() {
+            return Err(ngc::NameCollisionError { collisions }.into());
+        }
+        self.save_pair_unchecked(paths)
+    }
+
+    /// Save this archive as a .DGC/.NGC pair that shares the given path's
+    /// file stem, even if distinct names share an id.
+    pub fn save_unchecked(&self, path: &Path) -> CResult<()> {
+        self.save_pair_unchecked(&ArchivePathPair::resolve(path))
+    }
+
+    /// Save this archive to the given .DGC and .NGC files, even if distinct
+    /// names share an id.
+    pub fn save_pair_unchecked(&self, paths: &ArchivePathPair) -> CResult<()> {
+        let mut ngc_file = File::create(&paths.n).at_path(&paths.n)?;
+        self.ngc.write_to(&mut ngc_file).at_path(&paths.n)?;
+
+        let mut dgc_file = File::create(&paths.d).at_path(&paths.d)?;
+        self.dgc.write_to(&mut dgc_file).at_path(&paths.d)?;
+
+        Ok(())
+    }
+
+    /// Find the first file with the given name. Files of different types or subtypes can share
+    /// a name; use `find_entry` to tell them apart.
+    pub fn find(&self, name: &str) -> Option<&dgc::DgcFile> {
+        self.dgc.get_file(name_to_id(name))
+    }
+
+    /// Find the file with the given type, subtype and name. If there is no subtype, then the
+    /// file's name is used as its subtype.
+    pub fn find_entry(&self, type_name: &str, subtype: Option<&str>, name: &str) -> Option<&dgc::DgcFile> {
+        self.dgc.get_file(entry_key(type_name, subtype, name))
+    }
+
+    /// Get every id used by a file that has no name in the .NGC archive, sorted.
+    pub fn missing_ids(&self) -> Vec<i32> {
+        self.ngc.missing_ids(self.dgc.iter_files().flat_map(|f| vec![f.type_id, f.id1, f.id2]))
+    }
+
+    /// Find every problem with the names in this archive, sorted by id.
+    pub fn check_names(&self) -> Vec<ngc::NameDiagnostic> {
+        self.ngc.diagnostics()
+    }
+
+    /// Get every pair of distinct names that share an id, sorted by id.
+    pub fn collisions(&self) -> Vec<ngc::NameDiagnostic> {
+        self.ngc.diagnostics().into_iter().filter(|d| d.is_collision()).collect()
+    }
+
+    /// Return an error if any of the given names has the same id as a different name in the
+    /// .NGC archive.
+    fn check_insert(&self, names: &[&str]) -> CResult<()> {
+        match names.iter().filter_map(|name| self.ngc.check_insert(name)).next() {
+            Some(collision) => Err(collision.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Add a new file with the given type, subtype and name, and return its id. If there is no
+    /// subtype, then the file's name is used as its subtype. Fails if a file with the same type,
+    /// subtype and name already exists.
+    pub fn add_file(&mut self, type_name: &str, subtype: Option<&str>, name: &str, data: Vec<u8>)
+            -> CResult<i32> {
+        if self.find_entry(type_name, subtype, name).is_some() {
+            return Err(Error::FileExists(name.to_owned()));
+        }
+        self.check_insert(&[type_name, name, subtype.unwrap_or(name)])?;
+        let type_id = self.ngc.insert(type_name);
+        let id1 = self.ngc.insert(name);
+        let id2 = self.ngc.insert(subtype.unwrap_or(name));
+        self.dgc.add_file(dgc::DgcFile {
+            data,
+            type_id,
+            id1,
+            id2,
+        });
+        Ok(id1)
+    }
+
+    /// Remove the selected file, which is given by its id or its key. Names that are no longer
+    /// used by any file are removed from the .NGC archive.
+    pub fn remove_file<S: FileSelector>(&mut self, file: S) -> Option<dgc::DgcFile> {
+        let file = self.dgc.remove_file(file)?;
+        self.release_names(&[file.type_id, file.id1, file.id2]);
+        Some(file)
+    }
+
+    /// Replace the data of the selected file, and return the old data.
+    pub fn replace_data<S: FileSelector>(&mut self, file: S, data: Vec<u8>) -> Option<Vec<u8>> {
+        self.dgc.replace_data(file, data)
+    }
+
+    /// Get the key of the selected file, or an error if there is no such file.
+    fn selected_key<S: FileSelector>(&self, file: S) -> CResult<FileKey> {
+        let id = file.id();
+        self.dgc.get_file(file).map(dgc::DgcFile::key).ok_or(Error::FileNotFound(id))
+    }
+
+    /// Change the ids of the file with the given key. Names that are no longer used are removed
+    /// from the .NGC archive.
+    fn set_key(&mut self, key: FileKey, new_key: FileKey) {
+        self.dgc.set_file_ids(key, new_key.type_id, new_key.id1, new_key.id2);
+        self.release_names(&[key.type_id, key.id1, key.id2]);
+    }
+
+    /// Rename the selected file, which is given by its id or its key, and return its new id. If
+    /// the file has no subtype, then its subtype is renamed as well.
+    pub fn rename_file<S: FileSelector>(&mut self, file: S, new_name: &str) -> CResult<i32> {
+        let key = self.selected_key(file)?;
+        let new_id = name_to_id(new_name);
+        let new_key = key.with_id1(new_id);
+        if new_key != key && self.dgc.get_file(new_key).is_some() {
+            return Err(Error::FileExists(new_name.to_owned()));
+        }
+        self.check_insert(&[new_name])?;
+        self.ngc.insert(new_name);
+        self.set_key(key, new_key);
+        Ok(new_id)
+    }
+
+    /// Change the type and subtype of the selected file, which is given by its id or its key. If
+    /// no subtype is given, then the file's name is used as its subtype. Returns the file's new
+    /// key.
+    pub fn retype_file<S: FileSelector>(&mut self, file: S, type_name: &str, subtype: Option<&str>)
+            -> CResult<FileKey> {
+        let key = self.selected_key(file)?;
+        let mut names = vec![type_name];
+        names.extend(subtype);
+        self.check_insert(&names)?;
+        let new_key = FileKey {
+            type_id: name_to_id(type_name),
+            id2: subtype.map_or(key.id1, name_to_id),
+            ..key
+        };
+        if new_key != key && self.dgc.get_file(new_key).is_some() {
+            return Err(Error::FileExists(self.ngc.resolve(key.id1)));
+        }
+        self.ngc.insert(type_name);
+        if let Some(subtype) = subtype {
+            self.ngc.insert(subtype);
+        }
+        self.set_key(key, new_key);
+        Ok(new_key)
+    }
+
+    /// Remove the names with the given ids from the .NGC archive, unless a file still uses them.
+    fn release_names(&mut self, ids: &[i32]) {
+        for &id in ids {
+            let used = self.dgc.iter_files()
+                .any(|f| f.type_id == id || f.id1 == id || f.id2 == id);
+            if !used {
+                self.ngc.remove(id);
+            }
+        }
+    }
+}
+
+/// Chum archive whose .DGC file data is only read on demand.
+/// Useful for commands that only need file headers, or only a few files.
+pub struct ChumReader {
+    pub dgc: dgc::DgcReader<BufReader<File>>,
+    pub ngc: ngc::NgcArchive,
+}
+
+impl ChumReader {
+    /// Open the .DGC/.NGC pair that shares the given path's file stem.
+    /// See `ArchivePathPair::resolve`.
+    pub fn open(path: &Path) -> CResult<ChumReader> {
+        ChumReader::open_pair(&ArchivePathPair::resolve(path))
+    }
+
+    /// Open the given .DGC and .NGC files.
+    pub fn open_pair(paths: &ArchivePathPair) -> CResult<ChumReader> {
+        let mut name_file = File::open(&paths.n).at_path(&paths.n)?;
+        let data_file = File::open(&paths.d).at_path(&paths.d)?;
+        let dgcr = dgc::DgcReader::new(BufReader::new(data_file)).at_path(&paths.d)?;
+        let ngca = ngc::NgcArchive::read_from(&mut name_file).at_path(&paths.n)?;
+
+        Ok(ChumReader {
+            dgc: dgcr,
+            ngc: ngca
+        })
+    }
 
-/// Get the output file name for the given file string and id
-pub fn get_file_string(s: &str, id: u32) -> String {
-    if let Some(pos) = s.rfind('.') {
-        let (left, right) = s.split_at(pos);
-        format!("{}[{:8X}]{}", left, id, right)
+    /// Get every id used by a file that has no name in the .NGC archive, sorted.
+    pub fn missing_ids(&self) -> Vec<i32> {
+        self.ngc.missing_ids(self.dgc.iter_files().flat_map(|f| vec![f.type_id, f.id1, f.id2]))
+    }
+}
+
+/// Get the placeholder name that stands for an id which has no name, e.g. `#1A2B3C4D`.
+pub fn placeholder_name(id: i32) -> String {
+    format!("#{:08X}", id as u32)
+}
+
+/// Get the id that the given placeholder name stands for. Returns None if the name is not a
+/// placeholder.
+pub fn parse_placeholder(name: &str) -> Option<i32> {
+    if name.len() == 9 && name.starts_with('#') && name[1..].chars().all(|c| c.is_ascii_hexdigit()) {
+        u32::from_str_radix(&name[1..], 16).ok().map(|id| id as i32)
+    } else {
+        None
+    }
+}
+
+/// Get the id of the given name. Placeholder names give the id that they stand for, and any
+/// other name gives its hash.
+pub fn name_to_id(name: &str) -> i32 {
+    parse_placeholder(name).unwrap_or_else(|| hash_name(name))
+}
+
+/// Get the key of a file with the given type, subtype and name. If there is no subtype, then the
+/// file's name is used as its subtype.
+pub fn entry_key(type_name: &str, subtype: Option<&str>, name: &str) -> FileKey {
+    let id1 = name_to_id(name);
+    FileKey {
+        type_id: name_to_id(type_name),
+        id1,
+        id2: subtype.map_or(id1, name_to_id),
+    }
+}
+
+/// Describe the given ids that have no name, for warning the user.
+pub fn describe_missing_ids(ids: &[i32]) -> String {
+    const MAX_LISTED: usize = 8;
+    let mut names: Vec<String> = ids.iter().take(MAX_LISTED).map(|&id| placeholder_name(id)).collect();
+    if ids.len() > MAX_LISTED {
+        names.push(format!("and {} more", ids.len() - MAX_LISTED));
+    }
+    format!("{} ids have no name in the .NGC file and are shown as placeholders: {}",
+            ids.len(), names.join(", "))
+}
+
+/// Describe problems with an archive's names, for showing to the user.
+pub fn describe_name_diagnostics(diagnostics: &[ngc::NameDiagnostic]) -> String {
+    const MAX_LISTED: usize = 8;
+    let mut lines: Vec<String> = diagnostics.iter().take(MAX_LISTED).map(|d| d.to_string()).collect();
+    if diagnostics.len() > MAX_LISTED {
+        lines.push(format!("and {} more", diagnostics.len() - MAX_LISTED));
+    }
+    format!("{} problems were found with the names in the .NGC file:\n{}",
+            diagnostics.len(), lines.join("\n"))
+}
+
+/// Names of devices on Windows, which can not be used as the stem of a file name.
+const RESERVED_FILE_STEMS: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Escape a name so that it can be used as a file name on any file system.
+/// ASCII letters, digits, `-` and `_` are kept, and so are dots other than a leading or trailing
+/// one. Every other byte is written as `%XX`. The first letter of a name that starts with a
+/// reserved device name, e.g. `CON.BIN`, is escaped as well. The empty name is escaped as a lone
+/// `%`, which no other name is escaped to. Distinct names are never escaped to the same file
+/// name, and `unescape_file_name` gives the name back.
+pub fn escape_file_name(name: &str) -> String {
+    if name.is_empty() {
+        return String::from("%");
+    }
+    let bytes = name.as_bytes();
+    let stem = name.split('.').next().unwrap();
+    let reserved = RESERVED_FILE_STEMS.iter().any(|r| r.eq_ignore_ascii_case(stem));
+    let mut escaped = String::with_capacity(name.len());
+    for (i, &b) in bytes.iter().enumerate() {
+        let edge_dot = b == b'.' && (i == 0 || i == bytes.len() - 1);
+        if (b.is_ascii_alphanumeric() || b == b'-' || b == b'_' || b == b'.')
+                && !edge_dot && !(reserved && i == 0) {
+            escaped.push(b as char);
+        } else {
+            escaped.push_str(&format!("%{:02X}", b));
+        }
+    }
+    escaped
+}
+
+/// Get the name that was escaped by `escape_file_name`. Returns None if the given text could not
+/// have been escaped by it.
+pub fn unescape_file_name(escaped: &str) -> Option<String> {
+    if escaped == "%" {
+        return Some(String::new());
+    }
+    let bytes = escaped.as_bytes();
+    let mut name = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = escaped.get(i + 1..i + 3)?;
+            name.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            name.push(bytes[i]);
+            i += 1;
+        }
+    }
+    let name = String::from_utf8(name).ok()?;
+    if escape_file_name(&name) == escaped {
+        Some(name)
     } else {
-        format!("{}[{:8X}]", s, id)
-    }.replace(|c: char| {
-        !c.is_alphanumeric() && c != '.'
-    } , "_")
+        None
+    }
+}
+
+/// Get the file name that a file with the given name is extracted to. If an extension is given,
+/// e.g. by the file's codec plugin, then it is added unless the name already ends with it. A
+/// number greater than 1 is added in brackets before the extension, e.g. `NAME[2].BIN`, to tell
+/// apart files that would otherwise get the same file name. Names never contain unescaped
+/// brackets, so the number can not be mistaken for part of the name.
+pub fn get_file_string(name: &str, extension: Option<&str>, number: usize) -> String {
+    let mut file_name = escape_file_name(name);
+    let mut ext = String::new();
+    if let Some(extension) = extension {
+        let has_extension = file_name.len() > extension.len()
+            && file_name.to_lowercase().ends_with(&format!(".{}", extension.to_lowercase()));
+        if has_extension {
+            ext = file_name.split_off(file_name.len() - extension.len() - 1);
+        } else {
+            ext = format!(".{}", extension);
+        }
+    } else if let Some(pos) = file_name.rfind('.') {
+        ext = file_name.split_off(pos);
+    }
+    if number > 1 {
+        file_name.push_str(&format!("[{}]", number));
+    }
+    file_name + &ext
 }
 
 /// Represents a path that can represent both a NGC and DGC file
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ArchivePathPair {
     pub n: PathBuf,
     pub d: PathBuf,
 }
 
-/// Opens any file, doesn't care about file types
-pub fn open_any<W>(base_path: &Path, prompt: &str, parent: &W, action: FileChooserAction)
--> Option<PathBuf>
-where W: gtk::IsA<gtk::Window> {
-    let btn: &str = match action {
-        FileChooserAction::Open | FileChooserAction::SelectFolder => &gtk::STOCK_OPEN,
-        _ => &gtk::STOCK_SAVE,
-    };
-    let dialog = FileChooserDialog::with_buttons(
-        Some(prompt),  Some(parent), action,
-        &[(&gtk::STOCK_CANCEL, ResponseType::Cancel), (btn, ResponseType::Accept)]);
-    match action {
-        FileChooserAction::Open | FileChooserAction::Save => {
-            let file_filter = FileFilter::new();
-            file_filter.add_pattern("*.*");
-            gtk::FileFilterExt::set_name(&file_filter, "Any file");
-            dialog.add_filter(&file_filter);
+impl ArchivePathPair {
+    /// Find the .DGC/.NGC pair that shares the given path's file stem. The path
+    /// can name either file of the pair, or have no extension at all. Existing
+    /// files are found even if their names differ in case from the given path,
+    /// e.g. `level.dgc` finds `LEVEL.NGC`. Files that do not exist yet use the
+    /// case of the given path's extension.
+    pub fn resolve(path: &Path) -> ArchivePathPair {
+        ArchivePathPair {
+            n: find_sibling(path, "NGC"),
+            d: find_sibling(path, "DGC"),
         }
-        _ => {}
     }
-    dialog.set_current_folder(base_path);
 
-    let result = match dialog.run().into() {
-        ResponseType::Accept => dialog.get_filename(),
-        _ => None
+    /// Resolve a pair from an optional path to either file, and optional
+    /// explicit paths to the .DGC and .NGC files, which take precedence.
+    pub fn from_parts(path: Option<&Path>, dgc: Option<&Path>, ngc: Option<&Path>)
+            -> CResult<ArchivePathPair> {
+        let base = match path.or(dgc).or(ngc) {
+            Some(base) => ArchivePathPair::resolve(base),
+            None => return Err(Error::InvalidInput("No archive was given".to_owned())),
+        };
+        Ok(ArchivePathPair {
+            n: ngc.map_or(base.n, Path::to_path_buf),
+            d: dgc.map_or(base.d, Path::to_path_buf),
+        })
+    }
+}
+
+/// Find the file next to the given path that has the same file stem and the
+/// given extension, ignoring case. If there is none, then the path with the
+/// extension is returned, lowercase if the given path's extension is lowercase.
+fn find_sibling(path: &Path, extension: &str) -> PathBuf {
+    let lowercase = path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| !ext.is_empty() && !ext.chars().any(|c| c.is_ascii_uppercase()));
+    let extension = if lowercase {
+        extension.to_ascii_lowercase()
+    } else {
+        extension.to_owned()
+    };
+    let expected = path.with_extension(extension);
+    if expected.exists() {
+        return expected;
+    }
+    let file_name = match expected.file_name() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => return expected,
     };
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut found: Vec<_> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name())
+            .filter(|name| name.to_string_lossy().eq_ignore_ascii_case(&file_name))
+            .collect(),
+        Err(_) => return expected,
+    };
+    found.sort();
+    match found.into_iter().next() {
+        Some(name) => expected.with_file_name(name),
+        None => expected,
+    }
+}
 
-    dialog.destroy();
+/// Encode the given bytes as a lowercase hex string.
+pub fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
 
-    result
+/// Decode a hex string into bytes.
+pub fn from_hex(s: &str) -> CResult<Vec<u8>> {
+    let invalid = || Error::from(ManifestError::InvalidHex(s.to_owned()));
+    if !s.len().is_multiple_of(2) {
+        return Err(invalid());
+    }
+    s.as_bytes().chunks(2).map(|pair| {
+        str::from_utf8(pair).ok()
+            .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+            .ok_or_else(invalid)
+    }).collect()
 }
 
-/// Open a DGC file and construct a DGC/NGC file path pair
-pub fn open_gc<W>(base_path: &Path, parent: &W, action: FileChooserAction)
--> Option<ArchivePathPair>
-where W: gtk::IsA<gtk::Window> {
-    let btn: &str = match action {
-        FileChooserAction::Open | FileChooserAction::SelectFolder => &gtk::STOCK_OPEN,
-        _ => &gtk::STOCK_SAVE,
-    };
-    let dialog = FileChooserDialog::with_buttons(
-        Some("Open File"),  Some(parent), action,
-        &[(&gtk::STOCK_CANCEL, ResponseType::Cancel), (btn, ResponseType::Accept)]);
-    match action {
-        FileChooserAction::Open | FileChooserAction::Save => {
-            let file_filter = FileFilter::new();
-            file_filter.add_pattern("*.DGC");
-            gtk::FileFilterExt::set_name(&file_filter, "DGC files");
-            dialog.add_filter(&file_filter);
+/// Escape the given bytes as text that can be edited by hand. Valid UTF-8 is kept as is, except
+/// that backslashes are doubled. Other bytes and control characters other than tabs and line
+/// breaks are written as `\xNN`.
+pub fn escape_bytes(data: &[u8]) -> String {
+    let mut text = String::new();
+    for chunk in data.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '\\' => text.push_str("\\\\"),
+                '\t' | '\n' | '\r' => text.push(c),
+                c if c.is_control() && (c as u32) < 0x80 => text.push_str(&format!("\\x{:02x}", c as u32)),
+                c => text.push(c),
+            }
+        }
+        for b in chunk.invalid() {
+            text.push_str(&format!("\\x{:02x}", b));
         }
-        _ => {}
     }
-    dialog.set_current_folder(base_path);
+    text
+}
 
-    let result = match dialog.run().into() {
-        ResponseType::Accept => dialog.get_filename().map(|dname| {
-            let dpath: PathBuf = dname.into();
-            let npath: PathBuf = dpath.with_extension("NGC");
-            ArchivePathPair {
-                n: npath,
-                d: dpath,
+/// Turn text that was escaped with `escape_bytes` back into bytes. Backslashes that do not start
+/// an escape sequence are kept as they are.
+pub fn unescape_bytes(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut data = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' {
+            if bytes.get(i + 1) == Some(&b'\\') {
+                data.push(b'\\');
+                i += 2;
+                continue;
             }
-        }),
-        _ => None
-    };
-
-    dialog.destroy();
-
-    result
-}
-
-/// Confirm that the user wants to perform an action.
-/// Returns true if the action should be performed.
-pub fn ask_confirmation<W>(parent: &W, msg: &str) -> bool
-where W: gtk::IsA<gtk::Window> {
-    let flags = gtk::DialogFlags::DESTROY_WITH_PARENT;
-    let dialog = gtk::MessageDialog::new(
-        Some(parent), flags, gtk::MessageType::Warning,
-        gtk::ButtonsType::YesNo, msg);
-    let value = dialog.run();
-    dialog.destroy();
-    match value.into() {
-        gtk::ResponseType::Yes => true,
-        _ => false,
-    }
-}
-
-/// Ask the user if they would like to merge with the exissting folder
-/// Some(true) to merge
-/// Some(false) to replace
-/// None to cancel
-pub fn ask_merge<W>(parent: &W) -> Option<bool>
-where W: gtk::IsA<gtk::Window> {
-    let flags = gtk::DialogFlags::DESTROY_WITH_PARENT;
-    let dialog = gtk::MessageDialog::new(
-        Some(parent), flags, gtk::MessageType::Warning,
-        gtk::ButtonsType::None,
-"The given folder is not empty.
-Do you want to merge with the existing folder?
-(Note: Replacing the folder will remove the folder's contents)");
-    dialog.add_button("Replace", ResponseType::No.into());
-    dialog.add_button("Cancel", ResponseType::Cancel.into());
-    dialog.add_button("Merge", ResponseType::Yes.into());
-    let value = dialog.run();
-    dialog.destroy();
-    match value.into() {
-        gtk::ResponseType::Yes => Some(true),
-        gtk::ResponseType::No => Some(false),
-        _ => None,
-    }
-}
-
-/// Handle the given result and show an error window if it is a Result::Err
-pub fn handle_result<W>(err: CResult<()>, base_msg: &str, parent: &W)
-where W: gtk::IsA<gtk::Window> {
-    match err {
-        Ok(_) => {},
-        Err(ref err) => show_error(err.borrow(), base_msg, parent),
-    }
-}
-
-/// Show an error to the user
-pub fn show_error<W>(err: &Error, base_msg: &str, parent: &W)
-where W: gtk::IsA<gtk::Window> {
-    let flags = gtk::DialogFlags::DESTROY_WITH_PARENT;
-    let dialog = gtk::MessageDialog::new(
-        Some(parent), flags, gtk::MessageType::Error,
-        gtk::ButtonsType::Ok, &format!("{}:\n{}", base_msg, err.description()));
-    dialog.run();
-    dialog.destroy();
+            let hex = bytes.get(i + 2..i + 4)
+                .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+                .and_then(|hex| str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            if let (Some(&b'x'), Some(value)) = (bytes.get(i + 1), hex) {
+                data.push(value);
+                i += 4;
+                continue;
+            }
+        }
+        data.push(bytes[i]);
+        i += 1;
+    }
+    data
 }
 
 /// Hash the given name using the crc32 IEEE algorithm.
 pub fn hash_name(name: &str) -> i32 {
-    crc32::checksum_ieee(&name.as_bytes()) as i32
+    hash_bytes(name.as_bytes())
+}
+
+/// Get the id of a name that is given as raw bytes, which may not be valid UTF-8.
+pub fn hash_bytes(name: &[u8]) -> i32 {
+    crc32::checksum_ieee(name) as i32
 }
@@ -0,0 +1,173 @@
+extern crate chum_world;
+extern crate serde_json;
+
+mod common;
+
+use chum_world::dgc::{DgcArchive, DgcFile};
+use chum_world::extract;
+use chum_world::ngc::NgcArchive;
+use chum_world::plugin::PluginManager;
+use chum_world::util::{self, ChumArchive};
+use common::{header, scratch_dir};
+
+fn txt_data(text: &str) -> Vec<u8> {
+    let mut data = vec![0, 0, 0, text.len() as u8];
+    data.extend_from_slice(text.as_bytes());
+    data
+}
+
+/// Build a small archive with two text files.
+fn text_archive() -> ChumArchive {
+    let mut dgc = DgcArchive::new(&header(), 0);
+    let mut ngc = NgcArchive::new();
+    for name in &["HELLO.TXT", "WORLD.TXT"] {
+        let id = util::hash_name(name);
+        let type_id = util::hash_name("TXT");
+        dgc.add_file(DgcFile {
+            data: txt_data(name),
+            type_id,
+            id1: id,
+            id2: id,
+        });
+        ngc.names.insert(id, name.to_string());
+        ngc.names.insert(type_id, "TXT".to_string());
+    }
+    ChumArchive {
+        dgc,
+        ngc,
+    }
+}
+
+#[test]
+fn dgc_write_read_round_trip() {
+    let archive = text_archive();
+    let mut data = Vec::new();
+    archive.dgc.write_to(&mut data).unwrap();
+    assert_eq!(data.len(), 0x800 + archive.dgc.data.len() * archive.dgc.chunk_size);
+
+    let read = DgcArchive::read_from(&mut &data[..]).unwrap();
+    assert_eq!(read.chunk_size, archive.dgc.chunk_size);
+    assert_eq!(&read.header.legal_notice[..], &archive.dgc.header.legal_notice[..]);
+    let names: Vec<i32> = read.iter_files().map(|f| f.id1).collect();
+    let expected: Vec<i32> = archive.dgc.iter_files().map(|f| f.id1).collect();
+    assert_eq!(names, expected);
+    for (a, b) in read.iter_files().zip(archive.dgc.iter_files()) {
+        assert_eq!(a.data, b.data);
+        assert_eq!(a.type_id, b.type_id);
+        assert_eq!(a.id2, b.id2);
+    }
+}
+
+#[test]
+fn ngc_write_read_round_trip() {
+    let archive = text_archive();
+    let mut data = Vec::new();
+    archive.ngc.write_to(&mut data).unwrap();
+    let read = NgcArchive::read_from(&mut &data[..]).unwrap();
+    assert_eq!(read.names, archive.ngc.names);
+}
+
+#[test]
+fn text_plugin_round_trip() {
+    let plugins = PluginManager::new();
+    let mut exported = Vec::new();
+    plugins.export("TXT", &mut &txt_data("hello")[..], &mut exported).unwrap();
+    assert_eq!(exported, b"hello");
+    let mut imported = Vec::new();
+    plugins.import("TXT", &mut &exported[..], &mut imported).unwrap();
+    assert_eq!(imported, txt_data("hello"));
+}
+
+#[test]
+fn extract_import_round_trip() {
+    let dir = scratch_dir("extract_import");
+    let archive = text_archive();
+    extract::extract_archive(&archive, &dir, false).unwrap();
+    assert!(dir.join("meta.json").exists());
+
+    let imported = extract::import_archive(&dir).unwrap();
+    let mut original: Vec<(i32, Vec<u8>)> = archive.dgc.iter_files()
+        .map(|f| (f.id1, f.data.clone())).collect();
+    let mut result: Vec<(i32, Vec<u8>)> = imported.dgc.iter_files()
+        .map(|f| (f.id1, f.data.clone())).collect();
+    original.sort();
+    result.sort();
+    assert_eq!(original, result);
+    assert_eq!(imported.ngc.names, archive.ngc.names);
+
+}
+
+#[test]
+fn chum_archive_save_load() {
+    let dir = scratch_dir("save_load");
+    let archive = text_archive();
+    let path = dir.join("TEST.DGC");
+    archive.save(&path).unwrap();
+    assert!(dir.join("TEST.NGC").exists());
+
+    let loaded = ChumArchive::load(&path).unwrap();
+    assert_eq!(loaded.ngc.names, archive.ngc.names);
+    assert_eq!(loaded.dgc.iter_files().count(), 2);
+
+}
+
+#[test]
+fn ngc_output_is_sorted_by_name() {
+    let names = ["ZEBRA.TXT", "APPLE.TXT", "TXT", "MANGO.TXT"];
+    let mut forward = NgcArchive::new();
+    let mut backward = NgcArchive::new();
+    for name in names.iter() {
+        forward.names.insert(util::hash_name(name), name.to_string());
+    }
+    for name in names.iter().rev() {
+        backward.names.insert(util::hash_name(name), name.to_string());
+    }
+    let mut a = Vec::new();
+    let mut b = Vec::new();
+    forward.write_to(&mut a).unwrap();
+    backward.write_to(&mut b).unwrap();
+    assert_eq!(a, b);
+
+    let text = String::from_utf8(a).unwrap();
+    let written: Vec<&str> = text.lines().map(|l| l.split('"').nth(1).unwrap()).collect();
+    assert_eq!(written, vec!["APPLE.TXT", "MANGO.TXT", "TXT", "ZEBRA.TXT"]);
+}
+
+#[test]
+fn equal_sized_files_keep_their_order() {
+    let mut dgc = DgcArchive::new(&header(), 0);
+    let names = ["D.BIN", "C.BIN", "B.BIN", "A.BIN", "BIG.BIN"];
+    for name in &names {
+        // The last file is too large for the chunk size, so every file is redistributed
+        let size = if *name == "BIG.BIN" { 0x1000 } else { 0x500 };
+        dgc.add_file(DgcFile {
+            data: vec![0; size],
+            type_id: util::hash_name("BIN"),
+            id1: util::hash_name(name),
+            id2: util::hash_name(name),
+        });
+    }
+    assert_eq!(dgc.chunk_size, 0x1800);
+    let order: Vec<i32> = dgc.iter_files().map(|f| f.id1).collect();
+    let expected: Vec<i32> = names.iter().map(|n| util::hash_name(n)).collect();
+    assert_eq!(order, expected);
+    // The redistributed files share a chunk, so the large file needs a new one
+    assert_eq!(dgc.data.len(), 2);
+}
+
+#[test]
+fn packing_is_reproducible() {
+    let dir = scratch_dir("reproducible");
+    extract::extract_archive(&text_archive(), &dir, false).unwrap();
+
+    let mut outputs = Vec::new();
+    for _ in 0..2 {
+        let archive = extract::import_archive(&dir).unwrap();
+        let mut dgc = Vec::new();
+        let mut ngc = Vec::new();
+        archive.dgc.write_to(&mut dgc).unwrap();
+        archive.ngc.write_to(&mut ngc).unwrap();
+        outputs.push((dgc, ngc));
+    }
+    assert!(outputs[0] == outputs[1]);
+}
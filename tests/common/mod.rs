@@ -0,0 +1,101 @@
+//! Helpers shared by the integration tests. Each test crate only uses some of them.
+#![allow(dead_code)]
+
+use chum_world::dgc::DgcArchive;
+use chum_world::extract::JsonData;
+use chum_world::ngc::NgcArchive;
+use chum_world::util::ChumArchive;
+use serde_json;
+use std::env;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// An empty scratch directory for a single test. The directory is removed when this is dropped,
+/// even if the test fails.
+pub struct ScratchDir {
+    path: PathBuf,
+}
+
+impl Deref for ScratchDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for ScratchDir {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Create an empty scratch directory for a single test.
+pub fn scratch_dir(name: &str) -> ScratchDir {
+    let path = env::temp_dir().join(format!("chum_world_test_{}_{}", name, process::id()));
+    if path.exists() {
+        fs::remove_dir_all(&path).unwrap();
+    }
+    fs::create_dir_all(&path).unwrap();
+    ScratchDir {
+        path,
+    }
+}
+
+/// The legal notice of test archives, padded with NULs to the full size of a .DGC header's.
+pub fn header() -> String {
+    let mut header = String::from("Test archive");
+    while header.len() < 0x100 {
+        header.push('\0');
+    }
+    header
+}
+
+/// Build an archive with no files.
+pub fn empty_archive() -> ChumArchive {
+    ChumArchive {
+        dgc: DgcArchive::new(&header(), 0),
+        ngc: NgcArchive::new(),
+    }
+}
+
+/// Build an archive with the given chunk size, and the given files added in order. Each file is
+/// given as its type, subtype, name and data.
+pub fn archive_with(chunk_size: usize, files: &[(&str, Option<&str>, &str, &[u8])]) -> ChumArchive {
+    let mut archive = ChumArchive {
+        dgc: DgcArchive::new(&header(), chunk_size),
+        ngc: NgcArchive::new(),
+    };
+    for &(type_name, subtype, name, data) in files {
+        archive.add_file(type_name, subtype, name, data.to_vec()).unwrap();
+    }
+    archive
+}
+
+/// Build an archive with a text file and two binary files in a single chunk, one of which has a
+/// subtype.
+pub fn sample_archive() -> ChumArchive {
+    archive_with(0, &[
+        ("TXT", None, "NOTE.TXT", &[0, 0, 0, 2, b'h', b'i']),
+        ("BIN", None, "A.BIN", &[1, 2, 3]),
+        ("BIN", Some("GROUP"), "B.BIN", &[4]),
+    ])
+}
+
+/// Read the meta.json of an extracted folder.
+pub fn read_meta(dir: &Path) -> JsonData {
+    serde_json::from_reader(fs::File::open(dir.join("meta.json")).unwrap()).unwrap()
+}
+
+/// Replace the meta.json of an extracted folder.
+pub fn write_meta(dir: &Path, meta: &JsonData) {
+    serde_json::to_writer_pretty(fs::File::create(dir.join("meta.json")).unwrap(), meta).unwrap();
+}
@@ -0,0 +1,151 @@
+extern crate chum_world;
+extern crate serde_json;
+
+mod common;
+
+use chum_world::dgc::{DgcFile, Endianness};
+use chum_world::extract::{self, ExtractOptions};
+use chum_world::names::{NameDictionary, NameSuggestion};
+use chum_world::ngc::NgcArchive;
+use chum_world::util;
+use common::{empty_archive, scratch_dir};
+use std::fs;
+
+#[test]
+fn harvest_and_word_lists() {
+    let mut names = NameDictionary::new();
+    let mut ngc = NgcArchive::new();
+    ngc.insert("A.BIN");
+    ngc.insert("BIN");
+    assert_eq!(names.harvest(&ngc), 2);
+    assert_eq!(names.harvest(&ngc), 0);
+    assert!(names.is_changed());
+
+    let list = "B.BIN\r\n\nA.BIN\n#1A2B3C4D\nC.BIN\n";
+    assert_eq!(names.add_word_list(list.as_bytes()).unwrap(), 2);
+    assert_eq!(names.len(), 4);
+    assert_eq!(names.get(util::hash_name("B.BIN")), Some("B.BIN"));
+    assert_eq!(names.get(0x1A2B3C4D), None);
+}
+
+#[test]
+fn save_and_load() {
+    let dir = scratch_dir("dictionary");
+    let path = dir.join("nested").join("names.txt");
+    assert!(NameDictionary::load(&path).unwrap().is_empty());
+
+    let mut names = NameDictionary::new();
+    names.insert("ZED.TXT");
+    names.insert("ALPHA.TXT");
+    names.save(&path).unwrap();
+    assert!(!names.is_changed());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "ALPHA.TXT\nZED.TXT\n");
+
+    let loaded = NameDictionary::load(&path).unwrap();
+    assert!(!loaded.is_changed());
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(loaded.get(util::hash_name("ZED.TXT")), Some("ZED.TXT"));
+}
+
+#[test]
+fn session_word_lists_are_not_saved() {
+    let dir = scratch_dir("dictionary_session");
+    let path = dir.join("names.txt");
+    let mut names = NameDictionary::new();
+    names.insert("SAVED.TXT");
+    assert_eq!(names.add_session_word_list(&b"SESSION.TXT\nFOUND.TXT\n"[..]).unwrap(), 2);
+    names.save(&path).unwrap();
+    assert_eq!(names.get(util::hash_name("SESSION.TXT")), Some("SESSION.TXT"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "SAVED.TXT\n");
+
+    // A session name is saved once an archive has it as well
+    let mut ngc = NgcArchive::new();
+    ngc.insert("FOUND.TXT");
+    assert_eq!(names.harvest(&ngc), 0);
+    assert!(names.is_changed());
+    names.save(&path).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "FOUND.TXT\nSAVED.TXT\n");
+}
+
+#[test]
+fn resolve_missing_names() {
+    let mut names = NameDictionary::new();
+    names.insert("KNOWN.BIN");
+    let mut other = NgcArchive::new();
+    other.insert("OTHER.BIN");
+
+    let known = util::hash_name("KNOWN.BIN");
+    let other_id = util::hash_name("OTHER.BIN");
+    let mut ngc = NgcArchive::new();
+    ngc.insert("BIN");
+    let found = names.resolve_missing(&ngc, &[known, other_id]);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found.get(known), Some("KNOWN.BIN"));
+    assert_eq!(found.resolve(&ngc, known), "KNOWN.BIN");
+    assert_eq!(found.resolve(&ngc, util::hash_name("BIN")), "BIN");
+    assert_eq!(names.get(util::hash_name("BIN")), Some("BIN"));
+    // Names that were found are never added to the archive
+    assert_eq!(ngc.get(known), None);
+
+    // Names harvested from one archive resolve the ids of another
+    names.harvest(&other);
+    let found = names.complete(&ngc, &[other_id]);
+    assert_eq!(found.resolve(&ngc, other_id), "OTHER.BIN");
+    assert_eq!(ngc.resolve(other_id), util::placeholder_name(other_id));
+}
+
+#[test]
+fn extract_with_found_names() {
+    let dir = scratch_dir("dictionary_extract");
+    let id = util::hash_name("KNOWN.BIN");
+    let mut archive = empty_archive();
+    archive.ngc.insert("BIN");
+    archive.dgc.add_file(DgcFile {
+        data: vec![1, 2, 3],
+        type_id: util::hash_name("BIN"),
+        id1: id,
+        id2: id,
+    });
+    let mut names = NameDictionary::new();
+    names.add_word_list(&b"KNOWN.BIN\n"[..]).unwrap();
+    let options = ExtractOptions {
+        names: names.complete(&archive.ngc, &archive.missing_ids()),
+        ..ExtractOptions::default()
+    };
+    extract::extract_with_options(&archive, &dir, &options).unwrap();
+
+    // The file is named after the dictionary, but the name is not added to the archive
+    let file_name = util::get_file_string("KNOWN.BIN", None, 1);
+    assert_eq!(fs::read(dir.join(&file_name)).unwrap(), vec![1, 2, 3]);
+    let meta = fs::read_to_string(dir.join("meta.json")).unwrap();
+    assert!(meta.contains(&format!("\"id\": \"{}\"", util::placeholder_name(id))));
+    let imported = extract::import_archive(&dir).unwrap();
+    assert_eq!(imported.ngc.get(id), None);
+    let mut expected = Vec::new();
+    let mut packed = Vec::new();
+    archive.ngc.write_to(&mut expected).unwrap();
+    imported.ngc.write_to(&mut packed).unwrap();
+    assert!(packed == expected);
+}
+
+#[test]
+fn suggest_names_from_data() {
+    let mut names = NameDictionary::new();
+    names.insert("TEX_ROCK");
+    let id = util::hash_name("TEX_ROCK");
+
+    let mut data = vec![0; 12];
+    data[8..12].copy_from_slice(&(id as u32).to_be_bytes());
+    let expected = vec![NameSuggestion {
+        offset: 8,
+        id,
+        name: "TEX_ROCK".to_string(),
+    }];
+    assert_eq!(names.suggest(&data, Endianness::Big), expected);
+    assert!(names.suggest(&data, Endianness::Little).is_empty());
+
+    let mut data = vec![0; 6];
+    data[2..6].copy_from_slice(&(id as u32).to_le_bytes());
+    // Only aligned values are suggested
+    assert!(names.suggest(&data, Endianness::Little).is_empty());
+}
@@ -0,0 +1,117 @@
+extern crate chum_world;
+extern crate serde_json;
+
+mod common;
+
+use chum_world::util::{self, ChumArchive};
+use common::archive_with;
+
+/// Build an archive with three files of 0x3E0 bytes, so that two files fit into each chunk.
+fn chunked_archive() -> ChumArchive {
+    archive_with(0x800, &[
+        ("BIN", None, "A.BIN", &[1; 0x3E0]),
+        ("BIN", Some("SHARED"), "B.BIN", &[2; 0x3E0]),
+        ("BIN", Some("SHARED"), "C.BIN", &[3; 0x3E0]),
+    ])
+}
+
+/// The names of the files in each chunk.
+fn layout(archive: &ChumArchive) -> Vec<Vec<&str>> {
+    archive.dgc.data.iter().map(|chunk| {
+        chunk.data.iter().map(|f| archive.ngc.get(f.id1).unwrap()).collect()
+    }).collect()
+}
+
+#[test]
+fn add_and_find() {
+    let archive = chunked_archive();
+    assert_eq!(layout(&archive), vec![vec!["A.BIN", "B.BIN"], vec!["C.BIN"]]);
+    let a = archive.find("A.BIN").unwrap();
+    assert_eq!(a.id2, a.id1);
+    assert_eq!(a.type_id, util::hash_name("BIN"));
+    let b = archive.find("B.BIN").unwrap();
+    assert_eq!(archive.ngc.get(b.id2), Some("SHARED"));
+    assert!(archive.find("D.BIN").is_none());
+
+    let mut archive = archive;
+    assert!(archive.add_file("BIN", None, "A.BIN", vec![]).is_err());
+}
+
+#[test]
+fn remove_releases_unused_names() {
+    let mut archive = chunked_archive();
+    let removed = archive.remove_file(util::hash_name("B.BIN")).unwrap();
+    assert_eq!(removed.data, vec![2; 0x3E0]);
+    assert!(archive.ngc.get(util::hash_name("B.BIN")).is_none());
+    // Still used by C.BIN
+    assert_eq!(archive.ngc.get(util::hash_name("SHARED")), Some("SHARED"));
+
+    archive.remove_file(util::hash_name("C.BIN")).unwrap();
+    assert!(archive.ngc.get(util::hash_name("SHARED")).is_none());
+    // The empty chunk is removed
+    assert_eq!(layout(&archive), vec![vec!["A.BIN"]]);
+    assert!(archive.remove_file(util::hash_name("C.BIN")).is_none());
+}
+
+#[test]
+fn freed_space_is_reused() {
+    let mut archive = chunked_archive();
+    archive.remove_file(util::hash_name("A.BIN"));
+    archive.add_file("BIN", None, "D.BIN", vec![4; 0x3E0]).unwrap();
+    assert_eq!(layout(&archive), vec![vec!["B.BIN", "D.BIN"], vec!["C.BIN"]]);
+}
+
+#[test]
+fn replace_data() {
+    let mut archive = chunked_archive();
+    let id = util::hash_name("A.BIN");
+    // Still fits into its chunk
+    let old = archive.replace_data(id, vec![5; 0x100]).unwrap();
+    assert_eq!(old, vec![1; 0x3E0]);
+    assert_eq!(layout(&archive), vec![vec!["A.BIN", "B.BIN"], vec!["C.BIN"]]);
+    assert_eq!(archive.find("A.BIN").unwrap().data, vec![5; 0x100]);
+
+    // Too large for its chunk, so it is moved to the first chunk with enough space
+    archive.replace_data(id, vec![6; 0x500]).unwrap();
+    assert_eq!(layout(&archive), vec![vec!["B.BIN"], vec!["C.BIN"], vec!["A.BIN"]]);
+    assert_eq!(archive.find("A.BIN").unwrap().data, vec![6; 0x500]);
+
+    assert!(archive.replace_data(util::hash_name("D.BIN"), vec![]).is_none());
+}
+
+#[test]
+fn rename() {
+    let mut archive = chunked_archive();
+    let new_id = archive.rename_file(util::hash_name("A.BIN"), "RENAMED.BIN").unwrap();
+    assert_eq!(new_id, util::hash_name("RENAMED.BIN"));
+    let file = archive.find("RENAMED.BIN").unwrap();
+    // A file without a subtype uses its own name as its subtype
+    assert_eq!(file.id2, new_id);
+    assert!(archive.ngc.get(util::hash_name("A.BIN")).is_none());
+    assert_eq!(layout(&archive), vec![vec!["RENAMED.BIN", "B.BIN"], vec!["C.BIN"]]);
+
+    let id = archive.rename_file(util::hash_name("B.BIN"), "OTHER.BIN").unwrap();
+    assert_eq!(archive.dgc.get_file(id).unwrap().id2, util::hash_name("SHARED"));
+
+    assert!(archive.rename_file(id, "C.BIN").is_err());
+    assert!(archive.rename_file(util::hash_name("A.BIN"), "X.BIN").is_err());
+}
+
+#[test]
+fn retype() {
+    let mut archive = chunked_archive();
+    let id = util::hash_name("B.BIN");
+    archive.retype_file(id, "TXT", None).unwrap();
+    let file = archive.dgc.get_file(id).unwrap();
+    assert_eq!(file.type_id, util::hash_name("TXT"));
+    assert_eq!(file.id2, id);
+    assert_eq!(archive.ngc.get(util::hash_name("TXT")), Some("TXT"));
+    // Still used by A.BIN and C.BIN
+    assert_eq!(archive.ngc.get(util::hash_name("BIN")), Some("BIN"));
+    assert_eq!(archive.ngc.get(util::hash_name("SHARED")), Some("SHARED"));
+
+    let id = util::hash_name("C.BIN");
+    archive.retype_file(id, "TXT", Some("NEW")).unwrap();
+    assert_eq!(archive.dgc.get_file(id).unwrap().id2, util::hash_name("NEW"));
+    assert!(archive.ngc.get(util::hash_name("SHARED")).is_none());
+}
@@ -0,0 +1,121 @@
+extern crate chum_world;
+extern crate serde_json;
+
+mod common;
+
+use chum_world::dgc::{DgcArchive, DgcFile, DgcReader, Endianness};
+use chum_world::extract;
+use chum_world::ngc::NgcArchive;
+use chum_world::util::{self, ChumArchive};
+use common::{header, scratch_dir};
+use std::fs::File;
+use std::io::{Cursor, Read};
+
+/// Build a little endian archive with a single text file.
+fn little_endian_archive() -> ChumArchive {
+    let mut dgc = DgcArchive::new(&header(), 0);
+    dgc.endianness = Endianness::Little;
+    let mut ngc = NgcArchive::new();
+    let id = util::hash_name("HELLO.TXT");
+    let type_id = util::hash_name("TXT");
+    dgc.add_file(DgcFile {
+        data: vec![5, 0, 0, 0, b'h', b'e', b'l', b'l', b'o'],
+        type_id,
+        id1: id,
+        id2: id,
+    });
+    ngc.names.insert(id, "HELLO.TXT".to_string());
+    ngc.names.insert(type_id, "TXT".to_string());
+    ChumArchive {
+        dgc,
+        ngc,
+    }
+}
+
+#[test]
+fn detect_from_chunk_size() {
+    assert_eq!(Endianness::detect([0, 0, 0x08, 0]), Endianness::Big);
+    assert_eq!(Endianness::detect([0, 0x08, 0, 0]), Endianness::Little);
+    assert_eq!(Endianness::detect([0, 0, 0x80, 0]), Endianness::Big);
+    assert_eq!(Endianness::detect([0, 0x80, 0, 0]), Endianness::Little);
+    // Neither byte order gives a multiple of 0x800
+    assert_eq!(Endianness::detect([0x12, 0x34, 0x56, 0x78]), Endianness::Big);
+}
+
+#[test]
+fn detect_from_first_chunk() {
+    // 0x100000 big endian is also 0x1000 little endian, but one file does not fit 16M file headers
+    assert_eq!(Endianness::detect_archive([0, 0x10, 0, 0], &[0, 0, 0, 1, 0, 0, 0, 0x20]), Endianness::Big);
+    assert_eq!(Endianness::detect_archive([0, 0x08, 0, 0], &[1, 0, 0, 0, 0x20, 0, 0, 0]), Endianness::Little);
+    // Nothing to check, so both are plausible
+    assert_eq!(Endianness::detect_archive([0, 0x08, 0, 0], &[]), Endianness::Big);
+}
+
+#[test]
+fn big_endian_large_chunks() {
+    let mut archive = DgcArchive::new(&header(), 0x100000);
+    archive.add_file(DgcFile {
+        data: vec![7; 0x2000],
+        type_id: 1,
+        id1: 2,
+        id2: 2,
+    });
+    let mut data = Vec::new();
+    archive.write_to(&mut data).unwrap();
+    assert_eq!(&data[0x100..0x104], &[0, 0x10, 0, 0]);
+
+    let read = DgcArchive::read_from(&mut &data[..]).unwrap();
+    assert_eq!(read.endianness, Endianness::Big);
+    assert_eq!(read.chunk_size, 0x100000);
+    assert_eq!(read.iter_files().next().unwrap().data, vec![7; 0x2000]);
+    let reader = DgcReader::new(Cursor::new(&data[..])).unwrap();
+    assert_eq!(reader.endianness, Endianness::Big);
+    assert_eq!(reader.chunk_size, 0x100000);
+}
+
+#[test]
+fn little_endian_round_trip() {
+    let archive = little_endian_archive();
+    let mut data = Vec::new();
+    archive.dgc.write_to(&mut data).unwrap();
+    assert_eq!(&data[0x100..0x104], &[0, 0x08, 0, 0]);
+    // Number of files in the first chunk, then the first file's size
+    assert_eq!(&data[0x800..0x808], &[1, 0, 0, 0, 25, 0, 0, 0]);
+
+    let read = DgcArchive::read_from(&mut &data[..]).unwrap();
+    assert_eq!(read.endianness, Endianness::Little);
+    assert_eq!(read.chunk_size, 0x800);
+    let file = read.iter_files().next().unwrap();
+    assert_eq!(file.id1, util::hash_name("HELLO.TXT"));
+    assert_eq!(file.type_id, util::hash_name("TXT"));
+
+    let reader = DgcReader::new(Cursor::new(&data[..])).unwrap();
+    assert_eq!(reader.endianness, Endianness::Little);
+    let header = reader.iter_files().next().unwrap();
+    assert_eq!(header.id1, util::hash_name("HELLO.TXT"));
+    assert_eq!(reader.get_data(header), &file.data[..]);
+
+    let mut written = Vec::new();
+    read.write_to(&mut written).unwrap();
+    assert!(written == data);
+}
+
+#[test]
+fn little_endian_extract_and_pack() {
+    let dir = scratch_dir("little_endian");
+    let archive = little_endian_archive();
+    extract::extract_archive(&archive, &dir, false).unwrap();
+
+    let name = util::get_file_string("HELLO.TXT", Some("txt"), 1);
+    let mut text = String::new();
+    File::open(dir.join(name)).unwrap().read_to_string(&mut text).unwrap();
+    assert_eq!(text, "hello");
+
+    let packed = extract::import_archive(&dir).unwrap();
+    assert_eq!(packed.dgc.endianness, Endianness::Little);
+    let mut expected = Vec::new();
+    let mut data = Vec::new();
+    archive.dgc.write_to(&mut expected).unwrap();
+    packed.dgc.write_to(&mut data).unwrap();
+    assert!(data == expected);
+}
@@ -0,0 +1,87 @@
+extern crate chum_world;
+extern crate serde_json;
+
+mod common;
+
+use chum_world::extract;
+use chum_world::util::{self, ChumArchive};
+use common::{archive_with, empty_archive, read_meta, scratch_dir};
+use std::fs;
+
+/// Build an archive with two files named SHARED.DAT that only differ in their type.
+fn shared_name_archive() -> ChumArchive {
+    archive_with(0, &[
+        ("TEXTURE", None, "SHARED.DAT", &[1]),
+        ("MESH", None, "SHARED.DAT", &[2]),
+        ("MESH", Some("GROUP"), "SHARED.DAT", &[3]),
+    ])
+}
+
+#[test]
+fn same_name_different_type() {
+    let mut archive = shared_name_archive();
+    assert_eq!(archive.dgc.iter_files().count(), 3);
+    assert_eq!(archive.find_entry("TEXTURE", None, "SHARED.DAT").unwrap().data, vec![1]);
+    assert_eq!(archive.find_entry("MESH", None, "SHARED.DAT").unwrap().data, vec![2]);
+    assert_eq!(archive.find_entry("MESH", Some("GROUP"), "SHARED.DAT").unwrap().data, vec![3]);
+    assert!(archive.add_file("MESH", None, "SHARED.DAT", vec![]).is_err());
+
+    let mesh = util::entry_key("MESH", None, "SHARED.DAT");
+    archive.replace_data(mesh, vec![4]);
+    assert_eq!(archive.find_entry("MESH", None, "SHARED.DAT").unwrap().data, vec![4]);
+    assert_eq!(archive.find_entry("TEXTURE", None, "SHARED.DAT").unwrap().data, vec![1]);
+
+    // Taken by the grouped mesh
+    assert!(archive.retype_file(mesh, "MESH", Some("GROUP")).is_err());
+    let new_key = archive.retype_file(mesh, "MODEL", None).unwrap();
+    assert_eq!(new_key, util::entry_key("MODEL", None, "SHARED.DAT"));
+    let id = archive.rename_file(new_key, "MODEL.DAT").unwrap();
+    assert_eq!(archive.find_entry("MODEL", None, "MODEL.DAT").unwrap().data, vec![4]);
+    assert_eq!(archive.find_entry("MODEL", None, "MODEL.DAT").unwrap().id2, id);
+    // The name is still used by the other files
+    assert_eq!(archive.ngc.get(util::hash_name("SHARED.DAT")), Some("SHARED.DAT"));
+
+    let texture = util::entry_key("TEXTURE", None, "SHARED.DAT");
+    assert_eq!(archive.remove_file(texture).unwrap().data, vec![1]);
+    assert!(archive.find_entry("MESH", Some("GROUP"), "SHARED.DAT").is_some());
+}
+
+#[test]
+fn extract_same_name_different_type() {
+    let dir = scratch_dir("entries_extract");
+    let archive = shared_name_archive();
+    extract::extract_archive(&archive, &dir, false).unwrap();
+    let meta = read_meta(&dir);
+    assert_eq!(meta.files.len(), 3);
+    let mut file_names: Vec<String> = meta.files.iter().map(|f| f.file_name.to_lowercase()).collect();
+    file_names.sort();
+    file_names.dedup();
+    assert_eq!(file_names.len(), 3);
+
+    let imported = extract::import_archive(&dir).unwrap();
+    for file in archive.dgc.iter_files() {
+        assert_eq!(imported.dgc.get_file(file.key()).unwrap().data, file.data);
+    }
+}
+
+#[test]
+fn merge_keeps_entries_of_other_types() {
+    let dir = scratch_dir("entries_merge");
+    let archive = shared_name_archive();
+    extract::extract_archive(&archive, &dir, false).unwrap();
+    let texture_file = read_meta(&dir).files.into_iter()
+        .find(|f| f.type_id == "TEXTURE").unwrap().file_name;
+
+    let mut other = empty_archive();
+    other.add_file("MESH", None, "SHARED.DAT", vec![5]).unwrap();
+    extract::extract_archive(&other, &dir, true).unwrap();
+    let meta = read_meta(&dir);
+    assert_eq!(meta.files.len(), 3);
+    // The texture's file was not overwritten by the mesh that has the same name
+    assert_eq!(fs::read(dir.join(&texture_file)).unwrap(), vec![1]);
+
+    let imported = extract::import_archive(&dir).unwrap();
+    assert_eq!(imported.find_entry("TEXTURE", None, "SHARED.DAT").unwrap().data, vec![1]);
+    assert_eq!(imported.find_entry("MESH", None, "SHARED.DAT").unwrap().data, vec![5]);
+    assert_eq!(imported.find_entry("MESH", Some("GROUP"), "SHARED.DAT").unwrap().data, vec![3]);
+}
@@ -0,0 +1,109 @@
+extern crate chum_world;
+extern crate serde_json;
+
+mod common;
+
+use chum_world::error::{Error, ManifestError};
+use chum_world::extract;
+use chum_world::util::{ArchivePathPair, ChumArchive};
+use common::{empty_archive, scratch_dir};
+use std::fs;
+use std::io;
+
+#[test]
+fn missing_files_have_paths() {
+    let dir = scratch_dir("error_missing");
+    let pair = ArchivePathPair::resolve(&dir.join("MISSING.DGC"));
+    match ChumArchive::load_pair(&pair) {
+        Err(Error::Io { path: Some(ref path), ref source }) => {
+            assert_eq!(path, &pair.n);
+            assert_eq!(source.kind(), io::ErrorKind::NotFound);
+        },
+        Err(err) => panic!("unexpected error {}", err),
+        Ok(_) => panic!("missing archive was loaded"),
+    }
+}
+
+#[test]
+fn ngc_syntax_errors_have_paths_and_lines() {
+    let dir = scratch_dir("error_ngc");
+    let pair = ArchivePathPair::resolve(&dir.join("BROKEN.DGC"));
+    empty_archive().save_pair(&pair).unwrap();
+    fs::write(&pair.n, b"1 \"A\"\n2 B\n").unwrap();
+    let err = ChumArchive::load_pair(&pair).err().unwrap();
+    match err {
+        Error::Ngc { path: Some(ref path), ref source } => {
+            assert_eq!(path, &pair.n);
+            assert_eq!(source.line, 2);
+        },
+        ref err => panic!("unexpected error {}", err),
+    }
+    assert!(err.chain().contains("(line 2)"));
+}
+
+#[test]
+fn codec_errors_name_the_entry() {
+    let dir = scratch_dir("error_codec");
+    let mut archive = empty_archive();
+    // Text files start with a 4 byte length, so this one cannot be exported
+    archive.add_file("TXT", None, "BROKEN.TXT", vec![1, 2]).unwrap();
+    let err = extract::extract_archive(&archive, &dir, false).err().unwrap();
+    match err {
+        Error::Codec { ref entry, ref type_name, ref source } => {
+            assert_eq!(entry, "BROKEN.TXT");
+            assert_eq!(type_name, "TXT");
+            match **source {
+                Error::Io { ref source, .. } =>
+                    assert_eq!(source.kind(), io::ErrorKind::UnexpectedEof),
+                ref err => panic!("unexpected source {}", err),
+            }
+        },
+        ref err => panic!("unexpected error {}", err),
+    }
+    assert_eq!(err.entry(), Some("BROKEN.TXT"));
+    let chain = err.chain();
+    assert!(chain.starts_with("Could not convert BROKEN.TXT (type TXT)\nCaused by: "));
+}
+
+#[test]
+fn manifest_errors_have_paths() {
+    let dir = scratch_dir("error_manifest");
+    let json_path = dir.join("meta.json");
+    fs::write(&json_path, "{\"header\": 5}").unwrap();
+    match extract::import_archive(&dir) {
+        Err(Error::Manifest { path: Some(ref path), source: ManifestError::Json(_) }) =>
+            assert_eq!(path, &json_path),
+        Err(err) => panic!("unexpected error {}", err),
+        Ok(_) => panic!("invalid manifest was imported"),
+    }
+
+    fs::write(&json_path, "{\"header\": \"\", \"files\": [], \
+                          \"names_padding\": {\"size\": 1, \"data\": \"0102\"}}").unwrap();
+    match extract::import_archive(&dir) {
+        Err(Error::Manifest { source: ManifestError::PaddingTooLarge { size: 1, data_size: 2 }, .. }) => {},
+        Err(err) => panic!("unexpected error {}", err),
+        Ok(_) => panic!("invalid manifest was imported"),
+    }
+}
+
+#[test]
+fn edit_errors() {
+    let mut archive = empty_archive();
+    let id = archive.add_file("BIN", None, "A.BIN", vec![]).unwrap();
+    match archive.add_file("BIN", None, "A.BIN", vec![]) {
+        Err(Error::FileExists(ref name)) => assert_eq!(name, "A.BIN"),
+        other => panic!("unexpected result {:?}", other.map(|_| ())),
+    }
+    match archive.rename_file(id ^ 1, "B.BIN") {
+        Err(Error::FileNotFound(missing)) => assert_eq!(missing, id ^ 1),
+        other => panic!("unexpected result {:?}", other.map(|_| ())),
+    }
+    match archive.add_file("BIN", None, "NAME_32060020.BIN", vec![]) {
+        Ok(_) => {},
+        Err(err) => panic!("unexpected error {}", err),
+    }
+    match archive.add_file("BIN", Some("NAME_29685295.BIN"), "C.BIN", vec![]) {
+        Err(Error::NameCollision(ref diagnostic)) => assert!(diagnostic.is_collision()),
+        other => panic!("unexpected result {:?}", other.map(|_| ())),
+    }
+}
@@ -0,0 +1,91 @@
+extern crate chum_world;
+extern crate serde_json;
+
+mod common;
+
+use chum_world::extract::{self, JsonData};
+use chum_world::util;
+use common::{empty_archive, read_meta, scratch_dir};
+use std::fs;
+
+#[test]
+fn escaped_names() {
+    assert_eq!(util::escape_file_name("A_B-1.BIN"), "A_B-1.BIN");
+    assert_ne!(util::escape_file_name("a-b.TXT"), util::escape_file_name("a_b.TXT"));
+    assert_eq!(util::escape_file_name("DIR/A B.BIN"), "DIR%2FA%20B.BIN");
+    assert_eq!(util::escape_file_name("100%"), "100%25");
+    assert_eq!(util::escape_file_name("#1A2B3C4D"), "%231A2B3C4D");
+    assert_eq!(util::escape_file_name(".HIDDEN."), "%2EHIDDEN%2E");
+    assert_eq!(util::escape_file_name("con.bin"), "%63on.bin");
+    assert_eq!(util::escape_file_name("CONSOLE.BIN"), "CONSOLE.BIN");
+    assert_eq!(util::escape_file_name("\u{e9}"), "%C3%A9");
+    assert_eq!(util::escape_file_name(""), "%");
+
+    for name in &["A_B-1.BIN", "DIR/A B.BIN", "100%", "[2]", ".HIDDEN.", "con.bin", "\u{e9}t\u{e9}", ""] {
+        assert_eq!(util::unescape_file_name(&util::escape_file_name(name)).as_deref(), Some(*name));
+    }
+    // Not something that a name is escaped to
+    assert_eq!(util::unescape_file_name("A B"), None);
+    assert_eq!(util::unescape_file_name("A%2"), None);
+    assert_eq!(util::unescape_file_name("%41"), None);
+    assert_eq!(util::unescape_file_name("%FF"), None);
+}
+
+#[test]
+fn file_strings() {
+    assert_eq!(util::get_file_string("HELLO.TXT", Some("txt"), 1), "HELLO.TXT");
+    assert_eq!(util::get_file_string("HELLO.TXT", Some("txt"), 2), "HELLO[2].TXT");
+    assert_eq!(util::get_file_string("IMAGE.BMP", Some("png"), 1), "IMAGE.BMP.png");
+    assert_eq!(util::get_file_string("IMAGE.BMP", Some("png"), 3), "IMAGE.BMP[3].png");
+    assert_eq!(util::get_file_string("A.BIN", None, 2), "A[2].BIN");
+    assert_eq!(util::get_file_string("NOEXT", None, 2), "NOEXT[2]");
+    assert_eq!(util::get_file_string("[2]", None, 2), "%5B2%5D[2]");
+    assert_eq!(util::get_file_string("", None, 1), "%");
+    assert_eq!(util::get_file_string("", Some("txt"), 2), "%[2].txt");
+}
+
+#[test]
+fn extract_distinct_file_names() {
+    let dir = scratch_dir("filenames_extract");
+    let mut archive = empty_archive();
+    archive.add_file("BIN", None, "a-b.BIN", vec![1]).unwrap();
+    archive.add_file("BIN", None, "a_b.BIN", vec![2]).unwrap();
+    archive.add_file("BIN", None, "CASE.BIN", vec![3]).unwrap();
+    archive.add_file("BIN", None, "case.bin", vec![4]).unwrap();
+    archive.add_file("TXT", None, "NOTE", vec![0, 0, 0, 1, b'x']).unwrap();
+    extract::extract_archive(&archive, &dir, false).unwrap();
+
+    let meta: JsonData = serde_json::from_reader(fs::File::open(dir.join("meta.json")).unwrap()).unwrap();
+    let file_name = |name: &str| meta.files.iter().find(|f| f.id == name).unwrap().file_name.clone();
+    assert_eq!(file_name("a-b.BIN"), "a-b.BIN");
+    assert_eq!(file_name("a_b.BIN"), "a_b.BIN");
+    assert_eq!(file_name("CASE.BIN"), "CASE.BIN");
+    assert_eq!(file_name("case.bin"), "case[2].bin");
+    assert_eq!(file_name("NOTE"), "NOTE.txt");
+    assert_eq!(fs::read(dir.join("NOTE.txt")).unwrap(), b"x");
+
+    let imported = extract::import_archive(&dir).unwrap();
+    for file in archive.dgc.iter_files() {
+        assert_eq!(imported.dgc.get_file(file.key()).unwrap().data, file.data);
+    }
+}
+
+#[test]
+fn extract_empty_name() {
+    let dir = scratch_dir("filenames_empty");
+    let mut archive = empty_archive();
+    archive.add_file("BIN", None, "", vec![1]).unwrap();
+    archive.add_file("TXT", None, "", vec![0, 0, 0, 1, b'x']).unwrap();
+    extract::extract_archive(&archive, &dir, false).unwrap();
+
+    let meta = read_meta(&dir);
+    let file_names: Vec<&str> = meta.files.iter().map(|f| f.file_name.as_str()).collect();
+    assert_eq!(file_names, vec!["%", "%.txt"]);
+    assert_eq!(fs::read(dir.join("%")).unwrap(), vec![1]);
+    assert_eq!(fs::read(dir.join("%.txt")).unwrap(), b"x");
+
+    let imported = extract::import_archive(&dir).unwrap();
+    for file in archive.dgc.iter_files() {
+        assert_eq!(imported.dgc.get_file(file.key()).unwrap().data, file.data);
+    }
+}
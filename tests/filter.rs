@@ -0,0 +1,200 @@
+extern crate chum_world;
+extern crate serde_json;
+
+mod common;
+
+use chum_world::extract::{self, filter, EntryFilter, ExtractOptions};
+use chum_world::util::{self, ChumArchive, ChumReader};
+use common::{archive_with, scratch_dir};
+use std::fs;
+use std::path::Path;
+
+/// Build an archive with two textures, a mesh and a text file, two files to a chunk.
+fn mixed_archive() -> ChumArchive {
+    archive_with(0x800, &[
+        ("BITMAP", None, "TEX_GRASS.BMP", &[1; 0x300]),
+        ("MESH", Some("LEVEL"), "TREE.MSH", &[2; 0x300]),
+        ("BITMAP", Some("LEVEL"), "TEX_BARK.BMP", &[3; 0x300]),
+        ("TXT", None, "NOTE.TXT", &[0, 0, 0, 1, b'x']),
+    ])
+}
+
+fn extract_filtered(archive: &ChumArchive, dir: &Path, filter: EntryFilter) {
+    let options = ExtractOptions {
+        filter,
+        ..ExtractOptions::default()
+    };
+    extract::extract_with_options(archive, dir, &options).unwrap();
+}
+
+fn listed(dir: &Path) -> Vec<String> {
+    extract::read_manifest(dir).unwrap().files.into_iter().map(|f| f.id).collect()
+}
+
+#[test]
+fn glob_patterns() {
+    assert!(filter::glob_match("TEX_*", "TEX_GRASS.BMP"));
+    assert!(filter::glob_match("tex_*.bmp", "TEX_GRASS.BMP"));
+    assert!(filter::glob_match("*", ""));
+    assert!(filter::glob_match("T?EE.*", "TREE.MSH"));
+    assert!(filter::glob_match("*A*A*", "BANANA"));
+    assert!(!filter::glob_match("TEX_*", "MY_TEX_GRASS.BMP"));
+    assert!(!filter::glob_match("*.BMP", "TEX.BMP.BAK"));
+    assert!(!filter::glob_match("T?EE", "TEE"));
+}
+
+#[test]
+fn filter_conditions() {
+    let filter = EntryFilter {
+        types: vec!["BITMAP".to_owned(), "MESH".to_owned()],
+        names: vec!["TEX_*".to_owned()],
+        ..EntryFilter::default()
+    };
+    assert!(filter.matches("BITMAP", None, "TEX_A", 1));
+    assert!(!filter.matches("MESH", None, "TREE.MSH", 1));
+    assert!(!filter.matches("TXT", None, "TEX_A", 1));
+    assert!(EntryFilter::default().matches("TXT", None, "NOTE.TXT", 1));
+
+    let by_subtype = EntryFilter {
+        subtypes: vec!["LEV*".to_owned()],
+        ..EntryFilter::default()
+    };
+    assert!(by_subtype.matches("MESH", Some("LEVEL"), "TREE.MSH", 1));
+    assert!(!by_subtype.matches("MESH", None, "TREE.MSH", 1));
+
+    assert_eq!(EntryFilter::parse_id("0x1A2B3C4D"), Ok(0x1A2B3C4D));
+    assert_eq!(EntryFilter::parse_id("0xFFFFFFFF"), Ok(-1));
+    assert_eq!(EntryFilter::parse_id("#0000002A"), Ok(42));
+    assert_eq!(EntryFilter::parse_id("-5"), Ok(-5));
+    assert!(EntryFilter::parse_id("0xZZ").is_err());
+}
+
+#[test]
+fn extract_selected_entries() {
+    let dir = scratch_dir("filter_extract");
+    let archive = mixed_archive();
+    extract_filtered(&archive, &dir, EntryFilter {
+        names: vec!["TEX_*".to_owned()],
+        ..EntryFilter::default()
+    });
+    let meta = extract::read_manifest(&dir).unwrap();
+    assert!(meta.partial);
+    assert_eq!(listed(&dir), vec!["TEX_GRASS.BMP", "TEX_BARK.BMP"]);
+    assert!(!dir.join("NOTE.TXT").exists());
+
+    let tree = archive.find("TREE.MSH").unwrap().id1;
+    let id_dir = dir.join("by_id");
+    extract_filtered(&archive, &id_dir, EntryFilter {
+        ids: vec![tree],
+        ..EntryFilter::default()
+    });
+    assert_eq!(listed(&id_dir), vec!["TREE.MSH"]);
+}
+
+#[test]
+fn extract_from_reader() {
+    let dir = scratch_dir("filter_reader");
+    mixed_archive().save(&dir.join("LEVEL.DGC")).unwrap();
+    let archive = ChumArchive::load(&dir.join("LEVEL.DGC")).unwrap();
+    let filters = vec![
+        EntryFilter::default(),
+        EntryFilter {
+            types: vec!["BITMAP".to_owned()],
+            ..EntryFilter::default()
+        },
+    ];
+    for (i, filter) in filters.into_iter().enumerate() {
+        let options = ExtractOptions {
+            filter,
+            ..ExtractOptions::default()
+        };
+        let loaded = dir.join(format!("loaded{}", i));
+        let read = dir.join(format!("read{}", i));
+        extract::extract_with_options(&archive, &loaded, &options).unwrap();
+        let mut reader = ChumReader::open(&dir.join("LEVEL.DGC")).unwrap();
+        extract::extract_from_reader(&mut reader, &read, &options).unwrap();
+
+        // Reading entries on demand extracts the same files and manifest
+        let meta = extract::read_manifest(&loaded).unwrap();
+        assert_eq!(fs::read_to_string(read.join("meta.json")).unwrap(),
+                   fs::read_to_string(loaded.join("meta.json")).unwrap());
+        for file in &meta.files {
+            assert_eq!(fs::read(read.join(&file.file_name)).unwrap(),
+                       fs::read(loaded.join(&file.file_name)).unwrap());
+        }
+    }
+    assert_eq!(listed(&dir.join("read1")), vec!["TEX_GRASS.BMP", "TEX_BARK.BMP"]);
+}
+
+#[test]
+fn apply_partial_folder() {
+    let dir = scratch_dir("filter_apply");
+    let archive = mixed_archive();
+    extract_filtered(&archive, &dir, EntryFilter {
+        types: vec!["BITMAP".to_owned()],
+        ..EntryFilter::default()
+    });
+    let meta = extract::read_manifest(&dir).unwrap();
+    let bark = meta.files.iter().find(|f| f.id == "TEX_BARK.BMP").unwrap();
+    fs::write(dir.join(&bark.file_name), vec![9; 0x300]).unwrap();
+
+    let report = extract::import_onto(mixed_archive(), &dir).unwrap();
+    assert_eq!(report.edited, vec!["TEX_BARK.BMP".to_owned()]);
+    let applied = report.archive;
+    assert_eq!(applied.find("TEX_BARK.BMP").unwrap().data, vec![9; 0x300]);
+    // Entries that were not extracted are kept, and every file keeps its place
+    let layout = |archive: &ChumArchive| -> Vec<Vec<i32>> {
+        archive.dgc.data.iter().map(|chunk| chunk.data.iter().map(|f| f.id1).collect()).collect()
+    };
+    assert_eq!(layout(&applied), layout(&archive));
+    assert_eq!(applied.find("NOTE.TXT").unwrap().data, archive.find("NOTE.TXT").unwrap().data);
+}
+
+#[test]
+fn apply_renamed_and_new_entries() {
+    let dir = scratch_dir("filter_apply_renamed");
+    let archive = mixed_archive();
+    extract_filtered(&archive, &dir, EntryFilter {
+        names: vec!["TEX_GRASS.BMP".to_owned()],
+        ..EntryFilter::default()
+    });
+    let mut meta = extract::read_manifest(&dir).unwrap();
+    meta.files[0].id = "TEX_MOSS.BMP".to_owned();
+    fs::write(dir.join("NEW.BIN"), [7]).unwrap();
+    let new_file = serde_json::from_str(
+        r#"{"id": "NEW.BIN", "type_id": "BIN", "file_name": "NEW.BIN"}"#).unwrap();
+    meta.files.push(new_file);
+    serde_json::to_writer(fs::File::create(dir.join("meta.json")).unwrap(), &meta).unwrap();
+
+    let applied = extract::import_onto(mixed_archive(), &dir).unwrap().archive;
+    assert!(applied.find("TEX_GRASS.BMP").is_none());
+    assert_eq!(applied.ngc.get(util::hash_name("TEX_GRASS.BMP")), None);
+    assert_eq!(applied.find("TEX_MOSS.BMP").unwrap().data, vec![1; 0x300]);
+    assert_eq!(applied.find("NEW.BIN").unwrap().data, vec![7]);
+    assert_eq!(applied.dgc.iter_files().count(), 5);
+}
+
+#[test]
+fn merge_filtered_into_complete_folder() {
+    let dir = scratch_dir("filter_merge");
+    let archive = mixed_archive();
+    extract::extract_archive(&archive, &dir, false).unwrap();
+    let options = ExtractOptions {
+        merge: true,
+        filter: EntryFilter {
+            types: vec!["TXT".to_owned()],
+            ..EntryFilter::default()
+        },
+        ..ExtractOptions::default()
+    };
+    extract::extract_with_options(&archive, &dir, &options).unwrap();
+    let meta = extract::read_manifest(&dir).unwrap();
+    assert!(!meta.partial);
+    assert_eq!(meta.files.len(), 4);
+    // Entries that were not extracted again keep their place in the layout
+    assert!(meta.files.iter().all(|f| f.chunk.is_some()));
+    let imported = extract::import_archive(&dir).unwrap();
+    for file in archive.dgc.iter_files() {
+        assert_eq!(imported.dgc.get_file(file.key()).unwrap().data, file.data);
+    }
+}
@@ -0,0 +1,62 @@
+extern crate chum_world;
+extern crate serde_json;
+
+mod common;
+
+use chum_world::dgc::{DgcArchive, LEGAL_NOTICE_SIZE};
+use chum_world::error::Error;
+use chum_world::extract;
+use chum_world::ngc::NgcArchive;
+use chum_world::util::{self, ChumArchive};
+use common::scratch_dir;
+use std::fs;
+
+#[test]
+fn headers_of_any_length() {
+    let dgc = DgcArchive::new("Short", 0);
+    assert_eq!(dgc.header.notice(), b"Short");
+    assert!(dgc.header.legal_notice[5..].iter().all(|&b| b == 0));
+
+    let long: String = "x".repeat(LEGAL_NOTICE_SIZE + 10);
+    let dgc = DgcArchive::new(&long, 0);
+    assert_eq!(dgc.header.notice(), &long.as_bytes()[..LEGAL_NOTICE_SIZE]);
+
+    let mut dgc = DgcArchive::new("", 0);
+    assert!(dgc.header.notice().is_empty());
+    match dgc.header.set_notice(long.as_bytes()) {
+        Err(Error::HeaderTooLong(size)) => assert_eq!(size, LEGAL_NOTICE_SIZE + 10),
+        other => panic!("unexpected result {:?}", other),
+    }
+    dgc.header.set_notice(b"Notice\0with NUL").unwrap();
+    assert_eq!(dgc.header.notice(), b"Notice\0with NUL");
+}
+
+#[test]
+fn escaped_bytes() {
+    let data: Vec<u8> = (0..=255).collect();
+    let text = util::escape_bytes(&data);
+    assert!(!text.contains('\0'));
+    assert_eq!(util::unescape_bytes(&text), data);
+
+    assert_eq!(util::escape_bytes(b"A\\B\0\xC9\t\xE2\x84\xA2"), "A\\\\B\\x00\\xc9\t\u{2122}");
+    // Backslashes that do not start an escape sequence are kept
+    assert_eq!(util::unescape_bytes("C:\\dir\\x4"), b"C:\\dir\\x4");
+}
+
+#[test]
+fn extract_keeps_header_bytes() {
+    let dir = scratch_dir("header_extract");
+    let mut archive = ChumArchive {
+        dgc: DgcArchive::new("", 0),
+        ngc: NgcArchive::new(),
+    };
+    let notice = b"(C) \xA9 Dutchman\0\x01 \\ end";
+    archive.dgc.header.set_notice(notice).unwrap();
+    archive.add_file("BIN", None, "A.BIN", vec![1, 2, 3]).unwrap();
+    extract::extract_archive(&archive, &dir, false).unwrap();
+
+    let meta = fs::read_to_string(dir.join("meta.json")).unwrap();
+    assert!(meta.contains("\"header\": \"(C) \\\\xa9 Dutchman\\\\x00\\\\x01 \\\\\\\\ end\""));
+    let imported = extract::import_archive(&dir).unwrap();
+    assert_eq!(&imported.dgc.header.legal_notice[..], &archive.dgc.header.legal_notice[..]);
+}
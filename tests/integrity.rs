@@ -0,0 +1,83 @@
+extern crate chum_world;
+extern crate serde_json;
+
+mod common;
+
+use chum_world::ngc::{NameDiagnostic, NgcArchive};
+use chum_world::util;
+use common::{empty_archive, scratch_dir};
+use std::fs;
+
+/// Two distinct names with the same crc32 hash.
+const FIRST: &str = "NAME_29685295.BIN";
+const SECOND: &str = "NAME_32060020.BIN";
+
+#[test]
+fn colliding_names_share_an_id() {
+    assert_eq!(util::hash_name(FIRST), util::hash_name(SECOND));
+}
+
+#[test]
+fn diagnostics_on_load() {
+    let id = util::hash_name(FIRST);
+    let text = format!("{0} \"{1}\"\n{0} \"{2}\"\n{0} \"{1}\"\n5 \"WRONG\"\n", id, FIRST, SECOND);
+    let ngc = NgcArchive::read_from(&mut text.as_bytes()).unwrap();
+    // The first name is kept
+    assert_eq!(ngc.get(id), Some(FIRST));
+    let collision = NameDiagnostic::Collision {
+        id,
+        kept: FIRST.to_string(),
+        dropped: SECOND.to_string(),
+    };
+    let mismatch = NameDiagnostic::Mismatch {
+        id: 5,
+        name: "WRONG".to_string(),
+    };
+    let mut expected = vec![collision, mismatch];
+    expected.sort_by_key(|d| d.id());
+    assert_eq!(ngc.diagnostics(), expected);
+}
+
+#[test]
+fn insert_keeps_the_first_name() {
+    let mut ngc = NgcArchive::new();
+    let id = ngc.insert(FIRST);
+    assert!(ngc.check_insert(FIRST).is_none());
+    assert!(ngc.check_insert(SECOND).unwrap().is_collision());
+    assert_eq!(ngc.insert(SECOND), id);
+    assert_eq!(ngc.get(id), Some(FIRST));
+    assert_eq!(ngc.collisions.len(), 1);
+    ngc.remove(id);
+    assert!(ngc.diagnostics().is_empty());
+}
+
+#[test]
+fn edits_refuse_collisions() {
+    let mut archive = empty_archive();
+    archive.add_file("BIN", None, FIRST, vec![1]).unwrap();
+    archive.add_file("BIN", None, "OTHER.BIN", vec![2]).unwrap();
+    assert!(archive.add_file("BIN", None, SECOND, vec![3]).is_err());
+    assert!(archive.add_file(SECOND, None, "THIRD.BIN", vec![3]).is_err());
+    let other = util::hash_name("OTHER.BIN");
+    assert!(archive.rename_file(other, SECOND).is_err());
+    assert!(archive.retype_file(other, "BIN", Some(SECOND)).is_err());
+    assert!(archive.check_names().is_empty());
+    assert_eq!(archive.ngc.get(other), Some("OTHER.BIN"));
+}
+
+#[test]
+fn save_refuses_collisions() {
+    let dir = scratch_dir("collisions");
+    let path = dir.join("TEST.DGC");
+    let mut archive = empty_archive();
+    archive.add_file("BIN", None, FIRST, vec![1]).unwrap();
+    archive.save(&path).unwrap();
+
+    archive.ngc.insert(SECOND);
+    assert_eq!(archive.collisions().len(), 1);
+    fs::remove_file(&path).unwrap();
+    assert!(archive.save(&path).is_err());
+    assert!(!path.exists());
+    archive.save_unchecked(&path).unwrap();
+    assert!(path.exists());
+}
@@ -0,0 +1,73 @@
+extern crate chum_world;
+extern crate serde_json;
+
+mod common;
+
+use chum_world::extract::{self, ExtractLayout, ExtractOptions};
+use chum_world::util::ChumArchive;
+use common::{archive_with, empty_archive, read_meta, scratch_dir};
+use std::fs;
+use std::path::Path;
+
+/// Build an archive with a text file and two binary files, one of which has a subtype with a
+/// slash in it.
+fn slashed_archive() -> ChumArchive {
+    archive_with(0, &[
+        ("TXT", None, "NOTE.TXT", &[0, 0, 0, 2, b'h', b'i']),
+        ("BIN", None, "A.BIN", &[1, 2, 3]),
+        ("BIN", Some("GROUP/1"), "B.BIN", &[4]),
+    ])
+}
+
+fn file_names(dir: &Path) -> Vec<String> {
+    read_meta(dir).files.into_iter().map(|f| f.file_name).collect()
+}
+
+fn by_type() -> ExtractOptions {
+    ExtractOptions {
+        layout: ExtractLayout::ByType,
+        ..ExtractOptions::default()
+    }
+}
+
+#[test]
+fn layout_names() {
+    for &layout in &ExtractLayout::ALL {
+        assert_eq!(layout.name().parse(), Ok(layout));
+    }
+    assert_eq!("by-type".parse(), Ok(ExtractLayout::ByType));
+    assert!("nested".parse::<ExtractLayout>().is_err());
+}
+
+#[test]
+fn extract_by_type() {
+    let dir = scratch_dir("layout_by_type");
+    let archive = slashed_archive();
+    extract::extract_with_options(&archive, &dir, &by_type()).unwrap();
+    assert_eq!(file_names(&dir), vec!["TXT/NOTE.TXT", "BIN/A.BIN", "BIN/GROUP%2F1/B.BIN"]);
+    assert_eq!(fs::read(dir.join("TXT").join("NOTE.TXT")).unwrap(), b"hi");
+    assert_eq!(fs::read(dir.join("BIN").join("GROUP%2F1").join("B.BIN")).unwrap(), vec![4]);
+
+    let imported = extract::import_archive(&dir).unwrap();
+    for file in archive.dgc.iter_files() {
+        assert_eq!(imported.dgc.get_file(file.key()).unwrap().data, file.data);
+    }
+}
+
+#[test]
+fn merge_into_other_layout() {
+    let dir = scratch_dir("layout_merge");
+    let mut other = empty_archive();
+    other.add_file("BIN", None, "OTHER.BIN", vec![5]).unwrap();
+    extract::extract_archive(&other, &dir, false).unwrap();
+
+    let options = ExtractOptions {
+        merge: true,
+        ..by_type()
+    };
+    extract::extract_with_options(&slashed_archive(), &dir, &options).unwrap();
+    assert_eq!(file_names(&dir), vec!["TXT/NOTE.TXT", "BIN/A.BIN", "BIN/GROUP%2F1/B.BIN", "OTHER.BIN"]);
+    let imported = extract::import_archive(&dir).unwrap();
+    assert_eq!(imported.find("OTHER.BIN").unwrap().data, vec![5]);
+    assert_eq!(imported.find("B.BIN").unwrap().data, vec![4]);
+}
@@ -0,0 +1,158 @@
+extern crate chum_world;
+extern crate serde_json;
+
+mod common;
+
+use chum_world::dgc::DgcArchive;
+use chum_world::error::{Error, ManifestError};
+use chum_world::extract::{self, ManifestWarning, MANIFEST_VERSION};
+use chum_world::util::{self, ChumArchive};
+use common::{archive_with, read_meta, sample_archive, scratch_dir, write_meta};
+use std::fs;
+
+#[test]
+fn entries_record_their_origin() {
+    let dir = scratch_dir("manifest_fields");
+    let archive = sample_archive();
+    extract::extract_archive(&archive, &dir, false).unwrap();
+    let meta = read_meta(&dir);
+    assert_eq!(meta.version, Some(MANIFEST_VERSION));
+
+    let note = meta.files.iter().find(|f| f.id == "NOTE.TXT").unwrap();
+    assert_eq!(note.position, Some(0));
+    assert_eq!(note.size, Some(6));
+    assert_eq!(note.file_size, Some(2));
+    assert_eq!(note.hash, Some(extract::content_hash(b"hi")));
+    assert_eq!(note.plugin.as_deref(), Some("length-text"));
+    assert_eq!(note.ids.map(Into::into), archive.find("NOTE.TXT").map(|f| f.key()));
+
+    let b = meta.files.iter().find(|f| f.id == "B.BIN").unwrap();
+    assert_eq!(b.position, Some(2));
+    assert_eq!(b.plugin, None);
+    assert_eq!(b.ids.unwrap().id2, util::hash_name("GROUP"));
+
+    let report = extract::import_folder(&dir).unwrap();
+    assert!(report.warnings.is_empty());
+    assert!(report.edited.is_empty());
+}
+
+#[test]
+fn edited_and_stale_entries() {
+    let dir = scratch_dir("manifest_edited");
+    extract::extract_archive(&sample_archive(), &dir, false).unwrap();
+    let mut meta = read_meta(&dir);
+    let a_file = meta.files.iter().find(|f| f.id == "A.BIN").unwrap().file_name.clone();
+    fs::write(dir.join(a_file), [9, 9, 9]).unwrap();
+    meta.files.iter_mut().find(|f| f.id == "B.BIN").unwrap().id = "RENAMED.BIN".to_owned();
+    write_meta(&dir, &meta);
+
+    let report = extract::import_folder(&dir).unwrap();
+    assert_eq!(report.edited, vec!["A.BIN".to_owned()]);
+    assert_eq!(report.warnings, vec![ManifestWarning::StaleIds { entry: "RENAMED.BIN".to_owned() }]);
+    assert_eq!(report.archive.find("A.BIN").unwrap().data, vec![9, 9, 9]);
+    assert!(report.archive.find("RENAMED.BIN").is_some());
+}
+
+#[test]
+fn unknown_and_older_versions() {
+    let dir = scratch_dir("manifest_versions");
+    let archive = sample_archive();
+    extract::extract_archive(&archive, &dir, false).unwrap();
+    let mut meta = read_meta(&dir);
+    meta.version = Some(MANIFEST_VERSION + 1);
+    // Files are put back at their recorded position, whatever order they are listed in
+    meta.files.reverse();
+    write_meta(&dir, &meta);
+    let report = extract::import_folder(&dir).unwrap();
+    assert_eq!(report.warnings, vec![ManifestWarning::UnknownVersion(MANIFEST_VERSION + 1)]);
+    let ids = |archive: &ChumArchive| archive.dgc.iter_files().map(|f| f.key()).collect::<Vec<_>>();
+    assert_eq!(ids(&report.archive), ids(&archive));
+
+    // A version 1 manifest has none of the new fields
+    for file in &mut meta.files {
+        file.position = None;
+        file.ids = None;
+        file.file_size = None;
+        file.hash = None;
+        file.plugin = None;
+    }
+    meta.version = None;
+    meta.files.reverse();
+    write_meta(&dir, &meta);
+    assert!(!fs::read_to_string(dir.join("meta.json")).unwrap().contains("version"));
+    let report = extract::import_folder(&dir).unwrap();
+    assert!(report.warnings.is_empty());
+    assert!(report.edited.is_empty());
+    assert_eq!(ids(&report.archive), ids(&archive));
+
+    // Version 1 headers are not escaped, and are truncated rather than rejected
+    let mut header = String::from("C:\\x41");
+    while header.len() < 300 {
+        header.push('\u{FFFD}');
+    }
+    meta.header = header.clone();
+    write_meta(&dir, &meta);
+    let report = extract::import_folder(&dir).unwrap();
+    assert_eq!(report.archive.dgc.header.notice(), &header.as_bytes()[..0x100]);
+}
+
+#[test]
+fn invalid_chunk_sizes() {
+    let dir = scratch_dir("manifest_chunk_size");
+    extract::extract_archive(&sample_archive(), &dir, false).unwrap();
+    for &(size, keep_files) in &[(1000, true), (0, true), (0, false)] {
+        let mut meta = read_meta(&dir);
+        meta.chunk_size = Some(size);
+        if !keep_files {
+            meta.files.clear();
+        }
+        write_meta(&dir, &meta);
+        match extract::import_folder(&dir) {
+            Err(Error::Manifest { source: ManifestError::InvalidChunkSize(found), path }) => {
+                assert_eq!(found, size);
+                assert_eq!(path, Some(dir.join("meta.json")));
+            },
+            other => panic!("Expected an invalid chunk size, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    // Chunks with no entries left are dropped
+    let two_chunks = archive_with(0x800, &[
+        ("BIN", None, "A.BIN", &[1; 0x500]),
+        ("BIN", None, "B.BIN", &[2; 0x500]),
+    ]);
+    assert_eq!(two_chunks.dgc.data.len(), 2);
+    extract::extract_archive(&two_chunks, &dir, false).unwrap();
+    let mut meta = read_meta(&dir);
+    meta.files.retain(|f| f.id == "A.BIN");
+    write_meta(&dir, &meta);
+    let archive = extract::import_archive(&dir).unwrap();
+    assert_eq!(archive.dgc.data.len(), 1);
+    let mut data = Vec::new();
+    archive.dgc.write_to(&mut data).unwrap();
+    assert_eq!(DgcArchive::read_from(&mut &data[..]).unwrap().iter_files().count(), 1);
+}
+
+#[test]
+fn file_names_outside_folder() {
+    let dir = scratch_dir("manifest_outside");
+    let outside = scratch_dir("manifest_outside_target");
+    fs::write(outside.join("A.BIN"), [9]).unwrap();
+    extract::extract_archive(&sample_archive(), &dir, false).unwrap();
+    let outside_name = outside.join("A.BIN").display().to_string();
+    for file_name in &["../A.BIN", "BIN/../../A.BIN", outside_name.as_str()] {
+        let mut meta = read_meta(&dir);
+        meta.files[1].file_name = file_name.to_string();
+        write_meta(&dir, &meta);
+        for result in [extract::import_folder(&dir).map(|_| ()),
+                           extract::import_onto(sample_archive(), &dir).map(|_| ())] {
+            match result {
+                Err(Error::Manifest { source: ManifestError::OutsideFolder(found), path }) => {
+                    assert_eq!(found, *file_name);
+                    assert_eq!(path, Some(dir.join("meta.json")));
+                },
+                other => panic!("Expected a file name outside the folder, got {:?}", other),
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+extern crate chum_world;
+extern crate serde_json;
+
+mod common;
+
+use chum_world::error::Error;
+use chum_world::extract::{self, ExtractOptions, MergeConflict, MergePolicy};
+use chum_world::util::ChumArchive;
+use common::{archive_with, scratch_dir};
+use std::fs;
+use std::path::Path;
+
+/// Build an archive with two binary files with the given data.
+fn binary_archive(a: &[u8], b: &[u8]) -> ChumArchive {
+    archive_with(0, &[
+        ("BIN", None, "A.BIN", a),
+        ("BIN", None, "B.BIN", b),
+    ])
+}
+
+fn merge(archive: &ChumArchive, dir: &Path, policy: MergePolicy) -> Result<extract::ExtractReport, Error> {
+    let options = ExtractOptions {
+        merge: true,
+        policy,
+        ..ExtractOptions::default()
+    };
+    extract::extract_with_options(archive, dir, &options)
+}
+
+#[test]
+fn policy_names() {
+    for &policy in &MergePolicy::ALL {
+        assert_eq!(policy.name().parse(), Ok(policy));
+    }
+    assert_eq!(MergePolicy::default(), MergePolicy::Ours);
+    assert!("mine".parse::<MergePolicy>().is_err());
+}
+
+#[test]
+fn keep_local_edits_of_unchanged_entries() {
+    let dir = scratch_dir("merge_keep");
+    extract::extract_archive(&binary_archive(&[1], &[2]), &dir, false).unwrap();
+    fs::write(dir.join("A.BIN"), [9]).unwrap();
+
+    // A was only edited in the folder, and B only in the archive
+    let report = merge(&binary_archive(&[1], &[3]), &dir, MergePolicy::Ours).unwrap();
+    assert_eq!(report.kept, vec!["A.BIN".to_owned()]);
+    assert!(report.conflicts.is_empty());
+    assert_eq!(fs::read(dir.join("A.BIN")).unwrap(), vec![9]);
+    assert_eq!(fs::read(dir.join("B.BIN")).unwrap(), vec![3]);
+
+    // The edit is still found when the folder is imported
+    let imported = extract::import_folder(&dir).unwrap();
+    assert_eq!(imported.edited, vec!["A.BIN".to_owned()]);
+    assert_eq!(imported.archive.find("A.BIN").unwrap().data, vec![9]);
+}
+
+#[test]
+fn resolve_conflicts_by_policy() {
+    let dir = scratch_dir("merge_conflict");
+    let conflict = |overwritten| vec![MergeConflict {
+        entry: "A.BIN".to_owned(),
+        file_name: "A.BIN".to_owned(),
+        overwritten,
+    }];
+
+    extract::extract_archive(&binary_archive(&[1], &[2]), &dir, false).unwrap();
+    fs::write(dir.join("A.BIN"), [9]).unwrap();
+    let report = merge(&binary_archive(&[5], &[2]), &dir, MergePolicy::Ours).unwrap();
+    assert_eq!(report.conflicts, conflict(false));
+    assert_eq!(report.conflicts[0].to_string(), "A.BIN (A.BIN), kept");
+    assert!(report.kept.is_empty());
+    assert_eq!(fs::read(dir.join("A.BIN")).unwrap(), vec![9]);
+
+    let report = merge(&binary_archive(&[6], &[2]), &dir, MergePolicy::Theirs).unwrap();
+    assert_eq!(report.conflicts, conflict(true));
+    assert_eq!(fs::read(dir.join("A.BIN")).unwrap(), vec![6]);
+
+    // Failing leaves the folder as it was
+    fs::write(dir.join("A.BIN"), [9]).unwrap();
+    let meta = fs::read(dir.join("meta.json")).unwrap();
+    match merge(&binary_archive(&[7], &[8]), &dir, MergePolicy::Fail) {
+        Err(Error::MergeConflicts(conflicts)) => assert_eq!(conflicts, conflict(false)),
+        other => panic!("Expected a merge conflict, got {:?}", other.map(|_| ())),
+    }
+    assert_eq!(fs::read(dir.join("A.BIN")).unwrap(), vec![9]);
+    assert_eq!(fs::read(dir.join("B.BIN")).unwrap(), vec![2]);
+    assert_eq!(fs::read(dir.join("meta.json")).unwrap(), meta);
+}
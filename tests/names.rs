@@ -0,0 +1,87 @@
+extern crate chum_world;
+extern crate serde_json;
+
+mod common;
+
+use chum_world::dgc::{DgcArchive, DgcFile};
+use chum_world::extract;
+use chum_world::ngc::NgcArchive;
+use chum_world::util::{self, ChumArchive};
+use common::{header, scratch_dir};
+
+const UNNAMED: i32 = 0x1A2B3C4D;
+const UNNAMED_TYPE: i32 = -0x10;
+
+/// Build an archive where one file's name and another file's type are missing from the .NGC.
+fn partial_archive() -> ChumArchive {
+    let mut dgc = DgcArchive::new(&header(), 0);
+    let mut ngc = NgcArchive::new();
+    let named = ngc.insert("NAMED.BIN");
+    let bin = ngc.insert("BIN");
+    dgc.add_file(DgcFile {
+        data: vec![1, 2, 3],
+        type_id: bin,
+        id1: UNNAMED,
+        id2: UNNAMED,
+    });
+    dgc.add_file(DgcFile {
+        data: vec![4, 5, 6],
+        type_id: UNNAMED_TYPE,
+        id1: named,
+        id2: named,
+    });
+    ChumArchive {
+        dgc,
+        ngc,
+    }
+}
+
+#[test]
+fn placeholder_names() {
+    assert_eq!(util::placeholder_name(UNNAMED), "#1A2B3C4D");
+    assert_eq!(util::placeholder_name(-1), "#FFFFFFFF");
+    assert_eq!(util::parse_placeholder("#1A2B3C4D"), Some(UNNAMED));
+    assert_eq!(util::parse_placeholder("#1a2b3c4d"), Some(UNNAMED));
+    assert_eq!(util::parse_placeholder("#FFFFFFFF"), Some(-1));
+    assert_eq!(util::parse_placeholder("#1A2B3C4"), None);
+    assert_eq!(util::parse_placeholder("1A2B3C4DE"), None);
+    assert_eq!(util::parse_placeholder("#1A2B3C4G"), None);
+    assert_eq!(util::name_to_id("#1A2B3C4D"), UNNAMED);
+    assert_eq!(util::name_to_id("NAMED.BIN"), util::hash_name("NAMED.BIN"));
+}
+
+#[test]
+fn missing_ids_are_resolved_to_placeholders() {
+    let archive = partial_archive();
+    assert_eq!(archive.missing_ids(), vec![UNNAMED_TYPE, UNNAMED]);
+    assert_eq!(archive.ngc.resolve(UNNAMED), "#1A2B3C4D");
+    assert_eq!(archive.ngc.resolve(util::hash_name("BIN")), "BIN");
+    assert!(archive.find("#1A2B3C4D").is_some());
+
+    let mut ngc = NgcArchive::new();
+    assert_eq!(ngc.insert("#1A2B3C4D"), UNNAMED);
+    assert!(ngc.names.is_empty());
+}
+
+#[test]
+fn placeholders_survive_extract_and_pack() {
+    let dir = scratch_dir("placeholders");
+    let archive = partial_archive();
+    extract::extract_archive(&archive, &dir, false).unwrap();
+
+    let packed = extract::import_archive(&dir).unwrap();
+    let ids: Vec<(i32, i32, i32)> = packed.dgc.iter_files()
+        .map(|f| (f.type_id, f.id1, f.id2))
+        .collect();
+    let expected: Vec<(i32, i32, i32)> = archive.dgc.iter_files()
+        .map(|f| (f.type_id, f.id1, f.id2))
+        .collect();
+    assert_eq!(ids, expected);
+    assert_eq!(packed.missing_ids(), archive.missing_ids());
+
+    let mut names = Vec::new();
+    let mut expected = Vec::new();
+    packed.ngc.write_to(&mut names).unwrap();
+    archive.ngc.write_to(&mut expected).unwrap();
+    assert_eq!(String::from_utf8(names).unwrap(), String::from_utf8(expected).unwrap());
+}
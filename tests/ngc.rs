@@ -0,0 +1,95 @@
+extern crate chum_world;
+
+use chum_world::ngc::{NgcArchive, NgcErrorKind};
+use chum_world::util;
+
+fn line(id: i32, name: &str) -> Vec<u8> {
+    format!("{} \"{}\"\n", id, name).into_bytes()
+}
+
+fn write(ngc: &NgcArchive) -> Vec<u8> {
+    let mut data = Vec::new();
+    ngc.write_to(&mut data).unwrap();
+    data
+}
+
+#[test]
+fn unusual_lines_are_byte_exact() {
+    let quoted = "SAY \"HI\".TXT";
+    let raw_name: &[u8] = b"CAF\xC9.TXT";
+    let raw_id = util::hash_bytes(raw_name);
+    let mut data = Vec::new();
+    // Unsigned id, tab separator and a CRLF line ending
+    data.extend(format!("{}\t\"B.TXT\"\r\n", util::hash_name("B.TXT") as u32).into_bytes());
+    data.extend(b"\n\n");
+    data.extend(line(util::hash_name(quoted), quoted));
+    data.extend(format!("{}   \"", raw_id).into_bytes());
+    data.extend(raw_name);
+    data.extend(b"\"\n");
+    data.extend(line(util::hash_name("A.TXT"), "A.TXT"));
+    data.extend(b"\n\0\0\0\0");
+
+    let ngc = NgcArchive::read_from(&mut &data[..]).unwrap();
+    assert_eq!(ngc.lines.len(), 4);
+    assert_eq!(ngc.get(util::hash_name(quoted)), Some(quoted));
+    assert_eq!(ngc.get(raw_id), Some("CAF\u{FFFD}.TXT"));
+    assert_eq!(ngc.get_bytes(raw_id), Some(raw_name));
+    assert!(ngc.diagnostics().is_empty());
+    assert_eq!(ngc.padding, b"\n\0\0\0\0");
+    assert!(write(&ngc) == data);
+}
+
+#[test]
+fn padding_after_the_last_name() {
+    let mut data = line(util::hash_name("A.TXT"), "A.TXT");
+    data.extend(format!("{} \"B.TXT\"", util::hash_name("B.TXT")).into_bytes());
+    data.extend(b"\0\0\0\n\0");
+
+    let ngc = NgcArchive::read_from(&mut &data[..]).unwrap();
+    assert_eq!(ngc.lines.len(), 2);
+    assert_eq!(ngc.get(util::hash_name("B.TXT")), Some("B.TXT"));
+    assert_eq!(ngc.padding, b"\0\0\0\n\0");
+    assert!(write(&ngc) == data);
+}
+
+#[test]
+fn errors_have_line_numbers() {
+    let cases: Vec<(&[u8], usize)> = vec![
+        (b"1 \"A\"\n2\n", 2),
+        (b"1 \"A\"\n\nABC \"B\"\n", 3),
+        (b"1 B\n", 1),
+        (b"1 \"B\n", 1),
+        (b"1    \r\n", 1),
+    ];
+    for (data, line) in cases {
+        let err = NgcArchive::read_from(&mut &data[..]).err().unwrap();
+        assert_eq!(err.line, line);
+    }
+    let err = NgcArchive::read_from(&mut &b"12X \"A\"\n"[..]).err().unwrap();
+    match err.kind {
+        NgcErrorKind::InvalidId(ref text) => assert_eq!(text, "12X"),
+        ref kind => panic!("unexpected error {}", kind),
+    }
+    assert_eq!(err.to_string(), "'12X' is not a valid id (line 1)");
+}
+
+#[test]
+fn edits_keep_the_other_lines() {
+    let mut data = Vec::new();
+    data.extend(format!("{}  \"A.TXT\"\r\n", util::hash_name("A.TXT")).into_bytes());
+    data.extend(format!("{} \"B.TXT\"", util::hash_name("B.TXT")).into_bytes());
+    let mut ngc = NgcArchive::read_from(&mut &data[..]).unwrap();
+    assert!(ngc.padding.is_empty());
+
+    ngc.insert("C.TXT");
+    let mut expected = data.clone();
+    expected.push(b'\n');
+    expected.extend(line(util::hash_name("C.TXT"), "C.TXT"));
+    assert!(write(&ngc) == expected);
+
+    ngc.remove(util::hash_name("B.TXT"));
+    let first_line = data.iter().position(|&c| c == b'\n').unwrap() + 1;
+    let mut expected = data[..first_line].to_vec();
+    expected.extend(line(util::hash_name("C.TXT"), "C.TXT"));
+    assert!(write(&ngc) == expected);
+}
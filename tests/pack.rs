@@ -0,0 +1,152 @@
+extern crate chum_world;
+extern crate serde_json;
+
+mod common;
+
+use chum_world::dgc::{DgcArchive, DgcFile, PackError, PackOptions, PackStrategy};
+use chum_world::util;
+use common::header;
+
+/// Create a file whose total size, including its 16 byte header, is `size`.
+fn file(name: &str, size: usize) -> DgcFile {
+    DgcFile {
+        data: vec![0; size - 16],
+        type_id: util::hash_name("BIN"),
+        id1: util::hash_name(name),
+        id2: util::hash_name(name),
+    }
+}
+
+/// Build an archive by adding the given files in order.
+fn archive(files: &[(&str, usize)]) -> DgcArchive {
+    let mut dgc = DgcArchive::new(&header(), 0x800);
+    for &(name, size) in files {
+        dgc.add_file(file(name, size));
+    }
+    dgc
+}
+
+/// The names of the files in each chunk.
+fn layout(dgc: &DgcArchive) -> Vec<Vec<i32>> {
+    dgc.data.iter().map(|chunk| chunk.data.iter().map(|f| f.id1).collect()).collect()
+}
+
+fn ids(names: &[&str]) -> Vec<i32> {
+    names.iter().map(|n| util::hash_name(n)).collect()
+}
+
+const FILES: [(&str, usize); 5] = [
+    ("A", 0x1F0),
+    ("B", 0x600),
+    ("C", 0x2F0),
+    ("D", 0x500),
+    ("E", 0x100),
+];
+
+#[test]
+fn first_fit_decreasing() {
+    let mut dgc = archive(&FILES);
+    dgc.pack(&PackOptions::default()).unwrap();
+    assert_eq!(dgc.chunk_size, 0x800);
+    assert_eq!(layout(&dgc), vec![
+        ids(&["B", "A"]),
+        ids(&["D", "C"]),
+        ids(&["E"]),
+    ]);
+}
+
+#[test]
+fn best_fit() {
+    let mut dgc = archive(&FILES);
+    dgc.pack(&PackOptions {
+        strategy: PackStrategy::BestFit,
+        ..PackOptions::default()
+    }).unwrap();
+    assert_eq!(layout(&dgc), vec![
+        ids(&["B", "A"]),
+        ids(&["D", "C"]),
+        ids(&["E"]),
+    ]);
+    for chunk in &dgc.data {
+        assert!(chunk.get_size() <= dgc.chunk_size);
+    }
+}
+
+#[test]
+fn preserve_order() {
+    let mut dgc = archive(&FILES);
+    dgc.pack(&PackOptions {
+        strategy: PackStrategy::PreserveOrder,
+        ..PackOptions::default()
+    }).unwrap();
+    assert_eq!(layout(&dgc), vec![
+        ids(&["A", "B"]),
+        ids(&["C", "D"]),
+        ids(&["E"]),
+    ]);
+}
+
+#[test]
+fn explicit_chunk_size() {
+    let mut dgc = archive(&FILES);
+    dgc.pack(&PackOptions {
+        chunk_size: Some(0x1000),
+        ..PackOptions::default()
+    }).unwrap();
+    assert_eq!(dgc.chunk_size, 0x1000);
+    assert_eq!(layout(&dgc), vec![
+        ids(&["B", "D", "C", "A"]),
+        ids(&["E"]),
+    ]);
+}
+
+#[test]
+fn invalid_chunk_size_leaves_archive_unchanged() {
+    let mut dgc = archive(&FILES);
+    dgc.add_file(file("BIG", 0x1000));
+    let before = layout(&dgc);
+    match dgc.pack(&PackOptions { chunk_size: Some(0x900), ..PackOptions::default() }) {
+        Err(PackError::UnalignedChunkSize(0x900)) => {},
+        other => panic!("unexpected result {:?}", other),
+    }
+    match dgc.pack(&PackOptions { chunk_size: Some(0x800), ..PackOptions::default() }) {
+        Err(PackError::FileTooLarge { size: 0x1000, chunk_size: 0x800 }) => {},
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert_eq!(layout(&dgc), before);
+    assert_eq!(dgc.chunk_size, 0x1800);
+}
+
+#[test]
+fn max_chunks_picks_smallest_chunk_size() {
+    let mut dgc = archive(&[("A", 0x7F0), ("B", 0x7F0), ("C", 0x7F0), ("D", 0x7F0)]);
+    assert_eq!(dgc.data.len(), 4);
+    dgc.pack(&PackOptions { max_chunks: Some(2), ..PackOptions::default() }).unwrap();
+    assert_eq!(dgc.chunk_size, 0x1000);
+    assert_eq!(dgc.data.len(), 2);
+
+    dgc.pack(&PackOptions { max_chunks: Some(1), ..PackOptions::default() }).unwrap();
+    assert_eq!(dgc.chunk_size, 0x2000);
+    assert_eq!(dgc.data.len(), 1);
+
+    match dgc.pack(&PackOptions { max_chunks: Some(0), ..PackOptions::default() }) {
+        Err(PackError::TooManyChunks { chunks: 1, max_chunks: 0 }) => {},
+        other => panic!("unexpected result {:?}", other),
+    }
+    match dgc.pack(&PackOptions {
+        chunk_size: Some(0x800),
+        max_chunks: Some(3),
+        ..PackOptions::default()
+    }) {
+        Err(PackError::TooManyChunks { chunks: 4, max_chunks: 3 }) => {},
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn strategy_names() {
+    for &strategy in &PackStrategy::ALL {
+        assert_eq!(strategy.name().parse::<PackStrategy>().unwrap(), strategy);
+    }
+    assert!("worst-fit".parse::<PackStrategy>().is_err());
+}
@@ -0,0 +1,62 @@
+extern crate chum_world;
+extern crate serde_json;
+
+mod common;
+
+use chum_world::util::{ArchivePathPair, ChumArchive};
+use common::{archive_with, scratch_dir};
+use std::fs;
+use std::path::Path;
+
+fn single_file_archive() -> ChumArchive {
+    archive_with(0, &[("BIN", None, "A.BIN", &[1, 2, 3])])
+}
+
+#[test]
+fn resolve_new_files() {
+    let pair = ArchivePathPair::resolve(Path::new("missing/level.dgc"));
+    assert_eq!(pair.d, Path::new("missing/level.dgc"));
+    assert_eq!(pair.n, Path::new("missing/level.ngc"));
+    let pair = ArchivePathPair::resolve(Path::new("missing/LEVEL.NGC"));
+    assert_eq!(pair.d, Path::new("missing/LEVEL.DGC"));
+    let pair = ArchivePathPair::resolve(Path::new("missing/LEVEL"));
+    assert_eq!(pair.n, Path::new("missing/LEVEL.NGC"));
+}
+
+#[test]
+fn resolve_existing_files_ignoring_case() {
+    let dir = scratch_dir("paths_case");
+    let pair = ArchivePathPair {
+        d: dir.join("Level.dgc"),
+        n: dir.join("LEVEL.Ngc"),
+    };
+    single_file_archive().save_pair(&pair).unwrap();
+
+    assert_eq!(ArchivePathPair::resolve(&dir.join("Level.dgc")), pair);
+    assert_eq!(ArchivePathPair::resolve(&dir.join("level.NGC")), pair);
+    let loaded = ChumArchive::load(&dir.join("LEVEL.DGC")).unwrap();
+    assert!(loaded.find("A.BIN").is_some());
+}
+
+#[test]
+fn explicit_paths() {
+    let dir = scratch_dir("paths_explicit");
+    let pair = ArchivePathPair {
+        d: dir.join("data").join("ARCHIVE.DGC"),
+        n: dir.join("names").join("OTHER.NGC"),
+    };
+    fs::create_dir_all(pair.d.parent().unwrap()).unwrap();
+    fs::create_dir_all(pair.n.parent().unwrap()).unwrap();
+    single_file_archive().save_pair(&pair).unwrap();
+
+    let parts = ArchivePathPair::from_parts(None, Some(&pair.d), Some(&pair.n)).unwrap();
+    assert_eq!(parts, pair);
+    let parts = ArchivePathPair::from_parts(Some(&pair.d), None, Some(&pair.n)).unwrap();
+    assert_eq!(parts, pair);
+    let parts = ArchivePathPair::from_parts(None, Some(&pair.d), None).unwrap();
+    assert_eq!(parts.n, pair.d.with_extension("NGC"));
+    assert!(ArchivePathPair::from_parts(None, None, None).is_err());
+
+    let loaded = ChumArchive::load_pair(&pair).unwrap();
+    assert!(loaded.find("A.BIN").is_some());
+}
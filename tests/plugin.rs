@@ -0,0 +1,62 @@
+extern crate chum_world;
+
+use chum_world::plugin::{CodecPlugin, PluginManager};
+use chum_world::CResult;
+use std::io::{Read, Write};
+
+/// Codec that stores data reversed inside the archive.
+struct CodecReverse;
+
+impl CodecPlugin for CodecReverse {
+    fn import_data(&self, input: &mut dyn Read, output: &mut dyn Write) -> CResult<()> {
+        let mut data = Vec::new();
+        input.read_to_end(&mut data)?;
+        data.reverse();
+        output.write_all(&data)?;
+        Ok(())
+    }
+
+    fn export_data(&self, input: &mut dyn Read, output: &mut dyn Write) -> CResult<()> {
+        self.import_data(input, output)
+    }
+
+    fn get_file_extension(&self) -> Option<&'static str> {
+        Some("rev")
+    }
+
+    fn get_plugin_string(&self) -> &'static str {
+        "reverse"
+    }
+}
+
+#[test]
+fn builtin_codecs_are_registered() {
+    let plugins = PluginManager::new();
+    assert!(plugins.get_codec("TXT").is_some());
+    assert_eq!(plugins.get_file_extension("TXT"), Some("txt"));
+    assert!(plugins.get_codec("BITMAP").is_none());
+    assert_eq!(plugins.get_file_extension("BITMAP"), None);
+}
+
+#[test]
+fn custom_codec() {
+    let mut plugins = PluginManager::new();
+    let name = plugins.register_plugin(Box::new(CodecReverse));
+    plugins.register_for_type(name, "REV");
+    assert_eq!(plugins.get_file_extension("REV"), Some("rev"));
+
+    let mut exported = Vec::new();
+    plugins.export("REV", &mut &b"abc"[..], &mut exported).unwrap();
+    assert_eq!(exported, b"cba");
+}
+
+#[test]
+fn unknown_types_pass_through() {
+    let plugins = PluginManager::new();
+    let mut exported = Vec::new();
+    plugins.export("BITMAP", &mut &b"\x00\x01\x02"[..], &mut exported).unwrap();
+    assert_eq!(exported, b"\x00\x01\x02");
+    let mut imported = Vec::new();
+    plugins.import("BITMAP", &mut &exported[..], &mut imported).unwrap();
+    assert_eq!(imported, b"\x00\x01\x02");
+}
@@ -0,0 +1,150 @@
+extern crate chum_world;
+extern crate serde_json;
+
+mod common;
+
+use chum_world::dgc::{DgcArchive, DgcError, DgcErrorKind, DgcFile, DgcReader};
+use common::header;
+use std::io::Cursor;
+
+/// Build an archive with enough files to span several chunks.
+fn multi_chunk_data() -> Vec<u8> {
+    let mut dgc = DgcArchive::new(&header(), 0);
+    for i in 0..40 {
+        dgc.add_file(DgcFile {
+            data: vec![i as u8; 100 + i * 10],
+            type_id: 1,
+            id1: i as i32,
+            id2: i as i32,
+        });
+    }
+    let mut data = Vec::new();
+    dgc.write_to(&mut data).unwrap();
+    data
+}
+
+/// Read the given archive with both DgcReader and DgcArchive, expecting both
+/// to fail.
+fn read_errors(data: &[u8]) -> Vec<DgcError> {
+    vec![DgcReader::new(Cursor::new(data)).err().unwrap(),
+         DgcArchive::read_from(&mut &data[..]).err().unwrap()]
+}
+
+#[test]
+fn index_matches_full_read() {
+    let data = multi_chunk_data();
+    let full = DgcArchive::read_from(&mut &data[..]).unwrap();
+    let mut reader = DgcReader::new(Cursor::new(&data[..])).unwrap();
+    assert!(reader.chunks.len() > 1);
+    assert_eq!(reader.chunk_size, full.chunk_size);
+    assert_eq!(reader.chunks.len(), full.data.len());
+
+    let headers: Vec<_> = reader.iter_files().cloned().collect();
+    assert_eq!(headers.len(), full.iter_files().count());
+    for (header, file) in headers.iter().zip(full.iter_files()) {
+        assert_eq!(header.id1, file.id1);
+        assert_eq!(header.data_size, file.data.len());
+        assert_eq!(reader.get_data(header), &file.data[..]);
+        assert_eq!(reader.read_data(header).unwrap(), file.data);
+    }
+}
+
+#[test]
+fn into_archive_loads_everything() {
+    let data = multi_chunk_data();
+    let reader = DgcReader::new(Cursor::new(&data[..])).unwrap();
+    let archive = reader.into_archive().unwrap();
+    let mut written = Vec::new();
+    archive.write_to(&mut written).unwrap();
+    assert_eq!(written, data);
+}
+
+#[test]
+fn file_past_end_of_chunk() {
+    let mut data = multi_chunk_data();
+    // Make the second file of the first chunk claim to be larger than the chunk
+    let second = 0x804 + 16 + 100;
+    data[second..second + 4].copy_from_slice(&[0, 1, 0, 0]);
+    for err in read_errors(&data) {
+        match err.kind {
+            DgcErrorKind::FileOutOfBounds { size, .. } => assert_eq!(size, 0x10000 - 16),
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+        assert_eq!(err.offset, second as u64);
+        assert_eq!(err.chunk, Some(0));
+        assert_eq!(err.file, Some(1));
+    }
+}
+
+#[test]
+fn file_smaller_than_header() {
+    let mut data = multi_chunk_data();
+    data[0x804..0x808].copy_from_slice(&[0, 0, 0, 4]);
+    for err in read_errors(&data) {
+        match err.kind {
+            DgcErrorKind::FileTooSmall(4) => {},
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+        assert_eq!(err.offset, 0x804);
+        assert_eq!(err.chunk, Some(0));
+        assert_eq!(err.file, Some(0));
+    }
+}
+
+#[test]
+fn partial_chunk() {
+    let mut data = multi_chunk_data();
+    let len = data.len();
+    data.truncate(len - 0x10);
+    for err in read_errors(&data) {
+        match err.kind {
+            DgcErrorKind::PartialChunk(size) => assert_eq!(size, 0x800 - 0x10),
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+        assert_eq!(err.offset, (len - 0x800) as u64);
+    }
+}
+
+#[test]
+fn zero_chunk_size() {
+    let mut data = multi_chunk_data();
+    data[0x100..0x104].copy_from_slice(&[0, 0, 0, 0]);
+    for err in read_errors(&data) {
+        match err.kind {
+            DgcErrorKind::ZeroChunkSize => {},
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+    }
+}
+
+#[test]
+fn invalid_chunk_size() {
+    let mut data = multi_chunk_data();
+    data[0x100..0x104].copy_from_slice(&[0x01, 0, 0x08, 0x01]);
+    for err in read_errors(&data) {
+        match err.kind {
+            DgcErrorKind::UnalignedChunkSize(0x01000801) => {},
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+        assert_eq!(err.offset, 0x100);
+    }
+    // A chunk size of 4 GiB is not allocated before the archive's data is read
+    data[0x100..0x104].copy_from_slice(&[0xFF, 0xFF, 0xF8, 0]);
+    for err in read_errors(&data) {
+        match err.kind {
+            DgcErrorKind::PartialChunk(_) => {},
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+    }
+}
+
+#[test]
+fn truncated_header() {
+    let data = multi_chunk_data();
+    let err = DgcArchive::read_from(&mut &data[..0x200]).err().unwrap();
+    match err.kind {
+        DgcErrorKind::TruncatedHeader => {},
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+    assert_eq!(err.to_string(), "archive header is truncated (offset 0x200)");
+}
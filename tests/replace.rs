@@ -0,0 +1,75 @@
+extern crate chum_world;
+extern crate serde_json;
+
+mod common;
+
+use chum_world::extract::{self, ExtractLayout, ExtractOptions};
+use common::{sample_archive, scratch_dir};
+use std::fs;
+use std::path::PathBuf;
+
+#[test]
+fn only_listed_files_are_removed() {
+    let dir = scratch_dir("replace_listed");
+    let options = ExtractOptions {
+        layout: ExtractLayout::ByType,
+        ..ExtractOptions::default()
+    };
+    extract::extract_with_options(&sample_archive(), &dir, &options).unwrap();
+    // Files that the user put into the folder
+    fs::write(dir.join("notes.md"), "my notes").unwrap();
+    fs::write(dir.join("BIN").join("script.py"), "print()").unwrap();
+
+    let files = extract::listed_files(&dir).unwrap();
+    let relative: Vec<PathBuf> = files.iter().map(|f| f.strip_prefix(&dir).unwrap().to_owned()).collect();
+    assert_eq!(relative, vec![
+        PathBuf::from("BIN/A.BIN"),
+        PathBuf::from("BIN/GROUP/B.BIN"),
+        PathBuf::from("TXT/NOTE.TXT"),
+        PathBuf::from("meta.json"),
+    ]);
+
+    extract::remove_listed_files(&dir, &files).unwrap();
+    assert!(files.iter().all(|f| !f.exists()));
+    assert_eq!(fs::read_to_string(dir.join("notes.md")).unwrap(), "my notes");
+    assert_eq!(fs::read_to_string(dir.join("BIN").join("script.py")).unwrap(), "print()");
+    // Folders are only removed once they are empty
+    assert!(!dir.join("TXT").exists());
+    assert!(!dir.join("BIN").join("GROUP").exists());
+    assert!(dir.join("BIN").exists());
+}
+
+#[test]
+fn paths_outside_of_the_folder_are_not_listed() {
+    let dir = scratch_dir("replace_outside");
+    let folder = dir.join("extracted");
+    fs::create_dir_all(&folder).unwrap();
+    fs::write(dir.join("OUTSIDE.BIN"), [1]).unwrap();
+    fs::write(folder.join("A.BIN"), [2]).unwrap();
+    fs::write(folder.join("meta.json"), r#"{
+        "header": "",
+        "files": [
+            {"id": "A.BIN", "type_id": "BIN", "file_name": "A.BIN"},
+            {"id": "OUTSIDE.BIN", "type_id": "BIN", "file_name": "../OUTSIDE.BIN"},
+            {"id": "GONE.BIN", "type_id": "BIN", "file_name": "GONE.BIN"}
+        ]
+    }"#).unwrap();
+    let files = extract::listed_files(&folder).unwrap();
+    assert_eq!(files, vec![folder.join("A.BIN"), folder.join("meta.json")]);
+    extract::remove_listed_files(&folder, &files).unwrap();
+    assert!(dir.join("OUTSIDE.BIN").exists());
+}
+
+#[test]
+fn dry_run_does_not_touch_disk() {
+    let dir = scratch_dir("replace_dry_run");
+    let folder = dir.join("fresh");
+    let options = ExtractOptions {
+        layout: ExtractLayout::ByType,
+        dry_run: true,
+        ..ExtractOptions::default()
+    };
+    let report = extract::extract_with_options(&sample_archive(), &folder, &options).unwrap();
+    assert_eq!(report.written, vec!["TXT/NOTE.TXT", "BIN/A.BIN", "BIN/GROUP/B.BIN"]);
+    assert!(!folder.exists());
+}
@@ -0,0 +1,166 @@
+extern crate chum_world;
+extern crate serde_json;
+
+mod common;
+
+use chum_world::dgc::{DgcArchive, DgcChunk, DgcFile};
+use chum_world::extract;
+use chum_world::ngc::NgcArchive;
+use chum_world::util::{self, ChumArchive};
+use common::scratch_dir;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+fn read_file(path: &Path) -> Vec<u8> {
+    let mut data = Vec::new();
+    File::open(path).unwrap().read_to_end(&mut data).unwrap();
+    data
+}
+
+fn file(type_name: &str, name: &str, data: Vec<u8>) -> DgcFile {
+    DgcFile {
+        data,
+        type_id: util::hash_name(type_name),
+        id1: util::hash_name(name),
+        id2: util::hash_name(name),
+    }
+}
+
+fn txt(text: &[u8]) -> Vec<u8> {
+    let mut data = vec![0, 0, (text.len() >> 8) as u8, text.len() as u8];
+    data.extend_from_slice(text);
+    data
+}
+
+/// Build an archive the way the game's tools might have: files are not in
+/// first-fit order, and the header and chunk padding contain junk.
+fn retail_archive() -> ChumArchive {
+    let mut header = String::from("Retail archive");
+    while header.len() < 0x100 {
+        header.push('\0');
+    }
+    let mut dgc = DgcArchive::new(&header, 0x800);
+    dgc.header.padding = vec![0xCD; 0x6FC];
+    let mut chunk0 = DgcChunk::new();
+    chunk0.add_file(file("BITMAP", "BIG.BMP", vec![7; 0x600]));
+    chunk0.padding = vec![0xAB, 0xCD];
+    let mut chunk1 = DgcChunk::new();
+    chunk1.add_file(file("TXT", "B.TXT", txt(&[b'b'; 0xFC])));
+    chunk1.add_file(file("TXT", "C.TXT", txt(&[b'c'; 0xFC])));
+    dgc.data.push(chunk0);
+    dgc.data.push(chunk1);
+
+    let mut ngc = NgcArchive::new();
+    for name in &["TXT", "C.TXT", "BITMAP", "BIG.BMP", "B.TXT"] {
+        ngc.push(name);
+    }
+    ngc.padding = vec![0; 0x40];
+    ChumArchive {
+        dgc,
+        ngc,
+    }
+}
+
+#[test]
+fn unmodified_archive_is_byte_identical() {
+    let dir = scratch_dir("roundtrip_identical");
+    let original = dir.join("ORIGINAL.DGC");
+    retail_archive().save(&original).unwrap();
+
+    let archive = ChumArchive::load(&original).unwrap();
+    extract::extract_archive(&archive, &dir.join("extracted"), false).unwrap();
+    let packed = dir.join("PACKED.DGC");
+    extract::import_archive(&dir.join("extracted")).unwrap().save(&packed).unwrap();
+
+    assert!(read_file(&original) == read_file(&packed));
+    assert_eq!(read_file(&original.with_extension("NGC")), read_file(&packed.with_extension("NGC")));
+
+}
+
+#[test]
+fn irregular_name_lines_are_byte_identical() {
+    let dir = scratch_dir("roundtrip_names");
+    let original = dir.join("ORIGINAL.DGC");
+    retail_archive().save(&original).unwrap();
+    // CRLF line endings, unsigned ids, a blank line, an id that is not the hash of its name and a
+    // last line without a line ending
+    let mut names = Vec::new();
+    write!(names, "{} \"TXT\"\r\n", util::hash_name("TXT")).unwrap();
+    for name in &["C.TXT", "BITMAP", "BIG.BMP"] {
+        writeln!(names, "{} \"{}\"", util::hash_name(name) as u32, name).unwrap();
+    }
+    write!(names, "\n12345 \"ALIAS\"\n{} \"B.TXT\"", util::hash_name("B.TXT")).unwrap();
+    File::create(original.with_extension("NGC")).unwrap().write_all(&names).unwrap();
+
+    let archive = ChumArchive::load(&original).unwrap();
+    extract::extract_archive(&archive, &dir.join("extracted"), false).unwrap();
+    let packed = dir.join("PACKED.DGC");
+    extract::import_archive(&dir.join("extracted")).unwrap().save(&packed).unwrap();
+
+    assert_eq!(read_file(&packed.with_extension("NGC")), names);
+    assert!(read_file(&original) == read_file(&packed));
+}
+
+#[test]
+fn entries_keep_ids_that_are_not_their_hash() {
+    let dir = scratch_dir("roundtrip_raw_ids");
+    let original = dir.join("ORIGINAL.DGC");
+    let bad = util::hash_bytes(b"\xFFBAD.TXT");
+    let mut archive = retail_archive();
+    archive.dgc.data[1].data[0].id1 = bad;
+    archive.dgc.data[1].data[0].id2 = bad;
+    archive.dgc.data[1].data[1].id1 = 777;
+    archive.dgc.data[1].data[1].id2 = 777;
+    archive.save(&original).unwrap();
+    let mut names = Vec::new();
+    for name in &["TXT", "BITMAP", "BIG.BMP"] {
+        writeln!(names, "{} \"{}\"", util::hash_name(name), name).unwrap();
+    }
+    write!(names, "{} \"", bad).unwrap();
+    names.extend_from_slice(b"\xFFBAD.TXT\"\n777 \"ODD.TXT\"\n");
+    File::create(original.with_extension("NGC")).unwrap().write_all(&names).unwrap();
+
+    let archive = ChumArchive::load(&original).unwrap();
+    let extracted = dir.join("extracted");
+    extract::extract_archive(&archive, &extracted, false).unwrap();
+    let packed = dir.join("PACKED.DGC");
+    let report = extract::import_folder(&extracted).unwrap();
+    assert!(report.warnings.is_empty());
+    report.archive.save(&packed).unwrap();
+    assert!(read_file(&original) == read_file(&packed));
+    assert_eq!(read_file(&packed.with_extension("NGC")), names);
+
+    // Applying the folder onto the archive and merging into it find the same entries
+    let applied = extract::import_onto(ChumArchive::load(&original).unwrap(), &extracted).unwrap();
+    let keys = |archive: &ChumArchive| archive.dgc.iter_files().map(|f| f.key()).collect::<Vec<_>>();
+    assert_eq!(keys(&applied.archive), keys(&archive));
+    let merged = extract::extract_archive(&archive, &extracted, true).unwrap();
+    assert!(merged.written.len() == 3 && merged.conflicts.is_empty());
+    assert_eq!(extract::read_manifest(&extracted).unwrap().files.len(), 3);
+}
+
+#[test]
+fn modified_file_only_touches_its_chunk() {
+    let dir = scratch_dir("roundtrip_modified");
+    let original = dir.join("ORIGINAL.DGC");
+    retail_archive().save(&original).unwrap();
+
+    let archive = ChumArchive::load(&original).unwrap();
+    let extracted = dir.join("extracted");
+    extract::extract_archive(&archive, &extracted, false).unwrap();
+    let path = extracted.join(util::get_file_string("B.TXT", Some("txt"), 1));
+    File::create(&path).unwrap().write_all(&[b'x'; 0x700]).unwrap();
+    let packed = dir.join("PACKED.DGC");
+    extract::import_archive(&extracted).unwrap().save(&packed).unwrap();
+
+    let original = read_file(&original);
+    let packed = read_file(&packed);
+    // Header and the first chunk are unchanged, B.TXT has moved to a new chunk
+    assert!(original[..0x1000] == packed[..0x1000]);
+    assert_eq!(packed.len(), 0x800 * 4);
+    let archive = DgcArchive::read_from(&mut &packed[..]).unwrap();
+    assert_eq!(archive.data[1].data.len(), 1);
+    assert_eq!(archive.data[2].data[0].id1, util::hash_name("B.TXT"));
+
+}
@@ -0,0 +1,62 @@
+extern crate chum_world;
+extern crate serde_json;
+
+mod common;
+
+use chum_world::extract;
+use chum_world::util::{self, ChumArchive};
+use common::{archive_with, read_meta, scratch_dir};
+use std::fs;
+
+fn grouped_archive() -> ChumArchive {
+    archive_with(0, &[
+        ("BIN", None, "PLAIN.BIN", &[1]),
+        ("BIN", Some("GROUP"), "GROUPED.BIN", &[2]),
+    ])
+}
+
+#[test]
+fn resolve_subtype() {
+    let archive = grouped_archive();
+    let plain = archive.find("PLAIN.BIN").unwrap();
+    assert_eq!(archive.ngc.resolve_subtype(plain.id1, plain.id2), None);
+    let grouped = archive.find("GROUPED.BIN").unwrap();
+    assert_eq!(archive.ngc.resolve_subtype(grouped.id1, grouped.id2), Some("GROUP".to_owned()));
+    // An unnamed subtype is still a subtype
+    assert_eq!(archive.ngc.resolve_subtype(grouped.id1, 0x1234),
+               Some(util::placeholder_name(0x1234)));
+}
+
+#[test]
+fn manifest_subtypes() {
+    let dir = scratch_dir("subtype_manifest");
+    let archive = grouped_archive();
+    extract::extract_archive(&archive, &dir, false).unwrap();
+    let meta = read_meta(&dir);
+    let subtype = |name: &str| meta.files.iter().find(|f| f.id == name).unwrap().subtype_id.clone();
+    assert_eq!(subtype("PLAIN.BIN"), None);
+    assert_eq!(subtype("GROUPED.BIN"), Some("GROUP".to_owned()));
+    assert!(!fs::read_to_string(dir.join("meta.json")).unwrap().contains("\"subtype_id\": \"PLAIN.BIN\""));
+
+    let imported = extract::import_archive(&dir).unwrap();
+    for file in archive.dgc.iter_files() {
+        let other = imported.dgc.get_file(file.id1).unwrap();
+        assert_eq!((other.type_id, other.id2), (file.type_id, file.id2));
+    }
+}
+
+#[test]
+fn older_manifests_name_the_file_itself() {
+    let dir = scratch_dir("subtype_old_manifest");
+    fs::write(dir.join("A.BIN"), [1, 2]).unwrap();
+    fs::write(dir.join("meta.json"), r#"{
+        "header": "",
+        "files": [
+            {"id": "A.BIN", "type_id": "BIN", "subtype_id": "A.BIN", "file_name": "A.BIN"}
+        ]
+    }"#).unwrap();
+    let imported = extract::import_archive(&dir).unwrap();
+    let file = imported.find("A.BIN").unwrap();
+    assert_eq!(file.id2, file.id1);
+    assert_eq!(imported.ngc.resolve_subtype(file.id1, file.id2), None);
+}